@@ -0,0 +1,105 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use neuro_symbolic_emulator::boot::BootSector;
+use neuro_symbolic_emulator::bus::Bundle;
+use neuro_symbolic_emulator::fu::{DiskImage, SECTOR_SIZE};
+
+#[derive(Parser)]
+#[command(name = "disk_image")]
+#[command(about = "Create and inspect disk images for the eMMC block device", long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Create a zero-filled image
+    Create {
+        path: PathBuf,
+        #[arg(long, default_value_t = 64)]
+        sectors: usize,
+    },
+    /// Show the size of an image and which sectors hold data
+    Info {
+        path: PathBuf,
+    },
+    /// Hex dump of one sector
+    Dump {
+        path: PathBuf,
+        #[arg(long, default_value_t = 0)]
+        sector: usize,
+    },
+    /// Copy a host file into the image, starting at a sector
+    Write {
+        path: PathBuf,
+        file: PathBuf,
+        #[arg(long, default_value_t = 0)]
+        sector: usize,
+    },
+    /// Write a JSON program (as loaded from `program_path`) into sector 0 as the boot program
+    Boot {
+        path: PathBuf,
+        program: PathBuf,
+        /// Bundle the ROM stage jumps to
+        #[arg(long, default_value_t = 0)]
+        entry: usize,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Create { path, sectors } => {
+            DiskImage::create(&path, sectors)?;
+            println!("Created {:?}: {} sectors ({} bytes)", path, sectors, sectors * SECTOR_SIZE);
+        }
+        Commands::Info { path } => {
+            let mut image = DiskImage::open(&path)?;
+            println!("{:?}: {} sectors ({} bytes)", path, image.sectors, image.sectors * SECTOR_SIZE);
+            let mut used = Vec::new();
+            for sector in 0..image.sectors {
+                if image.read_sector(sector)?.iter().any(|&b| b != 0) {
+                    used.push(sector);
+                }
+            }
+            println!("Non-empty sectors: {} {:?}", used.len(), used);
+            match BootSector::decode(&image.read_sector(0)?) {
+                Ok(boot) => println!("Bootable: {} bundles, entry {}", boot.program.len(), boot.entry),
+                Err(e) => println!("Not bootable: {}", e),
+            }
+        }
+        Commands::Dump { path, sector } => {
+            let mut image = DiskImage::open(&path)?;
+            for (row, chunk) in image.read_sector(sector)?.chunks(16).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+                let ascii: String = chunk.iter()
+                    .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                    .collect();
+                println!("{:04X}  {}  {}", row * 16, hex.join(" "), ascii);
+            }
+        }
+        Commands::Write { path, file, sector } => {
+            let mut image = DiskImage::open(&path)?;
+            let data = std::fs::read(&file)?;
+            let needed = data.len().div_ceil(SECTOR_SIZE);
+            if sector + needed > image.sectors {
+                anyhow::bail!("{} bytes need {} sectors from {}, image has {}", data.len(), needed, sector, image.sectors);
+            }
+            for (i, chunk) in data.chunks(SECTOR_SIZE).enumerate() {
+                image.write_sector(sector + i, chunk)?;
+            }
+            println!("Wrote {} bytes to sectors {}..{}", data.len(), sector, sector + needed);
+        }
+        Commands::Boot { path, program, entry } => {
+            let mut image = DiskImage::open(&path)?;
+            let program: Vec<Bundle> = serde_json::from_reader(std::fs::File::open(&program)?)?;
+            let bundles = program.len();
+            image.write_sector(0, &BootSector { program, entry }.encode()?)?;
+            println!("Wrote boot sector: {} bundles, entry {}", bundles, entry);
+        }
+    }
+
+    Ok(())
+}
@@ -1,9 +1,12 @@
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::path::Path;
-use neuro_symbolic_emulator::fu::{BaseFU, NeuralFunctionalUnit, ProgramCounterFU};
-use ndarray::{Array1, Array2};
+use std::fs;
+use std::path::{Path, PathBuf};
+use neuro_symbolic_emulator::fu::{BaseFU, FUType, NeuralFunctionalUnit, WeightFile};
+use neuro_symbolic_emulator::fu::nfn::{NfnFile, Precision};
+use neuro_symbolic_emulator::fu::quant::QuantizedFU;
+use neuro_symbolic_emulator::fu::weights::{content_hash, TrainingInfo, VerificationInfo};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 #[derive(Parser)]
 #[command(name = "manage_fus")]
@@ -20,10 +23,15 @@ enum Commands {
         name: String,
         #[arg(value_enum)]
         type_: FUType,
+        /// RNG seed for initialisation and sampling (random if omitted)
+        #[arg(long)]
+        seed: Option<u64>,
     },
     /// Batch train FUs from a manifest
     BatchTrain {
         manifest: String,
+        #[arg(long)]
+        seed: Option<u64>,
     },
     /// Verify a trained FU
     Verify {
@@ -31,14 +39,23 @@ enum Commands {
     },
     /// List all trained FUs
     List,
-}
-
-#[derive(clap::ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq)]
-enum FUType {
-    ADDER,
-    CMP,
-    BITWISE,
-    PC,
+    /// Convert a weight file between JSON and binary `.nfn` (chosen by output extension)
+    Convert {
+        input: String,
+        output: String,
+        /// Tensor precision for `.nfn` output
+        #[arg(long, value_enum, default_value = "f32")]
+        precision: Precision,
+    },
+    /// Quantize a trained FU and report exhaustive accuracy before and after
+    Quantize {
+        name: String,
+        #[arg(long, value_enum, default_value = "int8")]
+        precision: Precision,
+        /// Also write the quantized unit as `.nfn`
+        #[arg(long)]
+        output: Option<String>,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -61,18 +78,19 @@ fn main() -> anyhow::Result<()> {
     std::fs::create_dir_all(assets_dir)?;
 
     match cli.command {
-        Commands::Train { name, type_ } => {
+        Commands::Train { name, type_, seed } => {
             println!("Training {} ({:?})...", name, type_);
-            train_fu(&name, type_, assets_dir)?;
+            train_fu(&name, type_, seed.unwrap_or_else(rand::random), assets_dir)?;
         }
-        Commands::BatchTrain { manifest } => {
+        Commands::BatchTrain { manifest, seed } => {
             println!("Batch training from manifest: {}", manifest);
             let content = fs::read_to_string(manifest)?;
             let manifest: Manifest = serde_json::from_str(&content)?;
-            
-            for unit in manifest.units {
+
+            let base_seed = seed.unwrap_or_else(rand::random);
+            for (i, unit) in manifest.units.into_iter().enumerate() {
                 println!("Processing {}...", unit.name);
-                train_fu(&unit.name, unit.type_, assets_dir)?;
+                train_fu(&unit.name, unit.type_, base_seed.wrapping_add(i as u64), assets_dir)?;
             }
         }
         Commands::Verify { name } => {
@@ -83,148 +101,142 @@ fn main() -> anyhow::Result<()> {
             println!("Listing trained FUs:");
             for entry in fs::read_dir(assets_dir)? {
                 let entry = entry?;
-                println!(" - {}", entry.file_name().to_string_lossy());
+                let kind = WeightFile::load(&entry.path())
+                    .map(|f| format!("{:?}", f.header.kind))
+                    .unwrap_or_else(|_| "unreadable".to_string());
+                println!(" - {} [{}]", entry.file_name().to_string_lossy(), kind);
             }
         }
+        Commands::Convert { input, output, precision } => {
+            convert_fu(Path::new(&input), Path::new(&output), precision)?;
+        }
+        Commands::Quantize { name, precision, output } => {
+            quantize_fu(&name, precision, output.as_deref().map(Path::new), assets_dir)?;
+        }
     }
 
     Ok(())
 }
 
-fn train_fu(name: &str, type_: FUType, out_dir: &Path) -> anyhow::Result<()> {
-    // This is where we will delegate to specific training functions
-    // For now, we stub it out or reuse existing logic from fu.rs if available
-    
-    // We need to support saving the resulting FU.
-    // The BaseFU is serializable.
-    
+fn train_fu(name: &str, type_: FUType, seed: u64, out_dir: &Path) -> anyhow::Result<()> {
     let fu_file = out_dir.join(format!("{}.json", name));
-    
-    // Check if exists first for load
-    if !fu_file.exists() && matches!(type_, FUType::PC) {
-         println!("PC unit does not require training.");
-         return Ok(());
-    }
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let Some(fu) = type_.create_unit(&mut rng) else {
+        println!("{:?} Unit is structural, no training needed.", type_);
+        return Ok(());
+    };
 
-    match type_ {
-        FUType::ADDER => {
-            let fu = BaseFU::create_adder();
-            let trained_fu = train_adder(fu);
-            save_fu(&trained_fu, &fu_file)?;
-        },
-        FUType::CMP => {
-            let fu = BaseFU::create_comparator();
-            let trained_fu = train_comparator(fu);
-            save_fu(&trained_fu, &fu_file)?;
-        },
-        FUType::BITWISE => {
-             let fu = BaseFU::create_bitwise();
-             let trained_fu = train_bitwise(fu);
-             save_fu(&trained_fu, &fu_file)?;
-        },
-        FUType::PC => {
-            println!("PC Unit is structural, no training needed.");
+    let mut trained_fu = train_loop(fu, type_, &mut rng, name);
+    let mut rounds = 1;
+
+    // Every truth table is small enough to check exhaustively after each round,
+    // so keep training until the network is exact.
+    while rounds < MAX_TRAINING_ROUNDS {
+        let net_acc = type_.verify_network(&mut trained_fu);
+        if net_acc.errors == 0 {
+            break;
         }
+        println!("  [{}] Round {}: {} / {} training cases wrong, continuing", name, rounds, net_acc.errors, net_acc.cases);
+        trained_fu = train_loop(trained_fu, type_, &mut rng, name);
+        rounds += 1;
     }
-    
+
+    let accuracy = type_.verify_exhaustive(type_.build(trained_fu.clone()).as_mut());
+    println!("  [{}] Exhaustive: {} errors / {} cases ({:.2}%)",
+        name, accuracy.errors, accuracy.cases, accuracy.ratio() * 100.0);
+
+    let mut file = WeightFile::new(name, Some(type_), trained_fu);
+    file.header.training = Some(TrainingInfo {
+        learning_rate: LEARNING_RATE,
+        epochs: EPOCHS * rounds,
+        batch_size: BATCH_SIZE,
+        seed,
+    });
+    file.header.verification = Some(VerificationInfo::exhaustive(accuracy));
+    file.save(&fu_file)?;
+    println!("Saved to {:?}", fu_file);
+
     Ok(())
 }
 
-fn save_fu<T: Serialize>(fu: &T, path: &Path) -> anyhow::Result<()> {
-    let file = File::create(path)?;
-    serde_json::to_writer_pretty(file, fu)?;
-    println!("Saved to {:?}", path);
+/// Locate a unit's weights in the assets directory, preferring JSON over `.nfn`.
+fn find_fu(name: &str, dir: &Path) -> Option<PathBuf> {
+    ["json", "nfn"].iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|p| p.exists())
+}
+
+fn convert_fu(input: &Path, output: &Path, precision: Precision) -> anyhow::Result<()> {
+    let file = WeightFile::load(input)?;
+    let to_nfn = output.extension().is_some_and(|e| e == "nfn");
+    if to_nfn {
+        file.save_nfn(output, precision)?;
+    } else {
+        anyhow::ensure!(precision == Precision::F32, "JSON output is always f32; use a .nfn output for {:?}", precision);
+        file.save(output)?;
+    }
+    let in_size = fs::metadata(input)?.len();
+    let out_size = fs::metadata(output)?.len();
+    println!("Converted {:?} ({} bytes) -> {:?} ({} bytes, {:?})", input, in_size, output, out_size, precision);
     Ok(())
 }
 
-fn load_fu_base(path: &Path) -> anyhow::Result<BaseFU> {
-    let file = File::open(path)?;
-    let fu = serde_json::from_reader(file)?;
-    Ok(fu)
+fn quantize_fu(name: &str, precision: Precision, output: Option<&Path>, out_dir: &Path) -> anyhow::Result<()> {
+    let Some(path) = find_fu(name, out_dir) else {
+        println!("FU {} not found in {:?}", name, out_dir);
+        return Ok(());
+    };
+    let file = WeightFile::load(&path)?;
+    let Some(kind) = file.header.kind else {
+        anyhow::bail!("{}: unknown FU kind, cannot run exhaustive verification", name);
+    };
+
+    let quantized = QuantizedFU::quantize(&file.weights, precision);
+    let before = kind.verify_exhaustive(kind.build(file.weights.clone()).as_mut());
+    let after = kind.verify_exhaustive(kind.build_with(quantized.clone()).as_mut());
+    let f32_bytes = QuantizedFU::quantize(&file.weights, Precision::F32).weight_bytes();
+
+    println!("Quantizing {} ({:?}) to {:?}", name, kind, precision);
+    println!("  {:<8} {:>10} {:>14} {:>12}", "", "bytes", "case acc", "bit acc");
+    for (label, bytes, acc) in [("f32", f32_bytes, before), ("quant", quantized.weight_bytes(), after)] {
+        println!("  {:<8} {:>10} {:>13.3}% {:>11.4}%  ({} case / {} bit errors)",
+            label, bytes, acc.ratio() * 100.0, acc.bit_ratio() * 100.0, acc.errors, acc.bit_errors);
+    }
+
+    if let Some(output) = output {
+        let mut header = file.header.clone();
+        header.content_hash = content_hash(&quantized.dequantize());
+        header.verification = Some(VerificationInfo::exhaustive(after));
+        std::fs::write(output, NfnFile::from_quantized(header, &quantized).to_bytes()?)?;
+        println!("Saved to {:?}", output);
+    }
+    Ok(())
 }
 
 fn verify_fu(name: &str, out_dir: &Path) -> anyhow::Result<()> {
-    // Determine type from name or manifest? 
-    // The CLI verify command only takes name. We might need to look up type from manifest or infer or try all.
-    // For simplicity, let's just try to load as BaseFU and run a generic check or specific check based on name conventions.
-    
-    let path = out_dir.join(format!("{}.json", name));
-    if !path.exists() {
-        println!("FU {} not found at {:?}", name, path);
+    let Some(path) = find_fu(name, out_dir) else {
+        println!("FU {} not found in {:?}", name, out_dir);
         return Ok(());
-    }
-    
-    // We assume BaseFU for trained units
-    let mut fu = load_fu_base(&path)?;
-    
-    // Choose generator based on name
-    let generator: Option<Box<dyn Fn() -> (Array1<f32>, Array1<f32>)>> = if name.contains("adder") {
-        Some(Box::new(|| {
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
-            let a = rng.gen::<u8>();
-            let b = rng.gen::<u8>();
-            let sum = (a as u16) + (b as u16);
-            let mut input = u8_to_vec(a);
-            input.extend(u8_to_vec(b));
-            let mut target = u8_to_vec((sum & 0xFF) as u8);
-            target.push(if sum > 0xFF { 1.0 } else { 0.0 });
-            (Array1::from(input), Array1::from(target))
-        }))
-    } else if name.contains("compare") || name.contains("cmp") {
-        Some(Box::new(|| {
-             use rand::Rng;
-            let mut rng = rand::thread_rng();
-            let a = rng.gen::<u8>();
-            let b = rng.gen::<u8>();
-            let mut input = u8_to_vec(a);
-            input.extend(u8_to_vec(b));
-             let target = if a > b { vec![1.0, 0.0, 0.0] } 
-                     else if a == b { vec![0.0, 1.0, 0.0] } 
-                     else { vec![0.0, 0.0, 1.0] };
-            (Array1::from(input), Array1::from(target))
-        }))
-    } else if name.contains("bitwise") {
-        Some(Box::new(|| {
-             use rand::Rng;
-            let mut rng = rand::thread_rng();
-            let a = rng.gen::<u8>();
-            let b = rng.gen::<u8>();
-            let mode = rng.gen_range(0..3); 
-            let mut input = u8_to_vec(a);
-            input.extend(u8_to_vec(b));
-             match mode {
-                0 => input.extend(vec![1.0, 0.0, 0.0]),
-                1 => input.extend(vec![0.0, 1.0, 0.0]),
-                _ => input.extend(vec![0.0, 0.0, 1.0]),
-            }
-            let res = match mode {
-                0 => a & b,
-                1 => a | b,
-                _ => a ^ b,
-            };
-            (Array1::from(input), Array1::from(u8_to_vec(res)))
-        }))
-    } else {
-        None
     };
 
-    if let Some(gen) = generator {
-        let mut errors = 0;
-        let samples = 1000;
-        for _ in 0..samples {
-            let (input, target) = gen();
-            let output = fu.forward(&input);
-            let out_bits: Vec<u8> = output.iter().map(|&x| if x > 0.5 { 1 } else { 0 }).collect();
-            let target_bits: Vec<u8> = target.iter().map(|&x| if x > 0.5 { 1 } else { 0 }).collect();
-            if out_bits != target_bits {
-                errors += 1;
-            }
-        }
-        println!("Verification for {}: {} errors / {} samples", name, errors, samples);
-    } else {
-        println!("Unknown FU type for verification: {}", name);
+    let file = WeightFile::load(&path)?;
+    let header = &file.header;
+    println!("  Format v{} | kind {:?} | hash {}", header.version, header.kind, header.content_hash);
+    if let Some(t) = &header.training {
+        println!("  Trained: lr={} epochs={} batch={} seed={}", t.learning_rate, t.epochs, t.batch_size, t.seed);
     }
+    if let Some(v) = &header.verification {
+        println!("  Recorded: {} errors / {} cases", v.errors, v.cases);
+    }
+
+    let Some(kind) = header.kind else {
+        println!("Unknown FU type for verification: {}", name);
+        return Ok(());
+    };
+
+    let accuracy = kind.verify_exhaustive(kind.build(file.weights).as_mut());
+    println!("Verification for {}: {} errors / {} cases", name, accuracy.errors, accuracy.cases);
 
     Ok(())
 }
@@ -235,122 +247,33 @@ fn verify_fu(name: &str, out_dir: &Path) -> anyhow::Result<()> {
 const LEARNING_RATE: f32 = 0.1;
 const EPOCHS: usize = 500;
 const BATCH_SIZE: usize = 100;
+/// Rounds of `EPOCHS` each before giving up on an exact network.
+const MAX_TRAINING_ROUNDS: usize = 60;
 
-fn train_loop(
-    mut fu: BaseFU, 
-    data_generator: impl Fn() -> (Array1<f32>, Array1<f32>),
+fn train_loop<R: Rng>(
+    mut fu: BaseFU,
+    kind: FUType,
+    rng: &mut R,
     name: &str
 ) -> BaseFU {
-    let mut rng = rand::thread_rng();
-    
     for epoch in 0..EPOCHS {
         let mut total_error = 0.0;
-        
+
         for _ in 0..BATCH_SIZE {
-            let (input, target) = data_generator();
+            let (input, target) = kind.sample(rng);
             fu.train_step(&input, &target, LEARNING_RATE);
-            
+
             // Simple loss tracking
             let output = fu.forward(&input);
             for (i, t) in target.iter().enumerate() {
                 total_error += (output[i] - t).powi(2);
             }
         }
-        
-        if epoch % 1000 == 0 {
+
+        if epoch % 100 == 0 {
             println!("  [{}] Epoch {}: Loss = {:.4}", name, epoch, total_error / BATCH_SIZE as f32);
         }
     }
-    
-    // Verify
-    let mut errors = 0;
-    println!("  [{}] Verifying...", name);
-    for _ in 0..100 {
-        let (input, target) = data_generator();
-        let output = fu.forward(&input);
-        
-        // Threshold check
-        let out_bits: Vec<u8> = output.iter().map(|&x| if x > 0.5 { 1 } else { 0 }).collect();
-        let target_bits: Vec<u8> = target.iter().map(|&x| if x > 0.5 { 1 } else { 0 }).collect();
-        
-        if out_bits != target_bits {
-            errors += 1;
-        }
-    }
-    println!("  [{}] Verification Errors (Sample 100): {}", name, errors);
-    
-    fu
-}
-
-// --- Data Generators ---
-
-fn u8_to_vec(val: u8) -> Vec<f32> {
-    (0..8).map(|i| if (val >> i) & 1 == 1 { 1.0 } else { 0.0 }).collect()
-}
-
-
 
-fn train_adder(fu: BaseFU) -> BaseFU {
-    use rand::Rng;
-    train_loop(fu, || {
-        let mut rng = rand::thread_rng();
-        let a = rng.gen::<u8>();
-        let b = rng.gen::<u8>();
-        let sum = (a as u16) + (b as u16);
-        
-        let mut input = u8_to_vec(a);
-        input.extend(u8_to_vec(b));
-        
-        let mut target = u8_to_vec((sum & 0xFF) as u8); // Sum low byte
-        target.push(if sum > 0xFF { 1.0 } else { 0.0 }); // Carry bit
-        
-        (Array1::from(input), Array1::from(target))
-    }, "Adder")
-}
-
-fn train_comparator(fu: BaseFU) -> BaseFU {
-    use rand::Rng;
-    train_loop(fu, || {
-        let mut rng = rand::thread_rng();
-        let a = rng.gen::<u8>();
-        let b = rng.gen::<u8>();
-        
-        let mut input = u8_to_vec(a);
-        input.extend(u8_to_vec(b));
-        
-        let target = if a > b { vec![1.0, 0.0, 0.0] } // GT
-                     else if a == b { vec![0.0, 1.0, 0.0] } // EQ
-                     else { vec![0.0, 0.0, 1.0] }; // LT
-                     
-        (Array1::from(input), Array1::from(target))
-    }, "Comparator")
-}
-
-fn train_bitwise(fu: BaseFU) -> BaseFU {
-    use rand::Rng;
-    train_loop(fu, || {
-        let mut rng = rand::thread_rng();
-        let a = rng.gen::<u8>();
-        let b = rng.gen::<u8>();
-        let mode = rng.gen_range(0..3); // 0=AND, 1=OR, 2=XOR
-        
-        let mut input = u8_to_vec(a);
-        input.extend(u8_to_vec(b));
-        // Mode bits (3 bits)
-        match mode {
-            // let's use 1-hot for mode
-            0 => input.extend(vec![1.0, 0.0, 0.0]),
-            1 => input.extend(vec![0.0, 1.0, 0.0]),
-            _ => input.extend(vec![0.0, 0.0, 1.0]),
-        }
-        
-        let res = match mode {
-            0 => a & b,
-            1 => a | b,
-            _ => a ^ b,
-        };
-        
-        (Array1::from(input), Array1::from(u8_to_vec(res)))
-    }, "Bitwise")
+    fu
 }
-
@@ -0,0 +1,75 @@
+use clap::Parser;
+use std::path::PathBuf;
+use neuro_symbolic_emulator::gdb;
+use neuro_symbolic_emulator::loader::{boot, load_manifest};
+use neuro_symbolic_emulator::snapshot::{Snapshot, WeightMode};
+
+/// Headless runner: build a system, optionally resume a saved state, run and save.
+#[derive(Parser)]
+#[command(name = "run_system")]
+#[command(about = "Run the emulator without the GUI", long_about = None)]
+struct Cli {
+    /// Manifest to build the system from (defaults to the one recorded in --load-state, then manifest.json)
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+    /// Boot from this disk image instead of the manifest's program
+    #[arg(long)]
+    disk: Option<PathBuf>,
+    /// Cycle limit
+    #[arg(long, default_value_t = 10_000)]
+    cycles: usize,
+    /// Resume from a snapshot
+    #[arg(long)]
+    load_state: Option<PathBuf>,
+    /// Write a snapshot when the run ends
+    #[arg(long)]
+    save_state: Option<PathBuf>,
+    /// How the saved snapshot stores weights
+    #[arg(long, value_enum, default_value = "inline")]
+    weights: WeightMode,
+    /// Instead of running, wait for GDB on 127.0.0.1:PORT and let it drive the machine
+    #[arg(long, value_name = "PORT")]
+    gdb: Option<u16>,
+    /// Print the execution log
+    #[arg(long)]
+    trace: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let snapshot = cli.load_state.as_deref().map(Snapshot::load).transpose()?;
+    let manifest = cli.manifest.clone()
+        .or_else(|| snapshot.as_ref().and_then(|s| s.manifest.clone()))
+        .unwrap_or_else(|| PathBuf::from("manifest.json"));
+
+    let mut sys = match &cli.disk {
+        Some(disk) => boot(&manifest, disk, None)?,
+        None => load_manifest(&manifest, None)?,
+    };
+    if let Some(snapshot) = &snapshot {
+        sys.restore(snapshot)?;
+    }
+
+    if let Some(port) = cli.gdb {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", port))?;
+        eprintln!("Waiting for GDB on {} (target remote :{})", listener.local_addr()?, port);
+        gdb::serve(&mut sys, &listener)?;
+    } else {
+        let report = sys.run(cli.cycles);
+        eprintln!("{}", report);
+    }
+    if cli.trace {
+        for line in &sys.logs {
+            eprintln!("{}", line);
+        }
+    }
+    if let Some(fault) = &sys.fault {
+        eprintln!("FAULT: {}", fault);
+    }
+
+    if let Some(path) = &cli.save_state {
+        sys.snapshot(cli.weights).save(path)?;
+        eprintln!("Saved state at step {} (PC {}) to {:?}", sys.total_steps, sys.pc, path);
+    }
+    Ok(())
+}
@@ -1,7 +1,6 @@
 use ndarray::{Array1, Array2};
 use rand::Rng;
-use serde_json;
-use neuro_symbolic_emulator::fu::{BaseFU, Activation, NeuralFunctionalUnit};
+use neuro_symbolic_emulator::fu::{BaseFU, Activation, FUType, NeuralFunctionalUnit};
 use std::fs::File;
 use std::io::Write;
 use std::collections::HashMap;
@@ -17,9 +16,9 @@ fn main() -> anyhow::Result<()> {
 fn train_comparator() -> anyhow::Result<()> {
     println!("Training 8-bit Comparator FU...");
     
-    let input_size = 16; // 8 A + 8 B
-    let output_size = 3; // GT, EQ, LT
-    let hidden_size = 32;
+    let input_size = 17; // 8 A + 8 B + Signed
+    let output_size = 7; // Z, N, C, V, GT, EQ, LT
+    let hidden_size = 48;
     
     let mut rng = rand::thread_rng();
     
@@ -56,25 +55,8 @@ fn train_comparator() -> anyhow::Result<()> {
 }
 
 fn generate_cmp_batch<R: Rng>(size: usize, rng: &mut R) -> Vec<(Array1<f32>, Array1<f32>)> {
-    let mut batch = Vec::with_capacity(size);
-    for _ in 0..size {
-        let a_int: u8 = rng.gen();
-        let b_int: u8 = rng.gen();
-        
-        let mut input = Array1::zeros(16);
-        for i in 0..8 {
-            if (a_int >> i) & 1 == 1 { input[i] = 1.0; }
-            if (b_int >> i) & 1 == 1 { input[i+8] = 1.0; }
-        }
-        
-        let mut output = Array1::zeros(3);
-        if a_int > b_int { output[0] = 1.0; }
-        else if a_int == b_int { output[1] = 1.0; }
-        else { output[2] = 1.0; }
-        
-        batch.push((input, output));
-    }
-    batch
+    // Signed and unsigned comparisons with two's-complement flags
+    (0..size).map(|_| FUType::CMP.sample(rng)).collect()
 }
 
 // Helper for saving
@@ -0,0 +1,145 @@
+use anyhow::{bail, Result};
+
+use crate::bus::{Bundle, MoveOp};
+use crate::fu::SECTOR_SIZE;
+use crate::guard::{Guard, GuardTest};
+
+/// First bytes of a bootable sector 0.
+pub const BOOT_MAGIC: &[u8; 4] = b"NTSB";
+/// RAM address the boot sector is copied to.
+pub const BOOT_ADDR: u16 = 0x2000;
+
+const HEADER: usize = 8;
+const MOVE_SIZE: usize = 8;
+/// Moves that fit in one sector after the header.
+pub const MAX_BOOT_MOVES: usize = (SECTOR_SIZE - HEADER) / MOVE_SIZE;
+
+/// Mode byte: low bits pick the guard test, high bits modify it.
+const GUARD_NONE: u8 = 0;
+const GUARD_BIT: u8 = 1;
+const GUARD_ANY: u8 = 2;
+const GUARD_ALL: u8 = 3;
+const GUARD_MAJ: u8 = 4;
+/// Move issues in the same bundle as the one before it.
+const SAME_BUNDLE: u8 = 0x40;
+const NEGATE: u8 = 0x80;
+
+/// Binary boot sector: the program the ROM stage hands control to.
+///
+/// ```text
+/// 0..4   "NTSB"
+/// 4      move count (at most MAX_BOOT_MOVES)
+/// 5      entry bundle (initial PC)
+/// 6..8   reserved
+/// 8..    moves, 8 bytes each: src u16 LE, dest u16 LE, guard addr u16 LE,
+///        mode (test | NEGATE | SAME_BUNDLE), bit index for bit guards
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BootSector {
+    pub program: Vec<Bundle>,
+    pub entry: usize,
+}
+
+impl BootSector {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let moves: usize = self.program.iter().map(|b| b.moves.len()).sum();
+        if moves > MAX_BOOT_MOVES {
+            bail!("boot program has {} moves, a sector holds {}", moves, MAX_BOOT_MOVES);
+        }
+        if self.entry > u8::MAX as usize || (self.entry >= self.program.len() && self.entry > 0) {
+            bail!("entry bundle {} is outside the {}-bundle boot program", self.entry, self.program.len());
+        }
+        let mut out = BOOT_MAGIC.to_vec();
+        out.extend([moves as u8, self.entry as u8, 0, 0]);
+        for bundle in &self.program {
+            if bundle.moves.is_empty() {
+                bail!("boot programs cannot contain empty bundles");
+            }
+            for (i, op) in bundle.moves.iter().enumerate() {
+                let (guard_addr, mut mode, bit) = match op.guard {
+                    None => (0, GUARD_NONE, 0),
+                    Some(g) => {
+                        let (test, bit) = match g.test {
+                            GuardTest::Bit(b) => (GUARD_BIT, b as u8),
+                            GuardTest::Any => (GUARD_ANY, 0),
+                            GuardTest::All => (GUARD_ALL, 0),
+                            GuardTest::Majority => (GUARD_MAJ, 0),
+                        };
+                        (g.addr, if g.negate { test | NEGATE } else { test }, bit)
+                    }
+                };
+                if i > 0 {
+                    mode |= SAME_BUNDLE;
+                }
+                out.extend(op.src.to_le_bytes());
+                out.extend(op.dest.to_le_bytes());
+                out.extend(guard_addr.to_le_bytes());
+                out.extend([mode, bit]);
+            }
+        }
+        out.resize(SECTOR_SIZE, 0);
+        Ok(out)
+    }
+
+    pub fn decode(sector: &[u8]) -> Result<Self> {
+        if sector.len() < HEADER || &sector[..4] != BOOT_MAGIC {
+            bail!("no boot signature (expected {:?})", std::str::from_utf8(BOOT_MAGIC).unwrap_or_default());
+        }
+        let (count, entry) = (sector[4] as usize, sector[5] as usize);
+        if count > MAX_BOOT_MOVES || HEADER + count * MOVE_SIZE > sector.len() {
+            bail!("boot sector claims {} moves", count);
+        }
+        let mut program: Vec<Bundle> = Vec::new();
+        for raw in sector[HEADER..HEADER + count * MOVE_SIZE].chunks(MOVE_SIZE) {
+            let word = |i: usize| u16::from_le_bytes([raw[i], raw[i + 1]]);
+            let (mode, bit) = (raw[6], raw[7]);
+            let test = match mode & 0x3F {
+                GUARD_NONE => None,
+                GUARD_BIT => Some(GuardTest::Bit(bit as usize)),
+                GUARD_ANY => Some(GuardTest::Any),
+                GUARD_ALL => Some(GuardTest::All),
+                GUARD_MAJ => Some(GuardTest::Majority),
+                other => bail!("unknown guard mode {} in boot sector", other),
+            };
+            let guard = test.map(|test| Guard { addr: word(4), test, negate: mode & NEGATE != 0 });
+            let op = MoveOp { src: word(0), dest: word(2), guard };
+            match program.last_mut() {
+                Some(bundle) if mode & SAME_BUNDLE != 0 => bundle.moves.push(op),
+                _ => program.push(Bundle::from(op)),
+            }
+        }
+        if entry > 0 && entry >= program.len() {
+            bail!("boot entry {} is outside the {}-bundle boot program", entry, program.len());
+        }
+        Ok(Self { program, entry })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boot_sector_roundtrip() {
+        let mv = |src, dest, guard| MoveOp { src, dest, guard };
+        let boot = BootSector {
+            program: vec![
+                Bundle::from(mv(0x2010, 0, None)),
+                Bundle { moves: vec![mv(0, 0x8000, Some(Guard::bit(0x0FF0, 1).negated())), mv(1, 2, Some(Guard { addr: 3, test: GuardTest::Majority, negate: false }))] },
+            ],
+            entry: 1,
+        };
+        let sector = boot.encode().unwrap();
+        assert_eq!(sector.len(), SECTOR_SIZE);
+        let back = BootSector::decode(&sector).unwrap();
+        assert_eq!(back.entry, 1);
+        assert_eq!(back.program.len(), 2);
+        assert_eq!(back.program[1].moves.len(), 2);
+        assert_eq!(back.program[1].moves[0].guard, boot.program[1].moves[0].guard);
+        assert_eq!(back.program[1].moves[1].guard, boot.program[1].moves[1].guard);
+
+        assert!(BootSector::decode(&[0; SECTOR_SIZE]).is_err());
+        let too_long = BootSector { program: vec![Bundle::from(mv(0, 1, None)); MAX_BOOT_MOVES + 1], entry: 0 };
+        assert!(too_long.encode().is_err());
+    }
+}
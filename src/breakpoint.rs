@@ -0,0 +1,186 @@
+use anyhow::{anyhow, bail};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bus::Access;
+use crate::fu::kind::decode_bits;
+use crate::guard::{default_name, parse_addr};
+use crate::system::SystemEmulator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    const ALL: [(&'static str, CmpOp); 6] =
+        [("==", CmpOp::Eq), ("!=", CmpOp::Ne), ("<=", CmpOp::Le), (">=", CmpOp::Ge), ("<", CmpOp::Lt), (">", CmpOp::Gt)];
+
+    pub fn eval<T: PartialOrd>(self, lhs: T, rhs: T) -> bool {
+        match self {
+            CmpOp::Eq => lhs == rhs,
+            CmpOp::Ne => lhs != rhs,
+            CmpOp::Lt => lhs < rhs,
+            CmpOp::Le => lhs <= rhs,
+            CmpOp::Gt => lhs > rhs,
+            CmpOp::Ge => lhs >= rhs,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        Self::ALL.iter().find(|(_, op)| *op == self).map_or("?", |(s, _)| s)
+    }
+}
+
+/// When the machine should stop.
+///
+/// Text form (as typed into the dashboard): `pc 12`, `watch R3` (written),
+/// `rwatch 0x2000` (read), `awatch 0x8000` (either), `R3 == 42` (value decoded
+/// as an unsigned integer, LSB first) and `drift(0x1000) > 0.2` (largest
+/// distance of the unit's last output from a clean 0/1 bit).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Breakpoint {
+    /// Before the bundle at this index executes.
+    Pc(usize),
+    /// After a step that accessed `addr` (moves, guards and unit RAM accesses).
+    Watch { addr: u16, read: bool, write: bool },
+    Value { addr: u16, op: CmpOp, value: u32 },
+    Drift { unit: u16, op: CmpOp, threshold: f32 },
+}
+
+/// Why `run_until` stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakHit {
+    /// Index into `SystemEmulator::breakpoints`.
+    pub index: usize,
+    pub step: usize,
+    pub pc: usize,
+    pub reason: String,
+}
+
+impl Breakpoint {
+    /// Checked before a step; only PC breakpoints stop here.
+    pub(crate) fn before(&self, sys: &SystemEmulator) -> Option<String> {
+        match *self {
+            Breakpoint::Pc(pc) if sys.pc == pc => Some(format!("breakpoint at bundle {}", pc)),
+            _ => None,
+        }
+    }
+
+    /// Checked after a step.
+    pub(crate) fn after(&self, sys: &SystemEmulator) -> Option<String> {
+        match *self {
+            Breakpoint::Pc(_) => None,
+            Breakpoint::Watch { addr, read, write } => {
+                let hit = sys.bus.accesses.iter().find(|(a, access)| {
+                    *a == addr && match access {
+                        Access::Read => read,
+                        Access::Write => write,
+                    }
+                })?;
+                let verb = if hit.1 == Access::Read { "read" } else { "written" };
+                Some(format!("{} {}", default_name(addr), verb))
+            }
+            Breakpoint::Value { addr, op, value } => {
+                let current = decode_bits(sys.bus.peek(addr).as_slice().unwrap_or(&[]));
+                op.eval(current, value).then(|| format!("{} = {}", default_name(addr), current))
+            }
+            Breakpoint::Drift { unit, op, threshold } => {
+                let (_, output) = sys.bus.fu_io_cache.get(&unit)?;
+                let drift = output.iter().map(|&v| v.abs().min((1.0 - v).abs())).fold(0.0, f32::max);
+                op.eval(drift, threshold).then(|| format!("drift on 0x{:X} = {:.3}", unit, drift))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Breakpoint::Pc(pc) => write!(f, "pc {}", pc),
+            Breakpoint::Watch { addr, read, write } => {
+                let kind = match (read, write) {
+                    (true, true) => "awatch",
+                    (true, false) => "rwatch",
+                    _ => "watch",
+                };
+                write!(f, "{} {}", kind, default_name(addr))
+            }
+            Breakpoint::Value { addr, op, value } => write!(f, "{} {} {}", default_name(addr), op.symbol(), value),
+            Breakpoint::Drift { unit, op, threshold } => write!(f, "drift(0x{:X}) {} {}", unit, op.symbol(), threshold),
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if let Some(pc) = s.strip_prefix("pc ") {
+            return pc.trim().parse().map(Breakpoint::Pc).map_err(|_| anyhow!("bad bundle index in '{}'", s));
+        }
+        for (kind, read, write) in [("watch ", false, true), ("rwatch ", true, false), ("awatch ", true, true)] {
+            if let Some(addr) = s.strip_prefix(kind) {
+                return Ok(Breakpoint::Watch { addr: parse_addr(addr)?, read, write });
+            }
+        }
+
+        let Some((lhs, op, rhs)) = CmpOp::ALL.iter().find_map(|&(sym, op)| {
+            s.split_once(sym).map(|(l, r)| (l.trim(), op, r.trim()))
+        }) else {
+            bail!("'{}' is not a breakpoint (try 'pc 12', 'watch R3', 'R3 == 42' or 'drift(0x1000) > 0.2')", s);
+        };
+        if let Some(unit) = lhs.strip_prefix("drift(").and_then(|r| r.strip_suffix(')')) {
+            let threshold = rhs.parse().map_err(|_| anyhow!("bad drift threshold '{}'", rhs))?;
+            return Ok(Breakpoint::Drift { unit: parse_addr(unit)?, op, threshold });
+        }
+        let value = match rhs.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => rhs.parse(),
+        }.map_err(|_| anyhow!("bad value '{}'", rhs))?;
+        Ok(Breakpoint::Value { addr: parse_addr(lhs)?, op, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::MoveOp;
+    use crate::fu::BaseFU;
+    use crate::register::NeuralRegister;
+    use ndarray::{Array1, Array2};
+
+    #[test]
+    fn test_breakpoints_stop_run_until() {
+        for text in ["pc 3", "watch R3", "rwatch 0x2000", "awatch 0x8000", "R3 == 42", "drift(0x1000) > 0.2"] {
+            assert_eq!(text.parse::<Breakpoint>().unwrap().to_string(), text);
+        }
+        assert!("R3 = 42".parse::<Breakpoint>().is_err());
+
+        let mut sys = SystemEmulator::default();
+        // Identity-ish unit whose output sits at 0.7: a drift of 0.3
+        let fu = BaseFU::new(Array2::zeros((1, 8)), Array1::zeros(1), Array2::zeros((8, 1)), Array1::from_elem(8, 0.85),
+            crate::fu::Activation::Identity, crate::fu::Activation::Sigmoid);
+        sys.bus.add_unit(0x1000, Box::new(fu));
+        sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 42));
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        sys.load_program(vec![mv(0, 0x2000), mv(1, 2), mv(0x2000, 3), mv(3, 4), mv(0, 0x1000), mv(0, 5)]);
+        sys.breakpoints = vec!["R3 == 42".parse().unwrap(), "pc 4".parse().unwrap(), "drift(0x1000) > 0.2".parse().unwrap()];
+        sys.breakpoints.insert(0, "rwatch 0x2000".parse().unwrap());
+
+        let stops: Vec<(usize, usize)> = std::iter::from_fn(|| {
+            let (_, hit) = sys.run_until(100);
+            hit.map(|h| (h.index, h.pc))
+        }).take(10).collect();
+        // Bundle 2 reads 0x2000 and makes R3 == 42 (first match wins, and the
+        // condition does not fire again while it stays true); then PC 4; then the drifting unit
+        assert_eq!(stops, vec![(0, 3), (2, 4), (3, 5)]);
+        assert!(sys.last_break.is_none() && sys.pc == 6);
+    }
+}
@@ -1,7 +1,11 @@
-use crate::fu::{BaseFU, NeuralFunctionalUnit};
+use crate::fu::kind::{decode_bits, encode_bits, CMP_FLAGS};
+use crate::fu::lsu::{nearest_cell, AddrMetric, AssocStats};
+use crate::fu::{InterruptControllerFU, MemRequest, MemResponse, NeuralFunctionalUnit, PortLayout};
+use crate::guard::Guard;
+use crate::pipeline::{HazardPolicy, Pipeline, UnitTiming};
 use crate::register::NeuralRegister;
 use ndarray::Array1;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 
@@ -9,17 +13,168 @@ use serde::{Deserialize, Serialize};
 pub struct MoveOp {
     pub src: u16,  // Address
     pub dest: u16, // Address
-    pub guard: Option<u16>, // Address of Guard Register
+    pub guard: Option<Guard>, // Condition on a guard address
+}
+
+/// One instruction word: the moves issued together in a cycle, one per transport bus.
+///
+/// In program JSON a single move object is a one-move bundle, so existing
+/// programs load unchanged; an array of moves is a multi-issue bundle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "BundleRepr", into = "BundleRepr")]
+pub struct Bundle {
+    pub moves: Vec<MoveOp>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BundleRepr {
+    Single(MoveOp),
+    Multi(Vec<MoveOp>),
+}
+
+impl From<BundleRepr> for Bundle {
+    fn from(repr: BundleRepr) -> Self {
+        match repr {
+            BundleRepr::Single(op) => op.into(),
+            BundleRepr::Multi(moves) => Self { moves },
+        }
+    }
+}
+
+impl From<Bundle> for BundleRepr {
+    fn from(mut bundle: Bundle) -> Self {
+        if bundle.moves.len() == 1 {
+            BundleRepr::Single(bundle.moves.remove(0))
+        } else {
+            BundleRepr::Multi(bundle.moves)
+        }
+    }
+}
+
+impl From<MoveOp> for Bundle {
+    fn from(op: MoveOp) -> Self {
+        Self { moves: vec![op] }
+    }
+}
+
+impl Bundle {
+    /// Destinations written by more than one unguarded move. Guarded moves may
+    /// share a destination as long as at most one guard passes at run time.
+    pub fn static_conflicts(&self) -> Vec<u16> {
+        let mut seen = HashSet::new();
+        let mut conflicts = Vec::new();
+        for op in self.moves.iter().filter(|op| op.guard.is_none()) {
+            if !seen.insert(op.dest) && !conflicts.contains(&op.dest) {
+                conflicts.push(op.dest);
+            }
+        }
+        conflicts
+    }
+}
+
+/// What a bundle did in its cycle.
+#[derive(Debug, Clone, Default)]
+pub struct BundleOutcome {
+    /// One log entry per move, in bundle order.
+    pub logs: Vec<String>,
+    /// Moves whose guard passed.
+    pub executed: usize,
+}
+
+/// Status register written by flag-producing units (layout `CMP_FLAGS`).
+/// Bit `i` can also be read on its own at `FLAGS_ADDR + 1 + i`, so a guard can test it.
+pub const FLAGS_ADDR: u16 = 0x0FF0;
+
+/// Program counter as seen from the bus (16 bits). Reading gives the index of the
+/// next bundle, i.e. the return address of a call made now; writing jumps there
+/// once the current bundle completes.
+pub const PC_ADDR: u16 = 0x0FFF;
+
+/// Start of the eMMC region: block storage controllers live at 0x9000-0xFFFF,
+/// above the MMIO devices.
+pub const STORAGE_BASE: u16 = 0x9000;
+
+/// `FLAGS` or `FLAGS.<bit>` for the status register and its single-bit aliases.
+pub fn flag_name(addr: u16) -> Option<String> {
+    match addr.checked_sub(FLAGS_ADDR)? {
+        0 => Some("FLAGS".to_string()),
+        i => CMP_FLAGS.get(i as usize - 1).map(|f| format!("FLAGS.{}", f)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortDir {
+    Input,
+    Output,
+}
+
+/// Kind of bus access, as seen by watchpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// RAM cells written during a recorded step, with what they held before.
+pub type RamJournal = Vec<(u16, Option<Array1<f32>>)>;
+
+/// Resolves a bus address to one port of a unit registered with a `PortLayout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRef {
+    pub base: u16,
+    pub dir: PortDir,
+    pub index: usize,
 }
 
 pub struct SystemBus {
     pub registers: HashMap<u16, NeuralRegister>, // 0x0000 - 0x0FFF (Mapped by ID)
-    pub units: HashMap<u16, Box<dyn NeuralFunctionalUnit>>, // 0x1000 range. Mapped by Base Port Address?
+    pub units: HashMap<u16, Box<dyn NeuralFunctionalUnit>>, // 0x1000 range. Mapped by Base Port Address
     pub ram: HashMap<u16, Array1<f32>>, // 0x2000 - 0x7FFF
     pub mmio: HashMap<u16, Box<dyn NeuralFunctionalUnit>>, // 0x8000+
+    pub names: HashMap<u16, String>, // Base -> Instance name (from the manifest)
     
     // Phase 9: Inspection Cache (Addr -> (Last Input, Last Output))
     pub fu_io_cache: HashMap<u16, (Array1<f32>, Array1<f32>)>,
+
+    // Port-mapped units: inputs at base+0.., outputs right after.
+    // Writing the last input port (the trigger) fires the unit.
+    pub layouts: HashMap<u16, PortLayout>, // Base -> Layout
+    pub ports: HashMap<u16, PortRef>,      // Port Addr -> Port
+    pub port_latches: HashMap<u16, Array1<f32>>, // Port Addr -> Latched Value
+
+    // Units whose output vector is copied into FLAGS every time they fire.
+    pub flag_units: HashSet<u16>,
+
+    // Pipelined units (Base -> results in flight). Others are combinational.
+    pub pipelines: HashMap<u16, Pipeline>,
+    pub hazard_policy: HazardPolicy,
+    pub stale_reads: usize,  // Output reads while a newer result was in flight
+    pub early_issues: usize, // Triggers inside a unit's initiation interval
+
+    // Associative (fuzzy address) lookups served for load/store units
+    pub assoc_stats: AssocStats,
+    // Set by an access the machine cannot continue from; the emulator halts on it
+    pub bus_fault: Option<String>,
+
+    // Control flow, shared with the emulator: the next bundle and a pending jump
+    pub pc: u16,
+    pub jump: Option<u16>,
+
+    // Interrupts: the controller's base and the line each source unit is wired to
+    pub irq_controller: Option<u16>,
+    pub irq_lines: HashMap<u16, u8>, // Source Base -> Line
+
+    // Reverse execution: previous contents of each RAM cell written, while the emulator records a step
+    pub ram_journal: Option<RamJournal>,
+    // Addresses read and written since the emulator last cleared it (once per step), for watchpoints
+    pub accesses: Vec<(u16, Access)>,
+}
+
+impl Default for SystemBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SystemBus {
@@ -29,7 +184,24 @@ impl SystemBus {
             units: HashMap::new(),
             ram: HashMap::new(),
             mmio: HashMap::new(),
+            names: HashMap::new(),
             fu_io_cache: HashMap::new(),
+            layouts: HashMap::new(),
+            ports: HashMap::new(),
+            port_latches: HashMap::new(),
+            flag_units: HashSet::new(),
+            pipelines: HashMap::new(),
+            hazard_policy: HazardPolicy::default(),
+            stale_reads: 0,
+            early_issues: 0,
+            assoc_stats: AssocStats::default(),
+            bus_fault: None,
+            pc: 0,
+            jump: None,
+            irq_controller: None,
+            irq_lines: HashMap::new(),
+            ram_journal: None,
+            accesses: Vec::new(),
         }
     }
 
@@ -50,68 +222,344 @@ impl SystemBus {
         self.mmio.insert(addr, device);
     }
 
+    /// Register a unit whose ports occupy consecutive addresses from `base_addr`:
+    /// one per input port (the last is the trigger), then one per output port.
+    /// Units at 0x8000+ land in MMIO.
+    pub fn add_unit_with_ports(&mut self, base_addr: u16, unit: Box<dyn NeuralFunctionalUnit>, layout: PortLayout) -> anyhow::Result<()> {
+        let count = layout.inputs.len() + layout.outputs.len();
+        let addrs: Vec<u16> = (0..count)
+            .map(|i| base_addr.checked_add(i as u16))
+            .collect::<Option<_>>()
+            .ok_or_else(|| anyhow::anyhow!("ports of unit at 0x{:X} overflow the address space", base_addr))?;
+        for addr in &addrs {
+            if let Some(other) = self.ports.get(addr) {
+                anyhow::bail!("port 0x{:X} of unit at 0x{:X} overlaps unit at 0x{:X}", addr, base_addr, other.base);
+            }
+            if *addr != base_addr && (self.units.contains_key(addr) || self.mmio.contains_key(addr)) {
+                anyhow::bail!("port 0x{:X} of unit at 0x{:X} overlaps another unit", addr, base_addr);
+            }
+        }
+
+        for (i, addr) in addrs.into_iter().enumerate() {
+            let (dir, index) = if i < layout.inputs.len() {
+                (PortDir::Input, i)
+            } else {
+                (PortDir::Output, i - layout.inputs.len())
+            };
+            self.ports.insert(addr, PortRef { base: base_addr, dir, index });
+        }
+        self.layouts.insert(base_addr, layout);
+        if base_addr >= 0x8000 {
+            self.mmio.insert(base_addr, unit);
+        } else {
+            self.units.insert(base_addr, unit);
+        }
+        Ok(())
+    }
+
+    /// Route a port-mapped unit's results into the FLAGS register (created on first use).
+    pub fn add_flag_unit(&mut self, base_addr: u16) {
+        self.flag_units.insert(base_addr);
+        self.registers.entry(FLAGS_ADDR).or_insert_with(|| NeuralRegister::new(CMP_FLAGS.len()));
+    }
+
+    /// Give a port-mapped unit a latency and initiation interval.
+    pub fn set_timing(&mut self, base_addr: u16, timing: UnitTiming) -> anyhow::Result<()> {
+        if !self.layouts.contains_key(&base_addr) {
+            anyhow::bail!("unit at 0x{:X} has no ports to pipeline", base_addr);
+        }
+        if timing.initiation_interval == 0 {
+            anyhow::bail!("unit at 0x{:X}: initiation interval must be at least 1", base_addr);
+        }
+        self.pipelines.insert(base_addr, Pipeline::new(timing));
+        Ok(())
+    }
+
+    /// Under `HazardPolicy::Stall`, why `moves` cannot issue this cycle: a read of
+    /// a result still in flight, or a trigger inside the unit's initiation interval.
+    pub fn stall_reason(&self, moves: &[MoveOp]) -> Option<String> {
+        if self.hazard_policy != HazardPolicy::Stall {
+            return None;
+        }
+        for op in moves {
+            for addr in op.guard.map(|g| g.addr).into_iter().chain([op.src]) {
+                let Some(port) = self.ports.get(&addr).filter(|p| p.dir == PortDir::Output) else { continue };
+                if self.pipelines.get(&port.base).is_some_and(|p| p.is_pending()) {
+                    return Some(format!("waiting on {}", self.port_name(addr).unwrap_or_default()));
+                }
+            }
+            if self.is_trigger(op.dest) {
+                let base = self.ports[&op.dest].base;
+                if self.pipelines.get(&base).is_some_and(|p| p.is_blocked()) {
+                    return Some(format!("{} not ready for a new trigger", self.port_name(op.dest).unwrap_or_default()));
+                }
+            }
+        }
+        None
+    }
+
+    /// Unit at `base`, whether in an FU socket or an MMIO slot.
+    pub fn unit(&self, base: u16) -> Option<&dyn NeuralFunctionalUnit> {
+        self.units.get(&base).or_else(|| self.mmio.get(&base)).map(|u| u.as_ref())
+    }
+
+    /// Instance name of the unit at `base`, falling back to its kind.
+    pub fn unit_name(&self, base: u16) -> Option<String> {
+        if let Some(name) = self.names.get(&base) {
+            return Some(name.clone());
+        }
+        let unit = self.unit(base)?;
+        Some(unit.introspect().map_or("unit", |i| i.kind()).to_string())
+    }
+
+    /// Human-readable name of a port address, e.g. `FU[0x1000].SUM`, `MMIO[0x8000].TX` or `eMMC[0x9000].READ`.
+    pub fn port_name(&self, addr: u16) -> Option<String> {
+        let port = self.ports.get(&addr)?;
+        let layout = self.layouts.get(&port.base)?;
+        let spec = match port.dir {
+            PortDir::Input => &layout.inputs[port.index],
+            PortDir::Output => &layout.outputs[port.index],
+        };
+        let region = match port.base {
+            STORAGE_BASE.. => "eMMC",
+            0x8000.. => "MMIO",
+            _ => "FU",
+        };
+        Some(format!("{}[0x{:X}].{}", region, port.base, spec.name))
+    }
+
+    fn port_width(&self, port: &PortRef) -> usize {
+        let layout = &self.layouts[&port.base];
+        match port.dir {
+            PortDir::Input => layout.inputs[port.index].width,
+            PortDir::Output => layout.outputs[port.index].width,
+        }
+    }
+
+    /// Concatenate the latched input ports, run the unit and latch the outputs.
+    fn fire(&mut self, base: u16, trigger: usize) {
+        let layout = self.layouts[&base].clone();
+        let mut input = Vec::with_capacity(layout.input_width());
+        for (i, spec) in layout.inputs.iter().enumerate() {
+            match self.port_latches.get(&(base + i as u16)) {
+                Some(v) => input.extend(v.iter()),
+                None => input.extend(std::iter::repeat_n(0.0, spec.width)),
+            }
+        }
+        let input = Array1::from(input);
+
+        let unit = if base >= 0x8000 { self.mmio.get_mut(&base) } else { self.units.get_mut(&base) };
+        let Some(unit) = unit else { return };
+        let mut output = unit.trigger(trigger, &input);
+        while let Some(request) = unit.mem_request() {
+            let response = match request {
+                // Stack transfers: a RAM access plus a PC update
+                MemRequest::Call { slot, target } => {
+                    self.accesses.push((slot, Access::Write));
+                    journal_ram(&mut self.ram_journal, &self.ram, slot);
+                    let link = Array1::from(encode_bits(self.pc as u32, 16));
+                    let response = store_mem(&mut self.ram, slot, link);
+                    if response == MemResponse::Stored {
+                        self.jump = Some(target);
+                    }
+                    response
+                }
+                MemRequest::JumpVia(slot) => {
+                    self.accesses.push((slot, Access::Read));
+                    match load_mem(&self.ram, slot) {
+                        MemResponse::Loaded(Some(link)) => {
+                            self.jump = Some(decode_bits(link.as_slice().unwrap_or(&[])) as u16);
+                            MemResponse::Loaded(Some(link))
+                        }
+                        MemResponse::Loaded(None) => {
+                            let msg = format!("no jump address at 0x{:X}", slot);
+                            self.bus_fault = Some(msg.clone());
+                            MemResponse::Fault(msg)
+                        }
+                        response => response,
+                    }
+                }
+                MemRequest::Load(addr) => {
+                    self.accesses.push((addr, Access::Read));
+                    load_mem(&self.ram, addr)
+                }
+                MemRequest::Store(addr, data) => {
+                    self.accesses.push((addr, Access::Write));
+                    journal_ram(&mut self.ram_journal, &self.ram, addr);
+                    store_mem(&mut self.ram, addr, data)
+                }
+                MemRequest::Resolve { base, offset, metric, floor } => {
+                    resolve_mem(&self.ram, &mut self.assoc_stats, &mut self.bus_fault, &base, offset, metric, floor)
+                }
+            };
+            if let Some(updated) = unit.mem_complete(response) {
+                output = updated;
+            }
+        }
+        if let Some(fault) = unit.take_fault() {
+            self.bus_fault = Some(fault);
+        }
+        self.fu_io_cache.insert(base, (input, output.clone()));
+
+        let ready = match self.pipelines.get_mut(&base) {
+            Some(pipeline) => {
+                if pipeline.is_blocked() {
+                    self.early_issues += 1;
+                }
+                pipeline.issue(output)
+            }
+            None => Some(output),
+        };
+        if let Some(output) = ready {
+            self.publish(base, &output);
+        }
+    }
+
+    /// A result reaches the output ports (and FLAGS, for flag units).
+    fn publish(&mut self, base: u16, output: &Array1<f32>) {
+        self.latch_outputs(base, output);
+        if self.flag_units.contains(&base) {
+            if let Some(flags) = self.registers.get_mut(&FLAGS_ADDR) {
+                flags.write(&fit_width(output, flags.width));
+            }
+        }
+    }
+
+    /// Split a unit's output vector across its output port latches.
+    fn latch_outputs(&mut self, base: u16, output: &Array1<f32>) {
+        let layout = &self.layouts[&base];
+        let mut offset = 0;
+        for (j, spec) in layout.outputs.iter().enumerate() {
+            let addr = base + (layout.inputs.len() + j) as u16;
+            let value = Array1::from_shape_fn(spec.width, |k| *output.get(offset + k).unwrap_or(&0.0));
+            self.port_latches.insert(addr, value);
+            offset += spec.width;
+        }
+    }
+
+    /// True if writing `addr` fires a port-mapped unit.
+    fn is_trigger(&self, addr: u16) -> bool {
+        self.ports.get(&addr).is_some_and(|p| {
+            p.dir == PortDir::Input && self.layouts[&p.base].is_trigger(p.index)
+        })
+    }
+
     /// The core System Dispatch
     pub fn execute(&mut self, op: &MoveOp) -> String {
-        // 0. Check Guard
-        if let Some(guard_addr) = op.guard {
-            let guard_val = self.read_mem(guard_addr);
-            // Check LSB or Sum > 0.5
-            if guard_val.get(0).unwrap_or(&0.0) <= &0.5 {
-                 return "Skipped (Guard Low)".to_string();
-            }
+        // A single move cannot conflict with itself.
+        match self.execute_bundle(std::slice::from_ref(op)) {
+            Ok(mut outcome) => outcome.logs.remove(0),
+            Err(e) => e.to_string(),
         }
+    }
 
-        // 1. Read Source
-        let data = self.read_mem(op.src);
+    /// Execute the moves of one cycle across parallel buses.
+    ///
+    /// Read-before-write: every guard and source is read from the state at the
+    /// start of the cycle, then the writes land. Operand ports are written before
+    /// triggers so a unit fired in the same cycle sees its new operands. Two
+    /// executing moves with the same destination are a conflict and nothing is written.
+    pub fn execute_bundle(&mut self, moves: &[MoveOp]) -> anyhow::Result<BundleOutcome> {
+        // 1. Read phase: guards, then sources of the moves that will execute
+        let mut reads = Vec::with_capacity(moves.len());
+        for op in moves {
+            let active = op.guard.is_none_or(|g| {
+                let guard_val = self.read_mem(g.addr);
+                g.eval(&guard_val)
+            });
+            reads.push(if active { Some(self.read_mem(op.src)) } else { None });
+        }
 
-        // 2. Write Destination
-        let dest_desc = self.write_mem(op.dest, &data);
-        
-        // Format Log: "Moved [0, 0, 0, 1...] to D"
-        // Show as integer vector for compactness if values are near 0/1
-        let vec_str: Vec<String> = data.iter().take(8).map(|&v| {
-            if (v - 1.0).abs() < 0.1 { "1".to_string() }
-            else if v.abs() < 0.1 { "0".to_string() }
-            else { format!("{:.1}", v) }
-        }).collect();
-        let val_str = format!("[{}]", vec_str.join(", "));
-        
-        format!("Moved {} to {}", val_str, dest_desc)
+        let mut dests = HashSet::new();
+        for (op, data) in moves.iter().zip(&reads) {
+            if data.is_some() && !dests.insert(op.dest) {
+                let name = self.port_name(op.dest).unwrap_or_else(|| format!("0x{:X}", op.dest));
+                anyhow::bail!("bus conflict: more than one move writes {}", name);
+            }
+        }
+
+        // 2. Write phase
+        let mut outcome = BundleOutcome { logs: vec![String::new(); moves.len()], executed: 0 };
+        let (triggers, operands): (Vec<usize>, Vec<usize>) = (0..moves.len()).partition(|&i| self.is_trigger(moves[i].dest));
+        for i in operands.into_iter().chain(triggers) {
+            outcome.logs[i] = match &reads[i] {
+                None => "Skipped (Guard Low)".to_string(),
+                Some(data) => {
+                    outcome.executed += 1;
+                    let dest_desc = self.write_mem(moves[i].dest, data);
+                    describe_move(data, &dest_desc)
+                }
+            };
+        }
+        Ok(outcome)
     }
 
     fn read_mem(&mut self, addr: u16) -> Array1<f32> {
+        self.accesses.push((addr, Access::Read));
+        if let Some(port) = self.ports.get(&addr) {
+            if port.dir == PortDir::Output && self.pipelines.get(&port.base).is_some_and(|p| p.is_pending()) {
+                self.stale_reads += 1;
+            }
+        }
+        self.peek(addr)
+    }
+
+    /// The value a move from `addr` would read, without counting it as an access.
+    pub fn peek(&self, addr: u16) -> Array1<f32> {
+        // Port-mapped units (FU sockets or MMIO devices)
+        if let Some(port) = self.ports.get(&addr) {
+            return self.port_latches.get(&addr).cloned()
+                .unwrap_or_else(|| Array1::zeros(self.port_width(port)));
+        }
+
+        if addr == PC_ADDR {
+            return Array1::from(encode_bits(self.pc as u32, 16));
+        }
         if addr < 0x1000 {
             // NRF
             if let Some(reg) = self.registers.get(&addr) {
                 return reg.read();
             }
+            // Single-bit views of FLAGS
+            if let (Some(flags), Some(bit)) = (self.registers.get(&FLAGS_ADDR), addr.checked_sub(FLAGS_ADDR + 1)) {
+                if let Some(&v) = flags.state.get(bit as usize) {
+                    return Array1::from(vec![v]);
+                }
+            }
         } else if addr < 0x2000 {
-            // FU Read (Output ports)
-            // Assuming (Addr & 0xFFF0) is Unit Base? 
-            // Simplified: If key exists in `units`, query it?
-            // Units usually provide output via `forward` return value or state.
-            // If we want to READ from a unit (like Status), we need `read()` trait method?
-            // For now, return Zeros mock.
+            // FU Read (Output ports are handled above; bare units have none)
         } else if addr < 0x8000 {
             // RAM
             if let Some(val) = self.ram.get(&addr) {
                 return val.clone();
             }
-        } else {
-             // MMIO Read (e.g. Keyboard)
-             if let Some(dev) = self.mmio.get_mut(&addr) {
-                  // Hack: using forward as read? Or specific read?
-                  // TTA usually reads from a "Output Register" of the Unit.
-                  // Let's assume MMIO read returns mock.
-             }
         }
+        // MMIO devices are read through their output ports (handled above)
         Array1::zeros(8) // Default
     }
 
     fn write_mem(&mut self, addr: u16, data: &Array1<f32>) -> String {
+        self.accesses.push((addr, Access::Write));
+        if let Some(port) = self.ports.get(&addr).copied() {
+            let name = self.port_name(addr).unwrap_or_default();
+            if port.dir == PortDir::Output {
+                return format!("ReadOnly[{}]", name);
+            }
+            self.port_latches.insert(addr, fit_width(data, self.port_width(&port)));
+            if self.layouts[&port.base].is_trigger(port.index) {
+                self.fire(port.base, port.index);
+            }
+            return name;
+        }
+
+        if addr == PC_ADDR {
+            self.jump = Some(decode_bits(data.as_slice().unwrap_or(&[])) as u16);
+            return "PC".to_string();
+        }
         if addr < 0x1000 {
             if let Some(reg) = self.registers.get_mut(&addr) {
-                reg.write(data);
+                // Narrow sources (flags, carry) zero-extend into the register.
+                reg.write(&fit_width(data, reg.width));
                 return format!("R{}", addr);
             }
         } else if addr < 0x2000 {
@@ -123,6 +571,7 @@ impl SystemBus {
             }
             
         } else if addr < 0x8000 {
+            journal_ram(&mut self.ram_journal, &self.ram, addr);
             self.ram.insert(addr, data.clone());
             return format!("RAM[0x{:X}]", addr);
         } else {
@@ -133,6 +582,9 @@ impl SystemBus {
                 if addr == 0x8000 { return "UART".to_string(); }
                 return format!("MMIO[0x{:X}]", addr);
             }
+            if addr >= STORAGE_BASE {
+                return format!("eMMC[0x{:X}]", addr);
+            }
         }
         format!("Unknown[0x{:X}]", addr)
     }
@@ -144,13 +596,150 @@ impl SystemBus {
         for dev in self.mmio.values_mut() {
             dev.tick();
         }
+        // Multi-cycle units publish progress (e.g. BUSY dropping) through their output ports.
+        let bases: Vec<u16> = self.layouts.keys().copied().collect();
+        for base in bases {
+            let unit = if base >= 0x8000 { self.mmio.get(&base) } else { self.units.get(&base) };
+            let Some(output) = unit.and_then(|u| u.poll()) else { continue };
+            self.latch_outputs(base, &output);
+            if let Some(entry) = self.fu_io_cache.get_mut(&base) {
+                entry.1 = output;
+            }
+        }
+        // Pipelined results that finished this cycle
+        let bases: Vec<u16> = self.pipelines.keys().copied().collect();
+        for base in bases {
+            let ready = self.pipelines.get_mut(&base).map(|p| p.tick()).unwrap_or_default();
+            for output in ready {
+                self.publish(base, &output);
+            }
+        }
         // PC tick logic needs to happen here too if PC is a unit.
+
+        // Interrupt sources
+        let mut raised: Vec<u8> = Vec::new();
+        for (base, line) in &self.irq_lines {
+            let unit = if *base >= 0x8000 { self.mmio.get_mut(base) } else { self.units.get_mut(base) };
+            if unit.is_some_and(|u| u.take_irq()) {
+                raised.push(*line);
+            }
+        }
+        for line in raised {
+            self.raise_irq(line);
+        }
+    }
+
+    /// Wire `source`'s interrupt requests to controller line `line`.
+    pub fn connect_irq(&mut self, source: u16, line: u8) -> anyhow::Result<()> {
+        if line as usize >= InterruptControllerFU::LINES {
+            anyhow::bail!("interrupt line {} out of range (0-{})", line, InterruptControllerFU::LINES - 1);
+        }
+        self.irq_lines.insert(source, line);
+        Ok(())
+    }
+
+    /// Set a pending bit on the controller, as if its RAISE port were written.
+    pub fn raise_irq(&mut self, line: u8) {
+        let Some(base) = self.irq_controller else { return };
+        let port = InterruptControllerFU::RAISE_PORT;
+        self.port_latches.insert(base + port as u16, Array1::from(encode_bits(1 << line, InterruptControllerFU::LINES)));
+        self.fire(base, port);
+    }
+
+    /// Enter the pending interrupt handler, if any, saving `resume` as the return PC.
+    /// Returns the handler address.
+    pub fn take_interrupt(&mut self, resume: u16) -> Option<u16> {
+        let base = self.irq_controller?;
+        let irq = base + (self.layouts.get(&base)?.inputs.len() + InterruptControllerFU::IRQ_OUTPUT) as u16;
+        if !self.port_latches.get(&irq).is_some_and(|v| v[0] > 0.5) {
+            return None;
+        }
+        let port = InterruptControllerFU::ACK_PORT;
+        self.port_latches.insert(base + port as u16, Array1::from(encode_bits(resume as u32, 16)));
+        self.jump = None;
+        self.fire(base, port);
+        self.jump.take()
+    }
+}
+
+/// Remember a RAM cell's contents before it is overwritten, if a step is being recorded.
+fn journal_ram(journal: &mut Option<RamJournal>, ram: &HashMap<u16, Array1<f32>>, addr: u16) {
+    if let Some(journal) = journal {
+        journal.push((addr, ram.get(&addr).cloned()));
+    }
+}
+
+/// Read a RAM cell on a unit's behalf. Only 0x2000 - 0x7FFF is RAM.
+fn load_mem(ram: &HashMap<u16, Array1<f32>>, addr: u16) -> MemResponse {
+    if !(0x2000..0x8000).contains(&addr) {
+        return MemResponse::Fault(format!("0x{:X} is not RAM", addr));
+    }
+    MemResponse::Loaded(ram.get(&addr).cloned())
+}
+
+/// Write a RAM cell on a unit's behalf. Only 0x2000 - 0x7FFF is RAM.
+fn store_mem(ram: &mut HashMap<u16, Array1<f32>>, addr: u16, data: Array1<f32>) -> MemResponse {
+    if !(0x2000..0x8000).contains(&addr) {
+        return MemResponse::Fault(format!("0x{:X} is not RAM", addr));
+    }
+    ram.insert(addr, data);
+    MemResponse::Stored
+}
+
+/// Associative lookup over the populated RAM cells. Refusals also raise a bus fault.
+fn resolve_mem(
+    ram: &HashMap<u16, Array1<f32>>,
+    assoc: &mut AssocStats,
+    bus_fault: &mut Option<String>,
+    base: &Array1<f32>,
+    offset: u16,
+    metric: AddrMetric,
+    floor: f32,
+) -> MemResponse {
+    assoc.lookups += 1;
+    let populated = ram.keys().copied().filter(|a| (0x2000..0x8000).contains(a));
+    let refused = match nearest_cell(base, offset, metric, populated) {
+        Some((addr, confidence)) if confidence >= floor => {
+            let decoded = decode_bits(base.as_slice().unwrap_or(&[])) as usize + offset as usize;
+            if decoded == addr as usize { assoc.exact += 1 } else { assoc.recovered += 1 }
+            assoc.confidence_sum += confidence;
+            return MemResponse::Resolved { addr, confidence };
+        }
+        Some((addr, confidence)) => {
+            format!("associative lookup: nearest cell 0x{:X} at confidence {:.2} < {:.2}", addr, confidence, floor)
+        }
+        None => "associative lookup: no populated RAM cell".to_string(),
+    };
+    assoc.faults += 1;
+    *bus_fault = Some(refused.clone());
+    MemResponse::Fault(refused)
+}
+
+/// Format Log: "Moved [0, 0, 0, 1...] to D"
+fn describe_move(data: &Array1<f32>, dest_desc: &str) -> String {
+    // Show as integer vector for compactness if values are near 0/1
+    let vec_str: Vec<String> = data.iter().take(8).map(|&v| {
+        if (v - 1.0).abs() < 0.1 { "1".to_string() }
+        else if v.abs() < 0.1 { "0".to_string() }
+        else { format!("{:.1}", v) }
+    }).collect();
+    let val_str = format!("[{}]", vec_str.join(", "));
+
+    format!("Moved {} to {}", val_str, dest_desc)
+}
+
+/// Truncate or zero-pad a vector to `width` elements.
+pub fn fit_width(data: &Array1<f32>, width: usize) -> Array1<f32> {
+    if data.len() == width {
+        return data.clone();
     }
+    Array1::from_shape_fn(width, |i| *data.get(i).unwrap_or(&0.0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fu::PortSpec;
 
     // Mock FU for testing bus dispatch
     #[derive(Debug, Clone)]
@@ -199,7 +788,7 @@ mod tests {
         
         // Case 1: Guard Low (0.0) -> No Move
         bus.write_mem(2, &Array1::from(vec![0.0]));
-        let op = MoveOp { src: 0, dest: 1, guard: Some(2) };
+        let op = MoveOp { src: 0, dest: 1, guard: Some(Guard::from(2)) };
         let res = bus.execute(&op);
         assert!(res.contains("Skipped"));
         assert_eq!(bus.read_mem(1)[0], 0.0);
@@ -210,4 +799,114 @@ mod tests {
         assert!(!res.contains("Skipped"));
         assert_eq!(bus.read_mem(1)[0], 1.0);
     }
+
+    #[test]
+    fn test_port_mapped_unit() {
+        let mut bus = SystemBus::new();
+        bus.add_register(0, 8);
+        let layout = PortLayout {
+            inputs: vec![PortSpec::new("A", 2), PortSpec::new("B", 2)],
+            outputs: vec![PortSpec::new("X", 1), PortSpec::new("Y", 2)],
+        };
+        bus.add_unit_with_ports(0x1000, Box::new(MockFU { last_in: Array1::zeros(0) }), layout.clone()).unwrap();
+
+        // Operand port only latches; trigger (last input) fires with both operands.
+        bus.write_mem(0x1000, &Array1::from(vec![1.0, 0.0, 1.0])); // Truncated to 2
+        assert!(bus.fu_io_cache.is_empty());
+        assert_eq!(bus.write_mem(0x1001, &Array1::from(vec![0.0, 1.0])), "FU[0x1000].B");
+        assert_eq!(bus.fu_io_cache[&0x1000].0, Array1::from(vec![1.0, 0.0, 0.0, 1.0]));
+
+        // Mock output [1, 2, 3] is split across X (1) and Y (2)
+        assert_eq!(bus.read_mem(0x1002), Array1::from(vec![1.0]));
+        assert_eq!(bus.read_mem(0x1003), Array1::from(vec![2.0, 3.0]));
+
+        // Narrow result zero-extends into an 8-bit register
+        bus.execute(&MoveOp { src: 0x1002, dest: 0, guard: None });
+        assert_eq!(bus.read_mem(0).len(), 8);
+        assert_eq!(bus.read_mem(0)[0], 1.0);
+
+        // Overlapping registration is rejected
+        assert!(bus.add_unit_with_ports(0x1003, Box::new(MockFU { last_in: Array1::zeros(0) }), layout).is_err());
+    }
+
+    #[test]
+    fn test_bundle_read_before_write() {
+        let mut bus = SystemBus::new();
+        for r in 0..3 {
+            bus.add_register(r, 1);
+        }
+        bus.write_mem(0, &Array1::from(vec![1.0]));
+
+        // Swap R0 and R1 in one cycle
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        let outcome = bus.execute_bundle(&[mv(0, 1), mv(1, 0)]).unwrap();
+        assert_eq!(outcome.executed, 2);
+        assert_eq!(bus.read_mem(0)[0], 0.0);
+        assert_eq!(bus.read_mem(1)[0], 1.0);
+
+        // Two live writes to R2 conflict and nothing lands
+        assert!(bus.execute_bundle(&[mv(1, 2), mv(0, 2)]).is_err());
+        assert_eq!(bus.read_mem(2)[0], 0.0);
+        // ...unless a guard (read before the writes) disables one of them
+        let guarded = MoveOp { src: 0, dest: 2, guard: Some(Guard::from(0)) };
+        let outcome = bus.execute_bundle(&[mv(1, 2), guarded.clone()]).unwrap();
+        assert_eq!(outcome.executed, 1);
+        assert_eq!(bus.read_mem(2)[0], 1.0);
+        assert_eq!(Bundle { moves: vec![mv(1, 2), guarded, mv(0, 2)] }.static_conflicts(), vec![2]);
+    }
+
+    #[test]
+    fn test_bundle_operands_land_before_trigger() {
+        let mut bus = SystemBus::new();
+        bus.add_register(0, 2);
+        let layout = PortLayout {
+            inputs: vec![PortSpec::new("A", 2), PortSpec::new("B", 2)],
+            outputs: vec![PortSpec::new("X", 3)],
+        };
+        bus.add_unit_with_ports(0x1000, Box::new(MockFU { last_in: Array1::zeros(0) }), layout).unwrap();
+        bus.write_mem(0, &Array1::from(vec![1.0, 1.0]));
+
+        // Trigger listed first still sees the operand written in the same cycle
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        bus.execute_bundle(&[mv(0, 0x1001), mv(0, 0x1000)]).unwrap();
+        assert_eq!(bus.fu_io_cache[&0x1000].0, Array1::from(vec![1.0, 1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_bundle_json_compatible_with_single_moves() {
+        let json = r#"[ { "src": 0, "dest": 1, "guard": null },
+                        [ { "src": 0, "dest": 1, "guard": null }, { "src": 1, "dest": 0, "guard": "!R2" } ] ]"#;
+        let program: Vec<Bundle> = serde_json::from_str(json).unwrap();
+        assert_eq!(program[0].moves.len(), 1);
+        assert_eq!(program[1].moves.len(), 2);
+        assert!(serde_json::to_string(&program[0]).unwrap().starts_with('{'));
+    }
+
+    #[test]
+    fn test_flags_register_guards() {
+        let mut bus = SystemBus::new();
+        bus.add_register(0, 8);
+        bus.add_register(1, 8);
+        let layout = PortLayout {
+            inputs: vec![PortSpec::new("A", 1)],
+            outputs: vec![PortSpec::new("X", 1), PortSpec::new("Y", 2)],
+        };
+        bus.add_unit_with_ports(0x1000, Box::new(MockFU { last_in: Array1::zeros(0) }), layout).unwrap();
+        bus.add_flag_unit(0x1000);
+        bus.write_mem(0, &Array1::from(vec![1.0; 8]));
+
+        // Firing copies the outputs [1, 2, 3] into FLAGS, zero-padded to 7 bits
+        bus.write_mem(0x1000, &Array1::from(vec![1.0]));
+        assert_eq!(bus.read_mem(FLAGS_ADDR).len(), 7);
+        assert_eq!(bus.read_mem(FLAGS_ADDR + 1), Array1::from(vec![1.0])); // FLAGS.Z
+        assert_eq!(bus.read_mem(FLAGS_ADDR + 4), Array1::from(vec![0.0])); // FLAGS.V
+        assert_eq!(flag_name(FLAGS_ADDR + 7).as_deref(), Some("FLAGS.LT"));
+        assert_eq!(flag_name(FLAGS_ADDR + 8), None);
+
+        // A guard on a single flag bit, via the alias or a bit-index guard
+        assert!(bus.execute(&MoveOp { src: 0, dest: 1, guard: Some(Guard::from(FLAGS_ADDR + 4)) }).contains("Skipped"));
+        assert!(!bus.execute(&MoveOp { src: 0, dest: 1, guard: Some(Guard::from(FLAGS_ADDR + 1)) }).contains("Skipped"));
+        assert!(bus.execute(&MoveOp { src: 0, dest: 1, guard: Some("FLAGS.V".parse().unwrap()) }).contains("Skipped"));
+        assert!(!bus.execute(&MoveOp { src: 0, dest: 1, guard: Some("!FLAGS.V".parse().unwrap()) }).contains("Skipped"));
+    }
 }
@@ -0,0 +1,241 @@
+use anyhow::{bail, Context};
+use ndarray::{s, Array1};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::kind::{decode_bits, encode_bits, PortLayout, PortSpec};
+use super::{MemRequest, MemResponse, NeuralFunctionalUnit};
+
+pub const SECTOR_SIZE: usize = 512;
+
+/// Raw disk image on the host: a whole number of 512-byte sectors.
+#[derive(Debug)]
+pub struct DiskImage {
+    file: File,
+    pub sectors: usize,
+}
+
+impl DiskImage {
+    /// Create (or truncate) a zero-filled image.
+    pub fn create(path: &Path, sectors: usize) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)
+            .with_context(|| format!("creating disk image {:?}", path))?;
+        file.set_len((sectors * SECTOR_SIZE) as u64)?;
+        Ok(Self { file, sectors })
+    }
+
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)
+            .with_context(|| format!("opening disk image {:?}", path))?;
+        let len = file.metadata()?.len() as usize;
+        if !len.is_multiple_of(SECTOR_SIZE) {
+            bail!("disk image {:?} is {} bytes, not a multiple of {}", path, len, SECTOR_SIZE);
+        }
+        Ok(Self { file, sectors: len / SECTOR_SIZE })
+    }
+
+    fn seek(&mut self, sector: usize) -> anyhow::Result<()> {
+        if sector >= self.sectors {
+            bail!("sector {} out of range (image has {})", sector, self.sectors);
+        }
+        self.file.seek(SeekFrom::Start((sector * SECTOR_SIZE) as u64))?;
+        Ok(())
+    }
+
+    pub fn read_sector(&mut self, sector: usize) -> anyhow::Result<Vec<u8>> {
+        self.seek(sector)?;
+        let mut buf = vec![0; SECTOR_SIZE];
+        self.file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Write one sector; shorter data is zero-padded.
+    pub fn write_sector(&mut self, sector: usize, data: &[u8]) -> anyhow::Result<()> {
+        if data.len() > SECTOR_SIZE {
+            bail!("{} bytes do not fit a {}-byte sector", data.len(), SECTOR_SIZE);
+        }
+        self.seek(sector)?;
+        let mut buf = data.to_vec();
+        buf.resize(SECTOR_SIZE, 0);
+        self.file.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+/// A sector transfer in progress, one RAM cell per `mem_request`.
+#[derive(Debug)]
+enum Transfer {
+    /// Disk -> RAM: `data[next..]` still to store.
+    Read { addr: u16, data: Vec<u8>, next: usize },
+    /// RAM -> disk: bytes gathered so far.
+    Write { sector: usize, addr: u16, data: Vec<u8> },
+}
+
+/// Block storage controller at the start of the eMMC region (0x9000).
+///
+/// Inputs: `SECTOR` (16) and `ADDR` (16, RAM address) select the transfer;
+/// writing `READ` (1) copies the sector into 512 consecutive 8-bit RAM cells,
+/// writing `WRITE` (1) copies 512 cells (unwritten ones read as 0) back to the
+/// image. Outputs: `ERROR` (1) for the last transfer and `SECTORS` (16), the
+/// image size.
+pub struct DiskFU {
+    pub image: DiskImage,
+    pub last_error: Option<String>,
+    error: bool,
+    transfer: Option<Transfer>,
+}
+
+impl DiskFU {
+    pub const READ_PORT: usize = 2;
+    pub const WRITE_PORT: usize = 3;
+
+    pub fn new(image: DiskImage) -> Self {
+        Self { image, last_error: None, error: false, transfer: None }
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![
+                PortSpec::new("SECTOR", 16),
+                PortSpec::new("ADDR", 16),
+                PortSpec::trigger("READ", 1),
+                PortSpec::trigger("WRITE", 1),
+            ],
+            outputs: vec![PortSpec::new("ERROR", 1), PortSpec::new("SECTORS", 16)],
+        }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        let mut out = vec![if self.error { 1.0 } else { 0.0 }];
+        out.extend(encode_bits(self.image.sectors.min(0xFFFF) as u32, 16));
+        Array1::from(out)
+    }
+
+    fn fail(&mut self, msg: String) {
+        self.error = true;
+        self.last_error = Some(msg);
+        self.transfer = None;
+    }
+}
+
+impl NeuralFunctionalUnit for DiskFU {
+    /// Without port information the input is SECTOR + ADDR and the sector is read.
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        self.trigger(Self::READ_PORT, input)
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn trigger(&mut self, port: usize, input: &Array1<f32>) -> Array1<f32> {
+        // Input: SECTOR (16) + ADDR (16) + READ (1) + WRITE (1)
+        let field = |start: usize, len: usize| {
+            let end = (start + len).min(input.len());
+            decode_bits(&input.slice(s![start.min(end)..end]).to_vec())
+        };
+        let (sector, addr) = (field(0, 16) as usize, field(16, 16) as u16);
+        self.error = false;
+        if addr < 0x2000 || addr as usize + SECTOR_SIZE > 0x8000 {
+            self.fail(format!("RAM 0x{:X}..+{} is not inside 0x2000-0x7FFF", addr, SECTOR_SIZE));
+        } else if sector >= self.image.sectors {
+            self.fail(format!("sector {} out of range (image has {})", sector, self.image.sectors));
+        } else if port == Self::WRITE_PORT {
+            self.transfer = Some(Transfer::Write { sector, addr, data: Vec::with_capacity(SECTOR_SIZE) });
+        } else {
+            match self.image.read_sector(sector) {
+                Ok(data) => self.transfer = Some(Transfer::Read { addr, data, next: 0 }),
+                Err(e) => self.fail(format!("{:#}", e)),
+            }
+        }
+        self.outputs()
+    }
+
+    fn mem_request(&mut self) -> Option<MemRequest> {
+        match self.transfer.as_ref()? {
+            Transfer::Read { addr, data, next } => {
+                Some(MemRequest::Store(addr + *next as u16, Array1::from(encode_bits(data[*next] as u32, 8))))
+            }
+            Transfer::Write { addr, data, .. } => Some(MemRequest::Load(addr + data.len() as u16)),
+        }
+    }
+
+    fn mem_complete(&mut self, response: MemResponse) -> Option<Array1<f32>> {
+        if let MemResponse::Fault(msg) = response {
+            self.fail(msg);
+            return Some(self.outputs());
+        }
+        match self.transfer.take()? {
+            Transfer::Read { addr, data, next } => {
+                if next + 1 < SECTOR_SIZE {
+                    self.transfer = Some(Transfer::Read { addr, data, next: next + 1 });
+                }
+            }
+            Transfer::Write { sector, addr, mut data } => {
+                let cell = match response {
+                    MemResponse::Loaded(cell) => cell,
+                    _ => None,
+                };
+                data.push(cell.map_or(0, |v| decode_bits(v.as_slice().unwrap_or(&[])) as u8));
+                if data.len() < SECTOR_SIZE {
+                    self.transfer = Some(Transfer::Write { sector, addr, data });
+                } else if let Err(e) = self.image.write_sector(sector, &data) {
+                    self.fail(format!("{:#}", e));
+                }
+            }
+        }
+        Some(self.outputs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{MoveOp, SystemBus};
+
+    #[test]
+    fn test_sector_roundtrip_through_ram() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("roundtrip.img");
+        let mut image = DiskImage::create(&path, 4).unwrap();
+        image.write_sector(1, b"HELLO").unwrap();
+
+        let disk = DiskFU::new(image);
+        let layout = disk.port_layout();
+        let mut bus = SystemBus::new();
+        bus.add_unit_with_ports(0x9000, Box::new(disk), layout).unwrap();
+        // Ports: SECTOR 0x9000, ADDR 0x9001, READ 0x9002, WRITE 0x9003, ERROR 0x9004, SECTORS 0x9005
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        let word = |v: u32| Array1::from(encode_bits(v, 16));
+        bus.ram.insert(0x7000, word(1));
+        bus.ram.insert(0x7001, word(0x3000));
+        bus.ram.insert(0x7002, word(2));
+        bus.ram.insert(0x7003, word(0x7F00)); // Runs past the end of RAM
+
+        for op in [mv(0x7000, 0x9000), mv(0x7001, 0x9001), mv(0x7000, 0x9002)] {
+            bus.execute(&op);
+        }
+        assert_eq!(bus.port_latches[&0x9004][0], 0.0);
+        assert_eq!(decode_bits(bus.port_latches[&0x9005].as_slice().unwrap()), 4);
+        assert_eq!(decode_bits(bus.ram[&0x3001].as_slice().unwrap()), b'E' as u32);
+        assert_eq!(bus.ram.keys().filter(|a| (0x3000..0x3200).contains(*a)).count(), SECTOR_SIZE);
+
+        // Patch RAM and write it to sector 2
+        bus.ram.insert(0x3000, Array1::from(encode_bits(b'J' as u32, 8)));
+        for op in [mv(0x7002, 0x9000), mv(0x7000, 0x9003)] {
+            bus.execute(&op);
+        }
+        drop(bus);
+        let mut image = DiskImage::open(&path).unwrap();
+        assert_eq!(&image.read_sector(2).unwrap()[..5], b"JELLO");
+
+        // Transfers that do not fit in RAM or the image are refused up front
+        let mut bus = SystemBus::new();
+        let disk = DiskFU::new(DiskImage::create(&path, 1).unwrap());
+        let layout = disk.port_layout();
+        bus.add_unit_with_ports(0x9000, Box::new(disk), layout).unwrap();
+        bus.ram.insert(0x7003, word(0x7F00));
+        bus.execute(&mv(0x7003, 0x9001));
+        bus.execute(&mv(0x7003, 0x9002));
+        assert_eq!(bus.port_latches[&0x9004][0], 1.0);
+    }
+}
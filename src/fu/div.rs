@@ -0,0 +1,185 @@
+use ndarray::Array1;
+
+use super::kind::decode_bits;
+use super::{BaseFU, NeuralFunctionalUnit};
+
+/// 8-bit neural divider: restoring division, one quotient bit per `tick()`.
+///
+/// The trained part is a full-subtractor cell `(r, d, borrow_in) -> (diff, borrow_out)`
+/// (8 cases). Each cycle shifts the next dividend bit into the partial remainder,
+/// ripples a trial subtraction of the divisor through 9 cells and keeps the
+/// difference only if the final borrow is clear, which is also the quotient bit.
+///
+/// Writing the trigger latches the operands and raises BUSY; QUOTIENT and
+/// REMAINDER are valid once BUSY drops, `WIDTH` ticks later. Re-triggering while
+/// busy restarts the division. Division by zero completes immediately with DIV0
+/// set, an all-ones quotient and the dividend as remainder (as on RISC-V).
+#[derive(Debug, Clone)]
+pub struct DividerFU<C: NeuralFunctionalUnit = BaseFU> {
+    pub cell: C,
+    dividend: Vec<f32>,
+    divisor: Vec<f32>,
+    /// Partial remainder, one bit wider than the operands.
+    remainder: Vec<f32>,
+    quotient: Vec<f32>,
+    div_zero: bool,
+    /// Division steps still to run; non-zero means busy.
+    remaining: usize,
+}
+
+impl<C: NeuralFunctionalUnit> DividerFU<C> {
+    pub const WIDTH: usize = 8;
+
+    pub fn new(cell: C) -> Self {
+        let n = Self::WIDTH;
+        Self {
+            cell,
+            dividend: vec![0.0; n],
+            divisor: vec![0.0; n],
+            remainder: vec![0.0; n + 1],
+            quotient: vec![0.0; n],
+            div_zero: false,
+            remaining: 0,
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.remaining > 0
+    }
+
+    /// One subtractor evaluation with cleanup: returns (diff, borrow) as 0.0/1.0.
+    fn sub(&mut self, r: f32, d: f32, borrow_in: f32) -> (f32, f32) {
+        let out = self.cell.forward(&Array1::from(vec![r, d, borrow_in]));
+        let snap = |v: f32| if v > 0.5 { 1.0 } else { 0.0 };
+        (snap(out[0]), snap(out[1]))
+    }
+
+    /// QUOTIENT (8) + REMAINDER (8) + DIV0 (1) + BUSY (1).
+    fn outputs(&self) -> Array1<f32> {
+        let n = Self::WIDTH;
+        let mut out = Vec::with_capacity(2 * n + 2);
+        out.extend(&self.quotient);
+        out.extend(&self.remainder[..n]);
+        out.push(if self.div_zero { 1.0 } else { 0.0 });
+        out.push(if self.is_busy() { 1.0 } else { 0.0 });
+        Array1::from(out)
+    }
+
+    /// Produce quotient bit `remaining - 1`.
+    fn step(&mut self) {
+        let n = Self::WIDTH;
+        let bit = self.remaining - 1;
+
+        // R = (R << 1) | dividend[bit]. R < divisor before the shift, so 9 bits suffice.
+        self.remainder.rotate_right(1);
+        self.remainder[0] = self.dividend[bit];
+
+        let mut diff = Vec::with_capacity(n + 1);
+        let mut borrow = 0.0;
+        for i in 0..=n {
+            let d = if i < n { self.divisor[i] } else { 0.0 };
+            let (x, b) = self.sub(self.remainder[i], d, borrow);
+            diff.push(x);
+            borrow = b;
+        }
+
+        let fits = borrow < 0.5;
+        if fits {
+            self.remainder = diff;
+        }
+        self.quotient[bit] = if fits { 1.0 } else { 0.0 };
+        self.remaining -= 1;
+    }
+}
+
+impl<C: NeuralFunctionalUnit> NeuralFunctionalUnit for DividerFU<C> {
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        // Input: DIVIDEND (8) + DIVISOR (8).
+        let n = Self::WIDTH;
+        let bit = |i: usize| if *input.get(i).unwrap_or(&0.0) > 0.5 { 1.0 } else { 0.0 };
+        self.dividend = (0..n).map(bit).collect();
+        self.divisor = (n..2 * n).map(bit).collect();
+        self.div_zero = decode_bits(&self.divisor) == 0;
+
+        if self.div_zero {
+            self.quotient = vec![1.0; n];
+            self.remainder = self.dividend.clone();
+            self.remainder.push(0.0);
+            self.remaining = 0;
+        } else {
+            self.quotient = vec![0.0; n];
+            self.remainder = vec![0.0; n + 1];
+            self.remaining = n;
+        }
+        self.outputs()
+    }
+
+    fn perturb(&mut self, amount: f32) {
+        self.cell.perturb(amount);
+    }
+
+    fn tick(&mut self) {
+        if self.is_busy() {
+            self.step();
+        }
+    }
+
+    fn poll(&self) -> Option<Array1<f32>> {
+        Some(self.outputs())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let state = (&self.dividend, &self.divisor, &self.remainder, &self.quotient, self.div_zero, self.remaining);
+        serde_json::to_value(state).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        (self.dividend, self.divisor, self.remainder, self.quotient, self.div_zero, self.remaining) =
+            serde_json::from_value(state)?;
+        Ok(())
+    }
+
+    fn weights(&self) -> Option<&BaseFU> {
+        self.cell.weights()
+    }
+
+    fn weights_mut(&mut self) -> Option<&mut BaseFU> {
+        self.cell.weights_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fu::kind::{bits_match, encode_bits, shipped_unit};
+    use crate::fu::FUType;
+
+    #[test]
+    fn test_shipped_cell_divides_over_ticks() {
+        let kind = FUType::DIV;
+        let cell = shipped_unit("alu_div", kind);
+        let mut div = DividerFU::new(cell);
+        let mut input = encode_bits(200, 8);
+        input.extend(encode_bits(7, 8));
+        let out = div.forward(&Array1::from(input));
+        assert_eq!(out[17], 1.0, "busy after trigger");
+        for _ in 0..DividerFU::<BaseFU>::WIDTH {
+            div.tick();
+        }
+        let out = div.poll().unwrap();
+        let out = out.as_slice().unwrap();
+        assert_eq!(decode_bits(&out[..8]), 28);
+        assert_eq!(decode_bits(&out[8..16]), 4);
+        assert_eq!(&out[16..], &[0.0, 0.0]);
+
+        // Sampled reference cases, including divide-by-zero (b = 0)
+        for idx in (0..kind.case_count()).step_by(97) {
+            let (input, target) = kind.case(idx);
+            div.forward(&input);
+            for _ in 0..kind.cycles() {
+                div.tick();
+            }
+            assert!(bits_match(&div.poll().unwrap(), &target), "case {}", idx);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use ndarray::{ArrayView1, ArrayViewD};
+
+use super::kind::PortLayout;
+
+/// Read-only view of a unit's internals for inspection tools (the GUI
+/// inspector, debuggers, scripts). Reached through
+/// `NeuralFunctionalUnit::introspect`.
+///
+/// The instance name is not here: it comes from the manifest and is kept by
+/// the bus next to the unit, see `SystemBus::unit_name`.
+pub trait Introspect {
+    /// Unit type, e.g. `"mlp"` or `"lsu"`.
+    fn kind(&self) -> &'static str;
+    /// Ports the unit expects on the bus, if it defines them itself.
+    fn port_layout(&self) -> Option<PortLayout> { None }
+    /// Trained tensors by name, in evaluation order.
+    fn tensors(&self) -> Vec<(&'static str, ArrayViewD<'_, f32>)> { Vec::new() }
+    /// Hidden-layer activations of the last forward pass.
+    fn hidden_activations(&self) -> Option<ArrayView1<'_, f32>> { None }
+    /// Internal registers and flags, formatted for display.
+    fn state(&self) -> Vec<(&'static str, String)> { Vec::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fu::{BaseFU, LoadStoreFU, NeuralFunctionalUnit, ProgramCounterFU, StackConfig, StackPointerFU, UartFU};
+    use ndarray::Array1;
+
+    #[test]
+    fn test_units_expose_internals() {
+        let mut mlp = BaseFU::create_random(4, 6, 2);
+        let view = mlp.introspect().unwrap();
+        let names: Vec<_> = view.tensors().iter().map(|(name, t)| (*name, t.shape().to_vec())).collect();
+        assert_eq!(names, vec![("w1", vec![6, 4]), ("b1", vec![6]), ("w2", vec![2, 6]), ("b2", vec![2])]);
+        assert!(view.hidden_activations().is_none());
+        mlp.forward(&Array1::ones(4));
+        assert_eq!(mlp.last_hidden.len(), 6);
+        assert_eq!(mlp.introspect().unwrap().hidden_activations(), Some(mlp.last_hidden.view()));
+
+        let stack = StackPointerFU::new(8, StackConfig { base: 0x7000, size: 16 });
+        let units: Vec<Box<dyn NeuralFunctionalUnit>> = vec![
+            Box::new(ProgramCounterFU::new()),
+            Box::new(LoadStoreFU::new(8)),
+            Box::new(stack),
+            Box::new(UartFU::new()),
+        ];
+        let kinds: Vec<_> = units.iter().map(|u| u.introspect().unwrap().kind()).collect();
+        assert_eq!(kinds, vec!["pc", "lsu", "stack", "uart"]);
+        let stack = units[2].introspect().unwrap();
+        assert!(stack.state().contains(&("sp", "0x7010".to_string())));
+        assert_eq!(stack.port_layout().unwrap().inputs[0].name, "SET_SP");
+        assert!(units[0].introspect().unwrap().port_layout().is_none());
+    }
+}
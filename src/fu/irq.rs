@@ -0,0 +1,164 @@
+use ndarray::{s, Array1};
+use std::collections::VecDeque;
+
+use super::kind::{decode_bits, encode_bits, PortLayout, PortSpec};
+use super::{MemRequest, MemResponse, NeuralFunctionalUnit};
+
+/// Vectored interrupt controller for 8 lines.
+///
+/// The vector table lives in RAM at `VECTORS`: cell `VECTORS + n` holds the
+/// handler address for line `n`, cell `VECTORS + 8` receives the interrupted PC.
+/// Line 0 has the highest priority. Handlers do not nest: while one runs
+/// (`ACTIVE`), further requests stay pending until `RETI`.
+///
+/// Inputs (all triggers):
+/// - `ENABLE` (8) sets the enable mask.
+/// - `VECTORS` (16) sets the table base.
+/// - `RAISE` (8) sets pending bits; the bus writes it for wired sources, programs may too.
+/// - `CLEAR` (8) clears pending bits.
+/// - `ACK` (16) enters the handler, saving the given PC. Written by the emulator.
+/// - `RETI` (1) jumps back to the saved PC.
+///
+/// `IRQ` is high while an enabled request waits and no handler is active.
+/// A `VECTORS` base whose table would leave RAM is a bus fault.
+#[derive(Debug, Clone)]
+pub struct InterruptControllerFU {
+    pub enabled: u8,
+    pub pending: u8,
+    pub vectors: u16,
+    pub active: bool,
+    requests: VecDeque<MemRequest>,
+    bus_fault: Option<String>,
+}
+
+impl InterruptControllerFU {
+    pub const LINES: usize = 8;
+    pub const ENABLE_PORT: usize = 0;
+    pub const VECTORS_PORT: usize = 1;
+    pub const RAISE_PORT: usize = 2;
+    pub const CLEAR_PORT: usize = 3;
+    pub const ACK_PORT: usize = 4;
+    pub const RETI_PORT: usize = 5;
+    /// Index of `IRQ` among the outputs.
+    pub const IRQ_OUTPUT: usize = 1;
+
+    pub fn new(vectors: u16) -> Self {
+        Self { enabled: 0, pending: 0, vectors, active: false, requests: VecDeque::new(), bus_fault: None }
+    }
+
+    /// The table and the saved-PC cell after it must lie inside RAM (0x2000 - 0x7FFF).
+    pub fn check_vectors(vectors: u16) -> anyhow::Result<()> {
+        let end = vectors as u32 + Self::LINES as u32;
+        if vectors < 0x2000 || end >= 0x8000 {
+            anyhow::bail!("vector table 0x{:X}..=0x{:X} is outside RAM (0x2000 - 0x7FFF)", vectors, end);
+        }
+        Ok(())
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![
+                PortSpec::trigger("ENABLE", Self::LINES),
+                PortSpec::trigger("VECTORS", 16),
+                PortSpec::trigger("RAISE", Self::LINES),
+                PortSpec::trigger("CLEAR", Self::LINES),
+                PortSpec::trigger("ACK", 16),
+                PortSpec::trigger("RETI", 1),
+            ],
+            outputs: vec![PortSpec::new("PENDING", Self::LINES), PortSpec::new("IRQ", 1), PortSpec::new("ACTIVE", 1)],
+        }
+    }
+
+    /// Highest-priority line that may be taken now.
+    pub fn next_line(&self) -> Option<usize> {
+        let ready = self.pending & self.enabled;
+        (!self.active && ready != 0).then(|| ready.trailing_zeros() as usize)
+    }
+
+    /// Cell that receives the interrupted PC.
+    pub fn saved_pc_slot(&self) -> u16 {
+        self.vectors + Self::LINES as u16
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        let mut out = encode_bits(self.pending as u32, Self::LINES);
+        out.push(if self.next_line().is_some() { 1.0 } else { 0.0 });
+        out.push(if self.active { 1.0 } else { 0.0 });
+        Array1::from(out)
+    }
+}
+
+impl NeuralFunctionalUnit for InterruptControllerFU {
+    /// Without port information the input raises lines.
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        let mut padded = vec![0.0; Self::LINES + 16];
+        padded.extend(input.iter());
+        self.trigger(Self::RAISE_PORT, &Array1::from(padded))
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn trigger(&mut self, port: usize, input: &Array1<f32>) -> Array1<f32> {
+        // Input: ENABLE (8) + VECTORS (16) + RAISE (8) + CLEAR (8) + ACK (16) + RETI (1)
+        let field = |start: usize, len: usize| {
+            let end = (start + len).min(input.len());
+            decode_bits(&input.slice(s![start.min(end)..end]).to_vec())
+        };
+        let n = Self::LINES;
+        match port {
+            Self::ENABLE_PORT => self.enabled = field(0, n) as u8,
+            Self::VECTORS_PORT => {
+                let vectors = field(n, 16) as u16;
+                match Self::check_vectors(vectors) {
+                    Ok(()) => self.vectors = vectors,
+                    Err(e) => self.bus_fault = Some(e.to_string()),
+                }
+            }
+            Self::RAISE_PORT => self.pending |= field(n + 16, n) as u8,
+            Self::CLEAR_PORT => self.pending &= !(field(2 * n + 16, n) as u8),
+            Self::ACK_PORT => {
+                if let Some(line) = self.next_line() {
+                    let resume = field(3 * n + 16, 16);
+                    self.pending &= !(1 << line);
+                    self.active = true;
+                    self.requests.push_back(MemRequest::Store(self.saved_pc_slot(), Array1::from(encode_bits(resume, 16))));
+                    self.requests.push_back(MemRequest::JumpVia(self.vectors + line as u16));
+                }
+            }
+            _ => {
+                if self.active {
+                    self.active = false;
+                    self.requests.push_back(MemRequest::JumpVia(self.saved_pc_slot()));
+                }
+            }
+        }
+        self.outputs()
+    }
+
+    fn mem_request(&mut self) -> Option<MemRequest> {
+        self.requests.pop_front()
+    }
+
+    fn mem_complete(&mut self, response: MemResponse) -> Option<Array1<f32>> {
+        if let MemResponse::Fault(_) = response {
+            // Without a vector there is nowhere to go; the bus has already stopped the machine.
+            self.requests.clear();
+        }
+        Some(self.outputs())
+    }
+
+    fn take_fault(&mut self) -> Option<String> {
+        self.bus_fault.take()
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value((self.enabled, self.pending, self.vectors, self.active)).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        let (enabled, pending, vectors, active) = serde_json::from_value(state)?;
+        Self::check_vectors(vectors)?;
+        (self.enabled, self.pending, self.vectors, self.active) = (enabled, pending, vectors, active);
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::kind::{encode_bits, PortLayout, PortSpec};
+use super::NeuralFunctionalUnit;
+
+/// Host keys of the CHIP-8 keypad, by key value:
+/// ```text
+/// 1 2 3 C        1 2 3 4
+/// 4 5 6 D   <-   Q W E R
+/// 7 8 9 E        A S D F
+/// A 0 B F        Z X C V
+/// ```
+pub const KEYPAD_KEYS: [char; 16] = ['X', '1', '2', '3', 'Q', 'W', 'E', 'A', 'S', 'D', 'Z', 'C', '4', 'R', 'F', 'V'];
+
+/// Keypad value (0x0-0xF) of a host key, if it is one of the 16.
+pub fn keypad_value(key: char) -> Option<u8> {
+    KEYPAD_KEYS.iter().position(|&k| k == key.to_ascii_uppercase()).map(|i| i as u8)
+}
+
+/// A key going down or up. `scancode` is the raw host code (ASCII for printable keys).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub scancode: u8,
+    pub down: bool,
+}
+
+impl KeyEvent {
+    pub fn key(key: char, down: bool) -> Self {
+        Self { scancode: key.to_ascii_uppercase() as u8, down }
+    }
+}
+
+/// Events from the GUI, drained by the keypad on every tick.
+#[derive(Debug, Clone, Default)]
+pub struct KeyQueue(Arc<Mutex<VecDeque<KeyEvent>>>);
+
+impl KeyQueue {
+    pub fn push(&self, event: KeyEvent) {
+        if let Ok(mut queue) = self.0.lock() {
+            queue.push_back(event);
+        }
+    }
+
+    fn drain(&self) -> Vec<KeyEvent> {
+        self.0.lock().map(|mut q| q.drain(..).collect()).unwrap_or_default()
+    }
+}
+
+/// One step of a headless key timeline, e.g. `{ "tick": 10, "key": "W", "down": true }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptedKey {
+    pub tick: usize,
+    pub key: char,
+    #[serde(default = "default_down")]
+    pub down: bool,
+}
+
+fn default_down() -> bool {
+    true
+}
+
+/// 16-key keypad at 0x8200.
+///
+/// Outputs: `KEYS` (16, bit n = key n held), `KEY_DOWN` (1, any key held),
+/// `LAST_KEY` (4, value of the last keypad key pressed), `SCANCODE` (8, raw code
+/// of the last key event of any kind) and `PRESSED` (1, a keypad key went down
+/// since the last write to `ACK`). Guards test keys directly, e.g.
+/// `0x8201.5` for key 5 or `!0x8202` for "no key held". A press also raises
+/// the unit's interrupt line if it is wired to one.
+#[derive(Debug, Clone, Default)]
+pub struct KeypadFU {
+    pub queue: KeyQueue,
+    /// Scripted events, in tick order; each is applied once the tick count reaches it.
+    pub script: VecDeque<ScriptedKey>,
+    pub ticks: usize,
+    held: u16,
+    last_key: u8,
+    scancode: u8,
+    pressed: bool,
+    irq: bool,
+}
+
+impl KeypadFU {
+    pub fn new(queue: KeyQueue) -> Self {
+        Self { queue, ..Self::default() }
+    }
+
+    pub fn with_script(self, mut script: Vec<ScriptedKey>) -> Self {
+        script.sort_by_key(|k| k.tick);
+        Self { script: script.into(), ..self }
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![PortSpec::trigger("ACK", 1)],
+            outputs: vec![
+                PortSpec::new("KEYS", 16),
+                PortSpec::new("KEY_DOWN", 1),
+                PortSpec::new("LAST_KEY", 4),
+                PortSpec::new("SCANCODE", 8),
+                PortSpec::new("PRESSED", 1),
+            ],
+        }
+    }
+
+    fn apply(&mut self, event: KeyEvent) {
+        self.scancode = event.scancode;
+        let Some(value) = keypad_value(event.scancode as char) else { return };
+        if event.down {
+            if self.held & (1 << value) == 0 {
+                self.pressed = true;
+                self.irq = true;
+            }
+            self.held |= 1 << value;
+            self.last_key = value;
+        } else {
+            self.held &= !(1 << value);
+        }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        let mut out = encode_bits(self.held as u32, 16);
+        out.push(if self.held != 0 { 1.0 } else { 0.0 });
+        out.extend(encode_bits(self.last_key as u32, 4));
+        out.extend(encode_bits(self.scancode as u32, 8));
+        out.push(if self.pressed { 1.0 } else { 0.0 });
+        Array1::from(out)
+    }
+}
+
+impl NeuralFunctionalUnit for KeypadFU {
+    /// Any write acknowledges `PRESSED`.
+    fn forward(&mut self, _input: &Array1<f32>) -> Array1<f32> {
+        self.pressed = false;
+        self.outputs()
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+        while self.script.front().is_some_and(|k| k.tick <= self.ticks) {
+            let step = self.script.pop_front().unwrap();
+            self.apply(KeyEvent::key(step.key, step.down));
+        }
+        for event in self.queue.drain() {
+            self.apply(event);
+        }
+    }
+
+    fn poll(&self) -> Option<Array1<f32>> {
+        Some(self.outputs())
+    }
+
+    fn take_irq(&mut self) -> bool {
+        std::mem::take(&mut self.irq)
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let state = (self.ticks, &self.script, self.held, self.last_key, self.scancode, self.pressed, self.irq);
+        serde_json::to_value(state).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        (self.ticks, self.script, self.held, self.last_key, self.scancode, self.pressed, self.irq) =
+            serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{MoveOp, SystemBus};
+    use crate::fu::kind::decode_bits;
+    use crate::guard::Guard;
+
+    #[test]
+    fn test_scripted_keys_drive_guards() {
+        let script: Vec<ScriptedKey> = serde_json::from_str(
+            r#"[{ "tick": 2, "key": "w" }, { "tick": 4, "key": "w", "down": false }, { "tick": 3, "key": "p" }]"#,
+        ).unwrap();
+        let queue = KeyQueue::default();
+        let keypad = KeypadFU::new(queue.clone()).with_script(script);
+        let layout = keypad.port_layout();
+        let mut bus = SystemBus::new();
+        bus.add_register(0, 8);
+        bus.add_register(1, 8);
+        bus.add_unit_with_ports(0x8200, Box::new(keypad), layout).unwrap();
+        // Ports: ACK 0x8200, KEYS 0x8201, KEY_DOWN 0x8202, LAST_KEY 0x8203, SCANCODE 0x8204, PRESSED 0x8205
+        let held_5 = MoveOp { src: 0, dest: 1, guard: Some(Guard::bit(0x8201, 5)) };
+        let latch = |bus: &SystemBus, addr: u16| decode_bits(bus.port_latches[&addr].as_slice().unwrap());
+
+        bus.tick_all();
+        assert!(bus.execute(&held_5).contains("Skipped"));
+        bus.tick_all(); // W (key 5) down
+        assert_eq!((latch(&bus, 0x8202), latch(&bus, 0x8203), latch(&bus, 0x8205)), (1, 5, 1));
+        assert!(!bus.execute(&held_5).contains("Skipped"));
+
+        bus.tick_all(); // P is not on the keypad: only SCANCODE changes
+        assert_eq!((latch(&bus, 0x8201), latch(&bus, 0x8204)), (1 << 5, b'P' as u32));
+        bus.execute(&MoveOp { src: 0, dest: 0x8200, guard: None });
+        assert_eq!(latch(&bus, 0x8205), 0);
+
+        bus.tick_all(); // W up
+        assert_eq!(latch(&bus, 0x8202), 0);
+
+        // Live events (as from the GUI) land on the next tick
+        queue.push(KeyEvent::key('v', true));
+        bus.tick_all();
+        assert_eq!((latch(&bus, 0x8201), latch(&bus, 0x8203)), (1 << 0xF, 0xF));
+    }
+}
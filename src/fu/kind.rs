@@ -0,0 +1,475 @@
+use ndarray::Array1;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::div::DividerFU;
+use super::mul::MultiplierFU;
+use super::shift::{ShifterFU, SHIFT_MODE_RIGHT, SHIFT_MODE_ROTATE};
+use super::{BaseFU, NeuralFunctionalUnit};
+
+/// The kinds of Functional Unit the toolchain knows how to build, train and verify.
+/// Variant names double as the `"type"` strings in the FU manifest.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum FUType {
+    ADDER,
+    CMP,
+    BITWISE,
+    PC,
+    MUL,
+    SHIFT,
+    DIV,
+}
+
+/// A single named port on an FU. Width is in bits (= vector elements).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PortSpec {
+    pub name: String,
+    pub width: usize,
+    /// Explicit trigger input. Only needed for units with several triggers.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub trigger: bool,
+}
+
+impl PortSpec {
+    pub fn new(name: &str, width: usize) -> Self {
+        Self { name: name.to_string(), width, trigger: false }
+    }
+
+    pub fn trigger(name: &str, width: usize) -> Self {
+        Self { trigger: true, ..Self::new(name, width) }
+    }
+}
+
+/// Describes how the flat input/output vectors of an FU are split into ports.
+/// Ports are concatenated in declaration order. By TTA convention the last
+/// input port is the trigger, unless inputs are marked as triggers explicitly.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PortLayout {
+    pub inputs: Vec<PortSpec>,
+    pub outputs: Vec<PortSpec>,
+}
+
+impl PortLayout {
+    pub fn input_width(&self) -> usize {
+        self.inputs.iter().map(|p| p.width).sum()
+    }
+
+    pub fn output_width(&self) -> usize {
+        self.outputs.iter().map(|p| p.width).sum()
+    }
+
+    /// True if writing input `index` fires the unit.
+    pub fn is_trigger(&self, index: usize) -> bool {
+        if self.inputs.iter().any(|p| p.trigger) {
+            self.inputs.get(index).is_some_and(|p| p.trigger)
+        } else {
+            index + 1 == self.inputs.len()
+        }
+    }
+}
+
+/// Flag outputs of the CMP unit, in port order. Z/N/C/V describe `A - B`
+/// (C is the borrow); GT/EQ/LT follow the SIGNED input.
+pub const CMP_FLAGS: [&str; 7] = ["Z", "N", "C", "V", "GT", "EQ", "LT"];
+
+/// Operations selected by the BITWISE unit's one-hot MODE port, one wire each
+/// in this order. The first three are the original 3-wire port: a 3-bit
+/// one-hot value is zero-extended on the bus and still selects the same operation.
+pub const BITWISE_MODES: [&str; 5] = ["AND", "OR", "XOR", "NOT", "NAND"];
+
+/// Operations selected by the SHIFT unit's MODE port (bit 0 = right, bit 1 = rotate).
+pub const SHIFT_MODES: [&str; 4] = ["LSH", "RSH", "ROL", "ROR"];
+
+/// How symbolic integers are laid out in the neural vectors.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BitEncoding {
+    /// One element per bit, 0.0 / 1.0, least significant bit first.
+    #[default]
+    UnipolarLsbFirst,
+}
+
+/// Encode the low `width` bits of `val` as a unipolar LSB-first vector.
+pub fn encode_bits(val: u32, width: usize) -> Vec<f32> {
+    (0..width).map(|i| if (val >> i) & 1 == 1 { 1.0 } else { 0.0 }).collect()
+}
+
+/// Decode a (possibly noisy) unipolar LSB-first vector by thresholding at 0.5.
+pub fn decode_bits(bits: &[f32]) -> u32 {
+    bits.iter().enumerate().fold(0, |acc, (i, &v)| if v > 0.5 { acc | (1 << i) } else { acc })
+}
+
+/// True if `output` thresholds to exactly the same bit pattern as `target`.
+pub fn bits_match(output: &Array1<f32>, target: &Array1<f32>) -> bool {
+    output.len() == target.len()
+        && output.iter().zip(target.iter()).all(|(&o, &t)| (o > 0.5) == (t > 0.5))
+}
+
+/// Result of running an FU against its reference behaviour.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Accuracy {
+    pub cases: usize,
+    /// Cases where any output bit was wrong.
+    pub errors: usize,
+    /// Output bits checked (cases * output width).
+    pub bits: usize,
+    pub bit_errors: usize,
+}
+
+impl Accuracy {
+    pub fn ratio(&self) -> f32 {
+        if self.cases == 0 { return 0.0; }
+        (self.cases - self.errors) as f32 / self.cases as f32
+    }
+
+    pub fn bit_ratio(&self) -> f32 {
+        if self.bits == 0 { return 0.0; }
+        (self.bits - self.bit_errors) as f32 / self.bits as f32
+    }
+}
+
+impl FUType {
+    pub fn port_layout(&self) -> PortLayout {
+        let p = PortSpec::new;
+        match self {
+            FUType::ADDER => PortLayout {
+                inputs: vec![p("A", 8), p("B", 8)],
+                outputs: vec![p("SUM", 8), p("CARRY", 1)],
+            },
+            FUType::CMP => PortLayout {
+                inputs: vec![p("A", 8), p("B", 8), p("SIGNED", 1)],
+                outputs: CMP_FLAGS.iter().map(|name| p(name, 1)).collect(),
+            },
+            FUType::BITWISE => PortLayout {
+                inputs: vec![p("A", 8), p("B", 8), p("MODE", BITWISE_MODES.len())],
+                outputs: vec![p("RESULT", 8)],
+            },
+            FUType::PC => PortLayout {
+                inputs: vec![p("TARGET", 8)],
+                outputs: vec![p("PC", 8)],
+            },
+            FUType::MUL => PortLayout {
+                inputs: vec![p("A", 8), p("B", 8)],
+                outputs: vec![p("LO", 8), p("HI", 8)],
+            },
+            FUType::SHIFT => PortLayout {
+                inputs: vec![p("A", 8), p("AMOUNT", 3), p("MODE", 2)],
+                outputs: vec![p("RESULT", 8), p("CARRY", 1)],
+            },
+            FUType::DIV => PortLayout {
+                inputs: vec![p("DIVIDEND", 8), p("DIVISOR", 8)],
+                outputs: vec![p("QUOTIENT", 8), p("REMAINDER", 8), p("DIV0", 1), p("BUSY", 1)],
+            },
+        }
+    }
+
+    /// Shape `(inputs, hidden, outputs)` of the trainable MLP behind this kind.
+    /// Monolithic units map ports straight onto the network; decomposed units
+    /// (MUL, SHIFT, DIV) train a small cell that `build` wires up. `None` for structural units.
+    pub fn network_shape(&self) -> Option<(usize, usize, usize)> {
+        let layout = self.port_layout();
+        let monolithic = |hidden| Some((layout.input_width(), hidden, layout.output_width()));
+        match self {
+            FUType::ADDER => monolithic(32),
+            FUType::CMP => monolithic(48),
+            FUType::BITWISE => monolithic(32),
+            FUType::PC => None,
+            FUType::MUL => Some((4, 8, 2)),
+            FUType::SHIFT => Some((3, 6, 1)),
+            FUType::DIV => Some((3, 8, 2)),
+        }
+    }
+
+    /// Build a randomly initialised (untrained) network for this kind.
+    pub fn create_unit<R: Rng>(&self, rng: &mut R) -> Option<BaseFU> {
+        let (inputs, hidden, outputs) = self.network_shape()?;
+        Some(BaseFU::create_random_with(rng, inputs, hidden, outputs))
+    }
+
+    /// Wrap trained weights into the functional unit that exposes `port_layout`.
+    pub fn build(&self, weights: BaseFU) -> Box<dyn NeuralFunctionalUnit> {
+        self.build_with(weights)
+    }
+
+    /// `build` for any network implementation (e.g. a `QuantizedFU`).
+    pub fn build_with<N: NeuralFunctionalUnit + 'static>(&self, net: N) -> Box<dyn NeuralFunctionalUnit> {
+        match self {
+            FUType::MUL => Box::new(MultiplierFU::new(net)),
+            FUType::SHIFT => Box::new(ShifterFU::new(net)),
+            FUType::DIV => Box::new(DividerFU::new(net)),
+            _ => Box::new(net),
+        }
+    }
+
+    /// Guess the kind of a bare weight dump from its tensor shapes.
+    pub fn infer(fu: &BaseFU) -> Option<FUType> {
+        let shape = (fu.w1.ncols(), fu.w2.nrows());
+        [FUType::ADDER, FUType::CMP, FUType::BITWISE, FUType::MUL, FUType::SHIFT, FUType::DIV].into_iter().find(|k| {
+            k.network_shape().is_some_and(|(i, _, o)| (i, o) == shape)
+        })
+    }
+
+    /// Number of cases the network itself is trained on. Equal to
+    /// `case_count` for monolithic units, the cell truth table otherwise.
+    pub fn train_case_count(&self) -> usize {
+        match self {
+            FUType::MUL => 16,
+            FUType::SHIFT | FUType::DIV => 8,
+            _ => self.case_count(),
+        }
+    }
+
+    pub fn train_case(&self, idx: usize) -> (Array1<f32>, Array1<f32>) {
+        match self {
+            FUType::MUL => {
+                // Multiply-accumulate cell: a*b + sum_in + carry_in
+                let input = encode_bits(idx as u32, 4);
+                let total = (idx & 1) * ((idx >> 1) & 1) + ((idx >> 2) & 1) + ((idx >> 3) & 1);
+                (Array1::from(input), Array1::from(encode_bits(total as u32, 2)))
+            }
+            FUType::SHIFT => {
+                // 2:1 mux cell: (sel, x0, x1) -> sel ? x1 : x0
+                let input = encode_bits(idx as u32, 3);
+                let out = if idx & 1 == 1 { (idx >> 2) & 1 } else { (idx >> 1) & 1 };
+                (Array1::from(input), Array1::from(encode_bits(out as u32, 1)))
+            }
+            FUType::DIV => {
+                // Full subtractor cell: r - d - borrow_in -> (diff, borrow_out)
+                let input = encode_bits(idx as u32, 3);
+                let diff = (idx & 1) as i32 - ((idx >> 1) & 1) as i32 - ((idx >> 2) & 1) as i32;
+                let target = vec![(diff & 1) as f32, if diff < 0 { 1.0 } else { 0.0 }];
+                (Array1::from(input), Array1::from(target))
+            }
+            _ => self.case(idx),
+        }
+    }
+
+    /// A uniformly random training case, for SGD.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> (Array1<f32>, Array1<f32>) {
+        self.train_case(rng.gen_range(0..self.train_case_count()))
+    }
+
+    /// Exhaustive accuracy of the bare network on its own training truth table.
+    pub fn verify_network(&self, net: &mut BaseFU) -> Accuracy {
+        verify_cases(net, self.train_case_count(), 0, |i| self.train_case(i))
+    }
+
+    /// Ticks a built unit needs after its trigger before the outputs are final.
+    pub fn cycles(&self) -> usize {
+        match self {
+            FUType::DIV => DividerFU::<BaseFU>::WIDTH,
+            _ => 0,
+        }
+    }
+
+    /// Number of distinct input cases in the unit's truth table.
+    /// Zero for kinds with no trainable reference behaviour.
+    pub fn case_count(&self) -> usize {
+        match self {
+            FUType::ADDER | FUType::MUL | FUType::DIV => 1 << 16,
+            FUType::CMP => 2 << 16,
+            FUType::BITWISE => BITWISE_MODES.len() << 16,
+            FUType::SHIFT => 1 << 13,
+            FUType::PC => 0,
+        }
+    }
+
+    /// The `idx`-th (input, target) pair of the unit's truth table.
+    pub fn case(&self, idx: usize) -> (Array1<f32>, Array1<f32>) {
+        if *self == FUType::SHIFT {
+            return shift_case(idx);
+        }
+        let a = (idx & 0xFF) as u8;
+        let b = ((idx >> 8) & 0xFF) as u8;
+        let mut input = encode_bits(a as u32, 8);
+        input.extend(encode_bits(b as u32, 8));
+
+        let target = match self {
+            FUType::ADDER => {
+                let sum = (a as u16) + (b as u16);
+                let mut target = encode_bits((sum & 0xFF) as u32, 8); // Sum low byte
+                target.push(if sum > 0xFF { 1.0 } else { 0.0 }); // Carry bit
+                target
+            }
+            FUType::CMP => {
+                let signed = idx >> 16 == 1;
+                input.push(if signed { 1.0 } else { 0.0 });
+                cmp_flags(a, b, signed).iter().map(|&f| if f { 1.0 } else { 0.0 }).collect()
+            }
+            FUType::BITWISE => {
+                // Mode is one-hot, see BITWISE_MODES
+                let mode = idx >> 16;
+                input.extend(encode_bits(1 << mode, BITWISE_MODES.len()));
+                let res = match mode {
+                    0 => a & b,
+                    1 => a | b,
+                    2 => a ^ b,
+                    3 => !a,
+                    _ => !(a & b),
+                };
+                encode_bits(res as u32, 8)
+            }
+            FUType::MUL => encode_bits(a as u32 * b as u32, 16),
+            FUType::DIV => {
+                // Quotient, remainder, DIV0, BUSY (always clear once finished)
+                let (q, r) = a.checked_div(b).map_or((0xFF, a), |q| (q, a % b));
+                let mut target = encode_bits(q as u32, 8);
+                target.extend(encode_bits(r as u32, 8));
+                target.extend([if b == 0 { 1.0 } else { 0.0 }, 0.0]);
+                target
+            }
+            FUType::PC | FUType::SHIFT => Vec::new(),
+        };
+
+        (Array1::from(input), Array1::from(target))
+    }
+
+    /// Run every case of the unit's truth table through `fu` (as returned by
+    /// `build`) and count bit-exact mismatches. Multi-cycle units are ticked to completion.
+    pub fn verify_exhaustive(&self, fu: &mut dyn NeuralFunctionalUnit) -> Accuracy {
+        verify_cases(fu, self.case_count(), self.cycles(), |i| self.case(i))
+    }
+}
+
+/// Two's-complement flags of `a - b`, in `CMP_FLAGS` order.
+pub fn cmp_flags(a: u8, b: u8, signed: bool) -> [bool; 7] {
+    let (diff, borrow) = a.overflowing_sub(b);
+    let overflow = (a ^ b) & (a ^ diff) & 0x80 != 0;
+    let ordering = if signed { (a as i8).cmp(&(b as i8)) } else { a.cmp(&b) };
+    [
+        diff == 0,
+        diff & 0x80 != 0,
+        borrow,
+        overflow,
+        ordering.is_gt(),
+        ordering.is_eq(),
+        ordering.is_lt(),
+    ]
+}
+
+/// SHIFT truth table: idx = A | AMOUNT << 8 | MODE << 11.
+fn shift_case(idx: usize) -> (Array1<f32>, Array1<f32>) {
+    let a = (idx & 0xFF) as u8;
+    let amount = ((idx >> 8) & 0x7) as u32;
+    let mode = ((idx >> 11) & 0x3) as u32;
+    let mut input = encode_bits(a as u32, 8);
+    input.extend(encode_bits(amount, 3));
+    input.extend(encode_bits(mode, 2));
+
+    let rotate = mode & SHIFT_MODE_ROTATE != 0;
+    let right = mode & SHIFT_MODE_RIGHT != 0;
+    // Carry is the last bit moved out of the word; nothing moves for amount 0.
+    let (res, carry) = match (rotate, right) {
+        _ if amount == 0 => (a, 0),
+        (false, false) => (a << amount, (a >> (8 - amount)) & 1),
+        (false, true) => (a >> amount, (a >> (amount - 1)) & 1),
+        (true, false) => (a.rotate_left(amount), a.rotate_left(amount) & 1),
+        (true, true) => (a.rotate_right(amount), a.rotate_right(amount) >> 7),
+    };
+    let mut target = encode_bits(res as u32, 8);
+    target.push(carry as f32);
+    (Array1::from(input), Array1::from(target))
+}
+
+fn verify_cases(
+    fu: &mut dyn NeuralFunctionalUnit,
+    cases: usize,
+    cycles: usize,
+    case: impl Fn(usize) -> (Array1<f32>, Array1<f32>),
+) -> Accuracy {
+    let mut acc = Accuracy { cases, errors: 0, bits: 0, bit_errors: 0 };
+    for idx in 0..cases {
+        let (input, target) = case(idx);
+        let mut output = fu.forward(&input);
+        if cycles > 0 {
+            for _ in 0..cycles {
+                fu.tick();
+            }
+            output = fu.poll().unwrap_or(output);
+        }
+        if !bits_match(&output, &target) {
+            acc.errors += 1;
+        }
+        acc.bits += target.len();
+        acc.bit_errors += target.iter().zip(output.iter())
+            .filter(|(&t, &o)| (t > 0.5) != (o > 0.5))
+            .count();
+    }
+    acc
+}
+
+/// Cell weights shipped in `assets/fus/<asset>.json`, checked exact on `kind`'s training table.
+#[cfg(test)]
+pub(crate) fn shipped_unit(asset: &str, kind: FUType) -> BaseFU {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fus").join(format!("{}.json", asset));
+    let mut cell = BaseFU::load_weights(&path).unwrap();
+    assert_eq!(kind.verify_network(&mut cell).errors, 0, "{} is not exact", asset);
+    cell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_matches_case_shapes() {
+        for kind in [FUType::ADDER, FUType::CMP, FUType::BITWISE, FUType::MUL, FUType::SHIFT, FUType::DIV] {
+            let layout = kind.port_layout();
+            let (input, target) = kind.case(kind.case_count() - 1);
+            assert_eq!(input.len(), layout.input_width());
+            assert_eq!(target.len(), layout.output_width());
+        }
+    }
+
+    #[test]
+    fn test_infer_from_shape() {
+        assert_eq!(FUType::infer(&BaseFU::create_adder()), Some(FUType::ADDER));
+        assert_eq!(FUType::infer(&BaseFU::create_comparator()), Some(FUType::CMP));
+        assert_eq!(FUType::infer(&BaseFU::create_bitwise()), Some(FUType::BITWISE));
+        assert_eq!(FUType::infer(&BaseFU::create_random(4, 4, 4)), None);
+    }
+
+    #[test]
+    fn test_adder_reference() {
+        // 200 + 100 = 300 -> low byte 44, carry 1
+        let (input, target) = FUType::ADDER.case(200 | (100 << 8));
+        assert_eq!(decode_bits(&input.as_slice().unwrap()[..8]), 200);
+        assert_eq!(decode_bits(&target.as_slice().unwrap()[..8]), 44);
+        assert_eq!(target[8], 1.0);
+    }
+
+    #[test]
+    fn test_cmp_flags_reference() {
+        //                        Z      N      C      V      GT     EQ     LT
+        assert_eq!(cmp_flags(5, 5, false), [true, false, false, false, false, true, false]);
+        assert_eq!(cmp_flags(1, 2, false), [false, true, true, false, false, false, true]);
+        // 0x80 (-128) vs 0x01: unsigned greater, signed less; -128 - 1 overflows
+        assert_eq!(cmp_flags(0x80, 0x01, false), [false, false, false, true, true, false, false]);
+        assert_eq!(cmp_flags(0x80, 0x01, true), [false, false, false, true, false, false, true]);
+        // -1 vs 1 signed: N set without overflow, LT
+        assert_eq!(cmp_flags(0xFF, 0x01, true), [false, true, false, false, false, false, true]);
+
+        let (input, target) = FUType::CMP.case(0xFF | (0x01 << 8) | (1 << 16));
+        assert_eq!(input[16], 1.0);
+        assert_eq!(target[6], 1.0);
+    }
+
+    #[test]
+    fn test_shift_and_bitwise_reference() {
+        let shift = |a: usize, n: usize, mode: usize| {
+            let (_, t) = FUType::SHIFT.case(a | (n << 8) | (mode << 11));
+            let t = t.as_slice().unwrap().to_vec();
+            (decode_bits(&t[..8]), t[8])
+        };
+        assert_eq!(shift(0b1000_0001, 1, 0), (0b0000_0010, 1.0)); // LSH
+        assert_eq!(shift(0b1000_0001, 1, 1), (0b0100_0000, 1.0)); // RSH
+        assert_eq!(shift(0b1000_0001, 1, 2), (0b0000_0011, 1.0)); // ROL
+        assert_eq!(shift(0b0000_0010, 2, 3), (0b1000_0000, 1.0)); // ROR
+        assert_eq!(shift(0b1111_1111, 0, 0), (0b1111_1111, 0.0));
+
+        let (input, target) = FUType::BITWISE.case(0x0F | (0x3C << 8) | (4 << 16));
+        assert_eq!(decode_bits(&input.as_slice().unwrap()[16..]), 1 << 4);
+        assert_eq!(decode_bits(target.as_slice().unwrap()), !(0x0F & 0x3C) as u8 as u32);
+    }
+}
@@ -0,0 +1,391 @@
+use ndarray::{s, Array1};
+use serde::{Deserialize, Serialize};
+
+use super::kind::{decode_bits, encode_bits, PortLayout, PortSpec};
+use super::{Introspect, MemRequest, MemResponse, NeuralFunctionalUnit};
+
+/// How a noisy address vector is compared against populated cell addresses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddrMetric {
+    /// Soft Hamming distance: sum of |v - bit| over the address bits.
+    #[default]
+    Hamming,
+    /// 1 - cosine similarity of the bipolar (2v - 1) vectors.
+    Cosine,
+}
+
+impl AddrMetric {
+    fn distance(self, v: &[f32], bits: &[f32]) -> f32 {
+        match self {
+            AddrMetric::Hamming => v.iter().zip(bits).map(|(&x, &b)| (x.clamp(0.0, 1.0) - b).abs()).sum(),
+            AddrMetric::Cosine => {
+                let x: Vec<f32> = v.iter().map(|&x| 2.0 * x.clamp(0.0, 1.0) - 1.0).collect();
+                let y: Vec<f32> = bits.iter().map(|&b| 2.0 * b - 1.0).collect();
+                let dot: f32 = x.iter().zip(&y).map(|(a, b)| a * b).sum();
+                let norm = x.iter().map(|a| a * a).sum::<f32>().sqrt() * (y.len() as f32).sqrt();
+                if norm == 0.0 { 1.0 } else { 1.0 - dot / norm }
+            }
+        }
+    }
+
+    /// Largest possible distance for `width` bits.
+    fn max_distance(self, width: usize) -> f32 {
+        match self {
+            AddrMetric::Hamming => width as f32,
+            AddrMetric::Cosine => 2.0,
+        }
+    }
+}
+
+/// Associative addressing, set per LSU in the manifest:
+/// `"associative": { "metric": "cosine", "confidence_floor": 0.8 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct AssocConfig {
+    #[serde(default)]
+    pub metric: AddrMetric,
+    /// Lookups resolving below this confidence raise a bus fault.
+    #[serde(default = "default_floor")]
+    pub confidence_floor: f32,
+}
+
+fn default_floor() -> f32 {
+    0.75
+}
+
+impl Default for AssocConfig {
+    fn default() -> Self {
+        Self { metric: AddrMetric::default(), confidence_floor: default_floor() }
+    }
+}
+
+/// How associative lookups went, kept by the bus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssocStats {
+    pub lookups: usize,
+    /// The thresholded address was already the resolved cell.
+    pub exact: usize,
+    /// Noise pointed elsewhere and the lookup snapped back to a populated cell.
+    pub recovered: usize,
+    /// Below the confidence floor, or nothing populated to match.
+    pub faults: usize,
+    pub confidence_sum: f32,
+}
+
+impl AssocStats {
+    pub fn recovery_rate(&self) -> f32 {
+        if self.lookups == 0 { 0.0 } else { self.recovered as f32 / self.lookups as f32 }
+    }
+
+    pub fn mean_confidence(&self) -> f32 {
+        let resolved = self.lookups - self.faults;
+        if resolved == 0 { 0.0 } else { self.confidence_sum / resolved as f32 }
+    }
+}
+
+/// Nearest candidate to `base` once `offset` is taken off, with a confidence in [0, 1].
+///
+/// With two or more candidates the confidence is `d2 / (d1 + d2)` for the best
+/// and runner-up distances: 1.0 for an exact hit, 0.5 for a tie. A lone
+/// candidate scores `1 - d1 / max_distance`.
+pub fn nearest_cell(
+    base: &Array1<f32>,
+    offset: u16,
+    metric: AddrMetric,
+    candidates: impl IntoIterator<Item = u16>,
+) -> Option<(u16, f32)> {
+    let v = base.to_vec();
+    let mut best: Option<(u16, f32)> = None;
+    let mut second = f32::INFINITY;
+    for addr in candidates {
+        let Some(rel) = addr.checked_sub(offset) else { continue };
+        let d = metric.distance(&v, &encode_bits(rel as u32, v.len()));
+        match best {
+            // Ties go to the lower address so lookups do not depend on map order
+            Some((b, bd)) if d < bd || (d == bd && addr < b) => {
+                second = bd;
+                best = Some((addr, d));
+            }
+            Some(_) => second = second.min(d),
+            None => best = Some((addr, d)),
+        }
+    }
+    let (addr, d1) = best?;
+    let confidence = if second.is_finite() {
+        if d1 + second == 0.0 { 0.5 } else { second / (d1 + second) }
+    } else {
+        1.0 - d1 / metric.max_distance(v.len())
+    };
+    Some((addr, confidence.clamp(0.0, 1.0)))
+}
+
+/// Load/store unit with real paths to System RAM.
+///
+/// Ports: `ADDR` (16-bit base), `DATA_IN`, and two triggers that each carry an
+/// 8-bit unsigned offset: writing `LOAD` reads `RAM[ADDR + offset]` into
+/// `DATA_OUT`, writing `STORE` writes `DATA_IN` there. The bus performs the
+/// access through `mem_request`. An address outside RAM, an overflowing
+/// `ADDR + offset` or a cell of the wrong width sets `FAULT` and leaves
+/// `DATA_OUT` unchanged; `FAULT` clears on the next successful access.
+///
+/// With `associative` set, `ADDR` is taken as a noisy vector: loads (and stores
+/// whose address bits are not clean) go to the nearest populated cell, see
+/// [`nearest_cell`]. A lookup below the confidence floor is a bus fault and
+/// stops the machine; clean store addresses still decode exactly so new cells
+/// can be written.
+#[derive(Debug, Clone)]
+pub struct LoadStoreFU {
+    pub width: usize,
+    pub associative: Option<AssocConfig>,
+    data_out: Array1<f32>,
+    fault: bool,
+    pub last_fault: Option<String>,
+    pending: Option<MemRequest>,
+    /// Store data waiting for an associative lookup to finish.
+    pending_store: Option<Array1<f32>>,
+}
+
+impl LoadStoreFU {
+    pub const ADDR_WIDTH: usize = 16;
+    pub const OFFSET_WIDTH: usize = 8;
+    pub const LOAD_PORT: usize = 2;
+    pub const STORE_PORT: usize = 3;
+    /// Address bits further than this from 0 or 1 count as noisy.
+    pub const CLEAN_MARGIN: f32 = 0.1;
+
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            associative: None,
+            data_out: Array1::zeros(width),
+            fault: false,
+            last_fault: None,
+            pending: None,
+            pending_store: None,
+        }
+    }
+
+    pub fn associative(width: usize, config: AssocConfig) -> Self {
+        Self { associative: Some(config), ..Self::new(width) }
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![
+                PortSpec::new("ADDR", Self::ADDR_WIDTH),
+                PortSpec::new("DATA_IN", self.width),
+                PortSpec::trigger("LOAD", Self::OFFSET_WIDTH),
+                PortSpec::trigger("STORE", Self::OFFSET_WIDTH),
+            ],
+            outputs: vec![PortSpec::new("DATA_OUT", self.width), PortSpec::new("FAULT", 1)],
+        }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        let mut out = self.data_out.to_vec();
+        out.push(if self.fault { 1.0 } else { 0.0 });
+        Array1::from(out)
+    }
+
+    fn set_fault(&mut self, msg: String) {
+        self.fault = true;
+        self.last_fault = Some(msg);
+    }
+}
+
+impl NeuralFunctionalUnit for LoadStoreFU {
+    /// Without port information the input is treated as a LOAD trigger.
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        self.trigger(Self::LOAD_PORT, input)
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn trigger(&mut self, port: usize, input: &Array1<f32>) -> Array1<f32> {
+        // Input: ADDR (16) + DATA_IN (width) + LOAD offset (8) + STORE offset (8)
+        let w = self.width;
+        let field = |start: usize, len: usize| {
+            let end = (start + len).min(input.len());
+            input.slice(s![start.min(end)..end]).to_vec()
+        };
+        let raw_base = field(0, Self::ADDR_WIDTH);
+        let base = decode_bits(&raw_base);
+        let offset_at = Self::ADDR_WIDTH + w + if port == Self::STORE_PORT { Self::OFFSET_WIDTH } else { 0 };
+        let offset = decode_bits(&field(offset_at, Self::OFFSET_WIDTH));
+        let data: Vec<f32> = field(Self::ADDR_WIDTH, w).iter().map(|&v| if v > 0.5 { 1.0 } else { 0.0 }).collect();
+
+        if let Some(config) = self.associative {
+            let clean = raw_base.iter().all(|&v| v.abs().min((1.0 - v).abs()) < Self::CLEAN_MARGIN);
+            if port != Self::STORE_PORT || !clean {
+                self.pending_store = (port == Self::STORE_PORT).then(|| Array1::from(data));
+                self.pending = Some(MemRequest::Resolve {
+                    base: Array1::from(raw_base),
+                    offset: offset as u16,
+                    metric: config.metric,
+                    floor: config.confidence_floor,
+                });
+                return self.outputs();
+            }
+        }
+
+        match u16::try_from(base + offset) {
+            Ok(addr) if port == Self::STORE_PORT => {
+                self.pending = Some(MemRequest::Store(addr, Array1::from(data)));
+            }
+            Ok(addr) => self.pending = Some(MemRequest::Load(addr)),
+            Err(_) => self.set_fault(format!("0x{:X} + 0x{:X} overflows the address space", base, offset)),
+        }
+        self.outputs()
+    }
+
+    fn mem_request(&mut self) -> Option<MemRequest> {
+        self.pending.take()
+    }
+
+    fn mem_complete(&mut self, response: MemResponse) -> Option<Array1<f32>> {
+        match response {
+            MemResponse::Loaded(Some(v)) if v.len() != self.width => {
+                self.set_fault(format!("cell holds {} bits, LSU is {} wide", v.len(), self.width));
+            }
+            MemResponse::Loaded(v) => {
+                self.data_out = v.unwrap_or_else(|| Array1::zeros(self.width));
+                self.fault = false;
+            }
+            MemResponse::Stored => self.fault = false,
+            MemResponse::Resolved { addr, .. } => {
+                // Second half of an associative access: the bus serves it right away
+                self.pending = Some(match self.pending_store.take() {
+                    Some(data) => MemRequest::Store(addr, data),
+                    None => MemRequest::Load(addr),
+                });
+                return None;
+            }
+            MemResponse::Fault(msg) => {
+                self.pending_store = None;
+                self.set_fault(msg);
+            }
+        }
+        Some(self.outputs())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value((&self.data_out, self.fault, &self.last_fault)).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        (self.data_out, self.fault, self.last_fault) = serde_json::from_value(state)?;
+        Ok(())
+    }
+
+    fn introspect(&self) -> Option<&dyn Introspect> { Some(self) }
+}
+
+impl Introspect for LoadStoreFU {
+    fn kind(&self) -> &'static str { "lsu" }
+
+    fn port_layout(&self) -> Option<PortLayout> { Some(LoadStoreFU::port_layout(self)) }
+
+    fn state(&self) -> Vec<(&'static str, String)> {
+        let mut state = vec![
+            ("data_out", decode_bits(self.data_out.as_slice().unwrap_or(&[])).to_string()),
+            ("fault", self.fault.to_string()),
+        ];
+        if let Some(config) = &self.associative {
+            state.push(("associative", format!("{:?}, floor {}", config.metric, config.confidence_floor)));
+        }
+        if let Some(fault) = &self.last_fault {
+            state.push(("last_fault", fault.clone()));
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{MoveOp, SystemBus};
+    use crate::fu::kind::encode_bits;
+    use crate::register::NeuralRegister;
+
+    #[test]
+    fn test_store_then_load_through_bus() {
+        let mut bus = SystemBus::new();
+        for r in 0..4 {
+            bus.add_register(r, 8);
+        }
+        let lsu = LoadStoreFU::new(8);
+        let layout = lsu.port_layout();
+        bus.add_unit_with_ports(0x1100, Box::new(lsu), layout).unwrap();
+        // Ports: ADDR 0x1100, DATA_IN 0x1101, LOAD 0x1102, STORE 0x1103, DATA_OUT 0x1104, FAULT 0x1105
+
+        // A 16-bit base pointer kept in RAM, offsets and data in registers
+        bus.ram.insert(0x2000, Array1::from(encode_bits(0x2100, 16)));
+        bus.registers.insert(0, NeuralRegister::from_symbolic(8, 0x42));
+        bus.registers.insert(1, NeuralRegister::from_symbolic(8, 5));
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        for op in [mv(0x2000, 0x1100), mv(0, 0x1101), mv(1, 0x1103)] {
+            bus.execute(&op);
+        }
+        assert_eq!(bus.ram[&0x2105], Array1::from(encode_bits(0x42, 8)));
+
+        bus.execute(&mv(1, 0x1102));
+        bus.execute(&mv(0x1104, 2));
+        assert_eq!(bus.registers[&2].to_symbolic(), 0x42);
+        assert!(bus.execute(&mv(0x1104, 0x1104)).ends_with("ReadOnly[FU[0x1100].DATA_OUT]"));
+
+        // Base 0x7FFF + 5 leaves RAM: FAULT set, DATA_OUT kept
+        bus.ram.insert(0x2001, Array1::from(encode_bits(0x7FFF, 16)));
+        bus.execute(&mv(0x2001, 0x1100));
+        bus.execute(&mv(1, 0x1102));
+        assert_eq!(bus.port_latches[&0x1105][0], 1.0);
+        assert_eq!(decode_bits(bus.port_latches[&0x1104].as_slice().unwrap()), 0x42);
+
+        // A 16-bit cell does not fit an 8-bit load
+        bus.execute(&mv(0x2000, 0x1100));
+        bus.registers.insert(3, NeuralRegister::from_symbolic(8, 0));
+        bus.ram.insert(0x2100, Array1::from(encode_bits(1, 16)));
+        bus.execute(&mv(3, 0x1102));
+        assert_eq!(bus.port_latches[&0x1105][0], 1.0);
+    }
+
+    #[test]
+    fn test_associative_lookup_recovers_and_faults() {
+        let mut bus = SystemBus::new();
+        bus.add_register(0, 8);
+        let lsu = LoadStoreFU::associative(8, AssocConfig::default());
+        let layout = lsu.port_layout();
+        bus.add_unit_with_ports(0x1100, Box::new(lsu), layout).unwrap();
+        bus.ram.insert(0x2100, Array1::from(encode_bits(0x11, 8)));
+        bus.ram.insert(0x2400, Array1::from(encode_bits(0x44, 8)));
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        let load_from = |bus: &mut SystemBus, base: Vec<f32>| {
+            bus.ram.insert(0x3000, Array1::from(base));
+            bus.execute(&mv(0x3000, 0x1100));
+            bus.execute(&mv(0, 0x1102));
+            decode_bits(bus.port_latches[&0x1104].as_slice().unwrap())
+        };
+
+        // Clean address of a populated cell
+        assert_eq!(load_from(&mut bus, encode_bits(0x2100, 16)), 0x11);
+        // 0x2100 with bit 9 drifted to a weak 1: thresholds to 0x2300, resolves to 0x2100
+        let mut noisy = encode_bits(0x2100, 16);
+        noisy[9] = 0.6;
+        assert_eq!(load_from(&mut bus, noisy), 0x11);
+        // Same in cosine space
+        let base = Array1::from(vec![0.9, 0.1, 0.8, 0.2]);
+        assert_eq!(nearest_cell(&base, 0, AddrMetric::Cosine, [0b0101, 0b1010]).unwrap().0, 0b0101);
+
+        let stats = bus.assoc_stats;
+        assert_eq!((stats.lookups, stats.exact, stats.recovered, stats.faults), (2, 1, 1, 0));
+        assert!(bus.bus_fault.is_none());
+
+        // Halfway between 0x2100 and 0x2400 (bits 8 and 10 differ): a tie, below the floor
+        let mut ambiguous = encode_bits(0x2000, 16);
+        ambiguous[8] = 0.5;
+        ambiguous[10] = 0.5;
+        assert_eq!(load_from(&mut bus, ambiguous), 0x11, "DATA_OUT kept");
+        assert_eq!(bus.port_latches[&0x1105][0], 1.0);
+        assert_eq!(bus.assoc_stats.faults, 1);
+        assert!(bus.bus_fault.take().unwrap().contains("confidence 0.50"));
+    }
+}
@@ -1,12 +1,95 @@
-use ndarray::{Array1, Array2};
+use ndarray::{Array1, Array2, ArrayView1, ArrayViewD};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
+pub mod disk;
+pub mod div;
+pub mod inspect;
+pub mod irq;
+pub mod keypad;
+pub mod kind;
+pub mod lsu;
+pub mod mul;
+pub mod nfn;
+pub mod quant;
+pub mod shift;
+pub mod stack;
+pub mod timer;
+pub mod uart;
+pub mod video;
+pub mod weights;
+
+pub use disk::{DiskFU, DiskImage, SECTOR_SIZE};
+pub use div::DividerFU;
+pub use inspect::Introspect;
+pub use irq::InterruptControllerFU;
+pub use keypad::{KeyEvent, KeyQueue, KeypadFU, ScriptedKey};
+pub use kind::{FUType, PortLayout, PortSpec};
+pub use lsu::{AddrMetric, AssocConfig, LoadStoreFU};
+pub use mul::MultiplierFU;
+pub use shift::ShifterFU;
+pub use stack::{StackConfig, StackPointerFU};
+pub use timer::TimerFU;
+pub use uart::{ByteSink, ByteSource, FileSource, InputQueue, StdinSource, StdoutSink, UartFU};
+pub use video::{Frame, SharedFrame, VideoFU};
+pub use weights::WeightFile;
+
 /// Interface for any Neural Functional Unit.
 /// Takes a vector input and produces a vector output.
 pub trait NeuralFunctionalUnit: Send + Sync {
     fn forward(&mut self, input: &Array1<f32>) -> Array1<f32>;
     fn perturb(&mut self, amount: f32); // For noise injection verification
     fn tick(&mut self) {} // Optional: Called every cycle
+    /// Current outputs of a multi-cycle unit. The bus re-latches them after every tick.
+    fn poll(&self) -> Option<Array1<f32>> { None }
+    /// Called by the bus when trigger input `port` is written. Units with several triggers override it.
+    fn trigger(&mut self, _port: usize, input: &Array1<f32>) -> Array1<f32> { self.forward(input) }
+    /// RAM access the unit wants after a trigger. The bus serves requests until this returns `None`.
+    fn mem_request(&mut self) -> Option<MemRequest> { None }
+    /// Outcome of the last `mem_request`; may return updated outputs.
+    fn mem_complete(&mut self, _response: MemResponse) -> Option<Array1<f32>> { None }
+    /// A fault the machine cannot continue from, raised by the last trigger (e.g. stack overflow).
+    fn take_fault(&mut self) -> Option<String> { None }
+    /// True once per interrupt the unit raised since the last call. The bus forwards it to the controller.
+    fn take_irq(&mut self) -> bool { false }
+    /// Internal state (not weights) for machine snapshots; `None` if the unit has none.
+    fn save_state(&self) -> Option<serde_json::Value> { None }
+    /// Counter that moves whenever `save_state` would return something new.
+    /// Lets the undo journal skip saving units with large state (a framebuffer)
+    /// on steps that left them alone; `None` means save and compare every step.
+    fn state_version(&self) -> Option<u64> { None }
+    /// Put back what `save_state` returned.
+    fn load_state(&mut self, _state: serde_json::Value) -> anyhow::Result<()> { Ok(()) }
+    /// Trained network, for snapshots that carry weights or check them by hash.
+    fn weights(&self) -> Option<&BaseFU> { None }
+    fn weights_mut(&mut self) -> Option<&mut BaseFU> { None }
+    /// Internals for inspection tools; `None` for units that do not expose them.
+    fn introspect(&self) -> Option<&dyn Introspect> { None }
+}
+
+/// A RAM access requested by a unit and performed by the bus.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemRequest {
+    Load(u16),
+    Store(u16, Array1<f32>),
+    /// Associative lookup: the populated cell whose address minus `offset` is
+    /// nearest to the (possibly noisy) `base` vector. Below `floor` confidence
+    /// the bus refuses and raises a bus fault.
+    Resolve { base: Array1<f32>, offset: u16, metric: AddrMetric, floor: f32 },
+    /// Store the return address (the PC of the next bundle) at `slot` and jump to `target`.
+    Call { slot: u16, target: u16 },
+    /// Load an address from `slot` and jump to it (RET, interrupt vectors).
+    JumpVia(u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemResponse {
+    /// Cell contents, `None` if never written.
+    Loaded(Option<Array1<f32>>),
+    Stored,
+    Resolved { addr: u16, confidence: f32 },
+    /// The access was refused (e.g. the address is not RAM).
+    Fault(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,6 +136,9 @@ pub struct BaseFU {
     pub b2: Array1<f32>,
     pub active_hidden: Activation,
     pub active_output: Activation,
+    /// Hidden-layer activations of the last `forward` (empty until then), for the inspector.
+    #[serde(skip)]
+    pub last_hidden: Array1<f32>,
 }
 
 impl BaseFU {
@@ -61,7 +147,7 @@ impl BaseFU {
         w2: Array2<f32>, b2: Array1<f32>,
         active_hidden: Activation, active_output: Activation
     ) -> Self {
-        Self { w1, b1, w2, b2, active_hidden, active_output }
+        Self { w1, b1, w2, b2, active_hidden, active_output, last_hidden: Array1::zeros(0) }
     }
 
     pub fn train_step(&mut self, input: &Array1<f32>, target: &Array1<f32>, lr: f32) {
@@ -108,6 +194,7 @@ impl NeuralFunctionalUnit for BaseFU {
         let h_pre = self.w1.dot(input) + &self.b1;
         let h = self.active_hidden.apply(&h_pre);
         let y_pre = self.w2.dot(&h) + &self.b2;
+        self.last_hidden = h;
         self.active_output.apply(&y_pre)
     }
 
@@ -122,13 +209,43 @@ impl NeuralFunctionalUnit for BaseFU {
             if rng.gen::<f32>() < 0.1 { *v += rng.gen_range(-amount..amount); }
         }
     }
+
+    fn weights(&self) -> Option<&BaseFU> { Some(self) }
+    fn weights_mut(&mut self) -> Option<&mut BaseFU> { Some(self) }
+    fn introspect(&self) -> Option<&dyn Introspect> { Some(self) }
+}
+
+impl Introspect for BaseFU {
+    fn kind(&self) -> &'static str { "mlp" }
+
+    fn tensors(&self) -> Vec<(&'static str, ArrayViewD<'_, f32>)> {
+        vec![
+            ("w1", self.w1.view().into_dyn()),
+            ("b1", self.b1.view().into_dyn()),
+            ("w2", self.w2.view().into_dyn()),
+            ("b2", self.b2.view().into_dyn()),
+        ]
+    }
+
+    fn hidden_activations(&self) -> Option<ArrayView1<'_, f32>> {
+        (!self.last_hidden.is_empty()).then(|| self.last_hidden.view())
+    }
+
+    fn state(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("shape", format!("{} -> {} -> {}", self.w1.ncols(), self.w1.nrows(), self.w2.nrows())),
+            ("activations", format!("{:?} / {:?}", self.active_hidden, self.active_output)),
+        ]
+    }
 }
 
 impl BaseFU {
     pub fn create_random(input_size: usize, hidden_size: usize, output_size: usize) -> Self {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        
+        Self::create_random_with(&mut rand::thread_rng(), input_size, hidden_size, output_size)
+    }
+
+    /// Same as `create_random` but drawing from a caller-supplied (e.g. seeded) RNG.
+    pub fn create_random_with<R: Rng>(rng: &mut R, input_size: usize, hidden_size: usize, output_size: usize) -> Self {
         let w1 = Array2::from_shape_fn((hidden_size, input_size), |_| rng.gen_range(-0.5..0.5));
         let b1 = Array1::from_shape_fn(hidden_size, |_| rng.gen_range(-0.1..0.1));
         let w2 = Array2::from_shape_fn((output_size, hidden_size), |_| rng.gen_range(-0.5..0.5));
@@ -144,16 +261,21 @@ impl BaseFU {
     }
 
     pub fn create_comparator() -> Self {
-        // 16 inputs (A: 8, B: 8)
-        // 3 outputs: GT, EQ, LT
-        Self::create_random(16, 24, 3)
+        // 17 inputs (A: 8, B: 8, Signed: 1)
+        // 7 outputs: Z, N, C, V, GT, EQ, LT
+        Self::create_random(17, 48, 7)
     }
 
     pub fn create_bitwise() -> Self {
-        // Inputs: A (8) + B (8) + Mode (3) = 19 inputs
+        // Inputs: A (8) + B (8) + Mode (5) = 21 inputs
         // Output: 8 bits
-        // Mode could be: 000=AND, 001=OR, 010=XOR, 011=NOT A...
-        Self::create_random(19, 32, 8)
+        // Mode is one-hot: AND, OR, XOR, NOT A, NAND
+        Self::create_random(21, 32, 8)
+    }
+
+    /// Load weights from a weight file (container or bare dump).
+    pub fn load_weights(path: &std::path::Path) -> anyhow::Result<Self> {
+        Ok(WeightFile::load(path)?.weights)
     }
 }
 
@@ -161,8 +283,6 @@ impl BaseFU {
 
 // --- Stateful Units ---
 
-use std::collections::HashMap;
-
 #[derive(Debug, Clone)]
 pub struct ProgramCounterFU {
     pub pc: u32,
@@ -172,6 +292,10 @@ impl ProgramCounterFU {
     pub fn new() -> Self { Self { pc: 0 } }
 }
 
+impl Default for ProgramCounterFU {
+    fn default() -> Self { Self::new() }
+}
+
 impl NeuralFunctionalUnit for ProgramCounterFU {
     fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
         // Input acts as JUMP Address.
@@ -194,119 +318,28 @@ impl NeuralFunctionalUnit for ProgramCounterFU {
     fn tick(&mut self) {
         self.pc += 1;
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct LoadStoreFU {
-    pub memory: HashMap<u32, Array1<f32>>,
-    pub width: usize,
-}
 
-impl LoadStoreFU {
-    pub fn new(width: usize) -> Self {
-         Self { memory: HashMap::new(), width }
+    fn save_state(&self) -> Option<serde_json::Value> {
+        Some(self.pc.into())
     }
-}
 
-impl NeuralFunctionalUnit for LoadStoreFU {
-    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
-        // Input: ADDR (width). 
-        // We assume DATA_IN is read from a register by the Bus and passed here? 
-        // OR the input vector contains ADDR + DATA?
-        // Prompt: "Trigger: Moving a value to ADDR with a Write-Enable bit set."
-        // This implies the standard TTA trigger is the ADDR register.
-        // But we need the DATA to write.
-        // Convention: We read DATA from a predetermined "DATA_IN" register.
-        // We can't access other registers here.
-        // So we must assume the input *is* the address, and we perform a LOAD?
-        // Or if Write-Enable is set (where? Mode register? Or part of input?), we WRITE.
-        
-        // Simplified Logic for Iteration 3:
-        // Always LOAD from Address.
-        // To WRITE, we might need a separate "STORE_TRIGGER" port/unit or encoding.
-        // Or, we stick to the prompt: "Moving a value to ADDR ... with Write-Enable".
-        // Let's assume input is just ADDR for now, and it returns the Data (LOAD).
-        // WRITE is complex without extra args.
-        
-        let mut addr = 0;
-        let len = input.len();
-        for (i, &v) in input.iter().enumerate() {
-             if v > 0.5 { addr |= 1 << i; }
-        }
-        
-        // MOCK: Return stored value or random
-        if let Some(val) = self.memory.get(&addr) {
-            return val.clone();
-        } else {
-            return Array1::zeros(self.width);
-        }
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        self.pc = serde_json::from_value(state)?;
+        Ok(())
     }
-    fn perturb(&mut self, _amount: f32) {}
-}
 
-#[derive(Debug, Clone)]
-pub struct StackPointerFU {
-    pub sp: u32,
-    pub stack: HashMap<u32, Array1<f32>>, // Mock stack memory
-    pub width: usize,
-}
-impl StackPointerFU {
-    pub fn new(width: usize) -> Self { 
-        Self { sp: 0xFF, stack: HashMap::new(), width } 
-    }
-}
-impl NeuralFunctionalUnit for StackPointerFU {
-    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> { 
-        // Trigger: STACK_DATA.
-        // If we move data here -> PUSH.
-        // Decrement SP, Store data.
-        self.sp = self.sp.wrapping_sub(1);
-        self.stack.insert(self.sp, input.clone());
-        input.clone() // Pass through or return new SP?
-    }
-    fn perturb(&mut self, _amount: f32) {}
+    fn introspect(&self) -> Option<&dyn Introspect> { Some(self) }
 }
 
-// Mocks removed for production.
+impl Introspect for ProgramCounterFU {
+    fn kind(&self) -> &'static str { "pc" }
 
-#[derive(Debug, Clone)]
-pub struct UartFU {
-    pub output_buffer: Option<std::sync::Arc<std::sync::Mutex<String>>>,
-}
-
-impl UartFU { 
-    pub fn new() -> Self { Self { output_buffer: None } }
-    
-    pub fn with_sink(sink: std::sync::Arc<std::sync::Mutex<String>>) -> Self {
-        Self { output_buffer: Some(sink) }
+    fn state(&self) -> Vec<(&'static str, String)> {
+        vec![("pc", self.pc.to_string())]
     }
 }
 
-impl NeuralFunctionalUnit for UartFU {
-    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
-        // Interpret input as ASCII char
-        let mut char_code: u8 = 0;
-        for i in 0..8 {
-            if input.get(i).unwrap_or(&0.0) > &0.5 { char_code |= 1 << i; }
-        }
-        let c = char_code as char;
-        
-        // Print to stdout
-        print!("{}", c);
-        use std::io::Write;
-        std::io::stdout().flush().unwrap();
-        
-        // Also capture to buffer if present
-        if let Some(buf_mutex) = &self.output_buffer {
-            if let Ok(mut buf) = buf_mutex.lock() {
-                buf.push(c);
-            }
-        }
-        
-        input.clone() // Echo?
-    }
-    fn perturb(&mut self, _amount: f32) {}
-}
+// Mocks removed for production.
 
 #[cfg(test)]
 mod tests {
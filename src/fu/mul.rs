@@ -0,0 +1,90 @@
+use ndarray::Array1;
+
+use super::{BaseFU, NeuralFunctionalUnit};
+
+/// 8x8 -> 16-bit neural multiplier built as an array multiplier.
+///
+/// A single monolithic MLP cannot learn all 65536 products reliably, so the
+/// unit is decomposed into one small trained "multiply-accumulate" cell:
+///
+/// `(a, b, sum_in, carry_in) -> (sum_out, carry_out)` computing `a*b + sum_in + carry_in`.
+///
+/// The cell has only 16 cases and trains to 100%. Each partial-product row is
+/// rippled through the accumulator with 8 cell evaluations, snapping the cell
+/// outputs to 0/1 between stages (the same cleanup `NeuralRegister` does), so
+/// an exact cell gives an exact multiplier.
+///
+/// The cell is generic so quantized cells can be dropped in for precision studies.
+#[derive(Debug, Clone)]
+pub struct MultiplierFU<C: NeuralFunctionalUnit = BaseFU> {
+    pub cell: C,
+}
+
+impl<C: NeuralFunctionalUnit> MultiplierFU<C> {
+    pub const WIDTH: usize = 8;
+
+    pub fn new(cell: C) -> Self {
+        Self { cell }
+    }
+
+    /// One cell evaluation with cleanup: returns (sum, carry) as 0.0/1.0.
+    fn mac(&mut self, a: f32, b: f32, sum_in: f32, carry_in: f32) -> (f32, f32) {
+        let out = self.cell.forward(&Array1::from(vec![a, b, sum_in, carry_in]));
+        let snap = |v: f32| if v > 0.5 { 1.0 } else { 0.0 };
+        (snap(out[0]), snap(out[1]))
+    }
+}
+
+impl<C: NeuralFunctionalUnit> NeuralFunctionalUnit for MultiplierFU<C> {
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        // Input: A (8) + B (8). Output: product LSB first, LO (8) then HI (8).
+        let n = Self::WIDTH;
+        let bit = |i: usize| *input.get(i).unwrap_or(&0.0);
+        let mut product = Array1::zeros(2 * n);
+
+        for i in 0..n {
+            let b_i = bit(n + i);
+            let mut carry = 0.0;
+            for j in 0..n {
+                let (sum, c) = self.mac(bit(j), b_i, product[i + j], carry);
+                product[i + j] = sum;
+                carry = c;
+            }
+            // Bits above i+7 are still zero here, so the carry lands directly.
+            product[i + n] = carry;
+        }
+        product
+    }
+
+    fn perturb(&mut self, amount: f32) {
+        // Every cell shares these weights: drift is a common-mode fault.
+        self.cell.perturb(amount);
+    }
+
+    fn weights(&self) -> Option<&BaseFU> {
+        self.cell.weights()
+    }
+
+    fn weights_mut(&mut self) -> Option<&mut BaseFU> {
+        self.cell.weights_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fu::kind::{decode_bits, encode_bits, shipped_unit};
+    use crate::fu::FUType;
+
+    #[test]
+    fn test_shipped_cell_gives_exact_products() {
+        let cell = shipped_unit("alu_mul", FUType::MUL);
+        let mut mul = MultiplierFU::new(cell);
+        for (a, b) in [(0u32, 0u32), (1, 255), (13, 21), (255, 255), (128, 2), (170, 85)] {
+            let mut input = encode_bits(a, 8);
+            input.extend(encode_bits(b, 8));
+            let out = mul.forward(&Array1::from(input));
+            assert_eq!(decode_bits(out.as_slice().unwrap()), a * b, "{} * {}", a, b);
+        }
+    }
+}
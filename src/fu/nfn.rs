@@ -0,0 +1,394 @@
+//! `.nfn` ("Neural Function") binary weight format: the hardware "bitstream".
+//!
+//! Layout (all integers little-endian):
+//!
+//! | Field        | Size                 | Notes                                 |
+//! | ---          | ---                  | ---                                   |
+//! | magic        | 4                    | `NFN\0`                               |
+//! | version      | u16                  | `NFN_VERSION`                         |
+//! | header_len   | u32                  | followed by the `WeightHeader` JSON   |
+//! | activations  | u8, u8               | hidden, output                        |
+//! | tensor_count | u16                  | followed by the tensors               |
+//! | crc32        | u32                  | IEEE CRC over every preceding byte    |
+//!
+//! Each tensor is `name_len u8, name, dtype u8, rank u8, dims u32 * rank` and
+//! then a payload depending on `dtype`:
+//! * `0` f32: `n * 4` bytes.
+//! * `1` f16: `n * 2` bytes (IEEE half).
+//! * `2` int8: `channels u32, scales f32 * channels, zero_points i8 * channels, n` bytes.
+//!   Channels split the tensor along its first axis; a single channel is per-tensor.
+
+use anyhow::{anyhow, bail, ensure, Result};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+
+use super::quant::{f16_to_f32, f32_to_f16, quantize_i8, quantize_i8_per_channel, QuantizedFU};
+use super::weights::{content_hash, WeightFile, WeightHeader};
+use super::{Activation, BaseFU};
+
+pub const NFN_MAGIC: &[u8; 4] = b"NFN\0";
+pub const NFN_VERSION: u16 = 1;
+
+/// Storage precision of tensor payloads.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    #[default]
+    F32,
+    F16,
+    /// One scale / zero point for the whole tensor.
+    Int8,
+    /// One scale / zero point per output row of each weight matrix.
+    Int8PerChannel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TensorData {
+    F32(Vec<f32>),
+    F16(Vec<u16>),
+    /// Affine int8: `value = (q - zero_point) * scale`, one (scale, zero_point) per channel.
+    I8 { data: Vec<i8>, scales: Vec<f32>, zero_points: Vec<i8> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NfnTensor {
+    pub name: String,
+    pub shape: Vec<usize>,
+    pub data: TensorData,
+}
+
+impl NfnTensor {
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn encode(name: &str, shape: &[usize], values: &[f32], precision: Precision) -> Self {
+        let data = match precision {
+            Precision::F32 => TensorData::F32(values.to_vec()),
+            Precision::F16 => TensorData::F16(values.iter().map(|&v| f32_to_f16(v)).collect()),
+            Precision::Int8PerChannel if shape.len() == 2 => {
+                let (data, scales, zero_points) = quantize_i8_per_channel(values, shape[0]);
+                TensorData::I8 { data, scales, zero_points }
+            }
+            Precision::Int8 | Precision::Int8PerChannel => {
+                let (data, scale, zp) = quantize_i8(values);
+                TensorData::I8 { data, scales: vec![scale], zero_points: vec![zp] }
+            }
+        };
+        Self { name: name.to_string(), shape: shape.to_vec(), data }
+    }
+
+    pub fn to_f32(&self) -> Vec<f32> {
+        match &self.data {
+            TensorData::F32(v) => v.clone(),
+            TensorData::F16(v) => v.iter().map(|&h| f16_to_f32(h)).collect(),
+            TensorData::I8 { data, scales, zero_points } => {
+                let per_channel = data.len() / scales.len().max(1);
+                data.iter().enumerate().map(|(i, &q)| {
+                    let c = (i / per_channel.max(1)).min(scales.len() - 1);
+                    (q as i32 - zero_points[c] as i32) as f32 * scales[c]
+                }).collect()
+            }
+        }
+    }
+}
+
+/// In-memory form of an `.nfn` file.
+#[derive(Debug, Clone)]
+pub struct NfnFile {
+    pub header: WeightHeader,
+    pub active_hidden: Activation,
+    pub active_output: Activation,
+    pub tensors: Vec<NfnTensor>,
+}
+
+impl NfnFile {
+    /// Lossy precisions rewrite the header hash to match the stored values and
+    /// drop the verification record, which no longer applies.
+    pub fn from_weight_file(file: &WeightFile, precision: Precision) -> Self {
+        let mut header = file.header.clone();
+        let quantized = QuantizedFU::quantize(&file.weights, precision);
+        if precision != Precision::F32 {
+            header.verification = None;
+            header.content_hash = content_hash(&quantized.dequantize());
+        }
+        Self::from_quantized(header, &quantized)
+    }
+
+    /// Store a quantized unit as-is. Biases are kept at f16 for `F16`, f32 otherwise.
+    pub fn from_quantized(header: WeightHeader, fu: &QuantizedFU) -> Self {
+        let bias_precision = if fu.precision == Precision::F16 { Precision::F16 } else { Precision::F32 };
+        let bias = |name: &str, b: &Array1<f32>| NfnTensor::encode(name, b.shape(), &b.to_vec(), bias_precision);
+        let mut w1 = fu.w1.clone();
+        w1.name = "w1".to_string();
+        let mut w2 = fu.w2.clone();
+        w2.name = "w2".to_string();
+        Self {
+            header,
+            active_hidden: fu.active_hidden.clone(),
+            active_output: fu.active_output.clone(),
+            tensors: vec![w1, bias("b1", &fu.b1), w2, bias("b2", &fu.b2)],
+        }
+    }
+
+    /// Rebuild the quantized unit without expanding the weight matrices.
+    pub fn to_quantized(&self) -> Result<QuantizedFU> {
+        let w1 = self.tensor("w1")?.clone();
+        let w2 = self.tensor("w2")?.clone();
+        ensure!(w1.shape.len() == 2 && w2.shape.len() == 2, "weight tensors must be rank 2");
+        let precision = match &w1.data {
+            TensorData::F32(_) => Precision::F32,
+            TensorData::F16(_) => Precision::F16,
+            TensorData::I8 { scales, .. } if scales.len() > 1 => Precision::Int8PerChannel,
+            TensorData::I8 { .. } => Precision::Int8,
+        };
+        check_shapes(&w1.shape, &self.tensor("b1")?.shape, &w2.shape, &self.tensor("b2")?.shape)?;
+        Ok(QuantizedFU {
+            precision,
+            w1,
+            b1: Array1::from(self.tensor("b1")?.to_f32()),
+            w2,
+            b2: Array1::from(self.tensor("b2")?.to_f32()),
+            active_hidden: self.active_hidden.clone(),
+            active_output: self.active_output.clone(),
+        })
+    }
+
+    pub fn tensor(&self, name: &str) -> Result<&NfnTensor> {
+        self.tensors.iter().find(|t| t.name == name).ok_or_else(|| anyhow!("missing tensor '{}'", name))
+    }
+
+    /// Expand every tensor back to f32 and rebuild the `BaseFU`.
+    pub fn dequantize(&self) -> Result<BaseFU> {
+        check_shapes(&self.tensor("w1")?.shape, &self.tensor("b1")?.shape, &self.tensor("w2")?.shape, &self.tensor("b2")?.shape)?;
+        let matrix = |name: &str| -> Result<Array2<f32>> {
+            let t = self.tensor(name)?;
+            ensure!(t.shape.len() == 2, "tensor '{}' must be rank 2", name);
+            Ok(Array2::from_shape_vec((t.shape[0], t.shape[1]), t.to_f32())?)
+        };
+        let vector = |name: &str| -> Result<Array1<f32>> {
+            let t = self.tensor(name)?;
+            ensure!(t.shape.len() == 1, "tensor '{}' must be rank 1", name);
+            Ok(Array1::from(t.to_f32()))
+        };
+        Ok(BaseFU::new(
+            matrix("w1")?, vector("b1")?,
+            matrix("w2")?, vector("b2")?,
+            self.active_hidden.clone(), self.active_output.clone(),
+        ))
+    }
+
+    pub fn to_weight_file(&self) -> Result<WeightFile> {
+        let file = WeightFile { header: self.header.clone(), weights: self.dequantize()? };
+        file.check_hash()?;
+        Ok(file)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        out.extend_from_slice(NFN_MAGIC);
+        out.extend_from_slice(&NFN_VERSION.to_le_bytes());
+
+        let header = serde_json::to_vec(&self.header)?;
+        out.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        out.extend_from_slice(&header);
+
+        out.push(activation_tag(&self.active_hidden));
+        out.push(activation_tag(&self.active_output));
+
+        out.extend_from_slice(&(self.tensors.len() as u16).to_le_bytes());
+        for t in &self.tensors {
+            ensure!(t.name.len() <= u8::MAX as usize, "tensor name too long: {}", t.name);
+            out.push(t.name.len() as u8);
+            out.extend_from_slice(t.name.as_bytes());
+            out.push(match t.data {
+                TensorData::F32(_) => 0,
+                TensorData::F16(_) => 1,
+                TensorData::I8 { .. } => 2,
+            });
+            out.push(t.shape.len() as u8);
+            for &d in &t.shape {
+                out.extend_from_slice(&(d as u32).to_le_bytes());
+            }
+            match &t.data {
+                TensorData::F32(v) => v.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+                TensorData::F16(v) => v.iter().for_each(|x| out.extend_from_slice(&x.to_le_bytes())),
+                TensorData::I8 { data, scales, zero_points } => {
+                    out.extend_from_slice(&(scales.len() as u32).to_le_bytes());
+                    scales.iter().for_each(|s| out.extend_from_slice(&s.to_le_bytes()));
+                    zero_points.iter().for_each(|z| out.push(*z as u8));
+                    data.iter().for_each(|q| out.push(*q as u8));
+                }
+            }
+        }
+
+        let crc = crc32(&out);
+        out.extend_from_slice(&crc.to_le_bytes());
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() >= NFN_MAGIC.len() + 4 && bytes.starts_with(NFN_MAGIC), "not an .nfn file");
+        let (body, crc_bytes) = bytes.split_at(bytes.len() - 4);
+        let stored = u32::from_le_bytes(crc_bytes.try_into()?);
+        let actual = crc32(body);
+        ensure!(stored == actual, "CRC mismatch (stored {:08x}, computed {:08x})", stored, actual);
+
+        let mut r = Reader { buf: body, pos: NFN_MAGIC.len() };
+        let version = r.u16()?;
+        if version > NFN_VERSION {
+            bail!(".nfn version {} is newer than supported {}", version, NFN_VERSION);
+        }
+
+        let header_len = r.u32()? as usize;
+        let header: WeightHeader = serde_json::from_slice(r.take(header_len)?)?;
+        let active_hidden = activation_from_tag(r.u8()?)?;
+        let active_output = activation_from_tag(r.u8()?)?;
+
+        let count = r.u16()?;
+        let mut tensors = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let name_len = r.u8()? as usize;
+            let name = String::from_utf8(r.take(name_len)?.to_vec())?;
+            let dtype = r.u8()?;
+            let rank = r.u8()?;
+            let mut shape = Vec::with_capacity(rank as usize);
+            for _ in 0..rank {
+                shape.push(r.u32()? as usize);
+            }
+            let n = shape.iter().try_fold(1usize, |n, &d| n.checked_mul(d))
+                .ok_or_else(|| anyhow!("tensor '{}': shape {:?} is too large", name, shape))?;
+            let data = match dtype {
+                0 => TensorData::F32((0..n).map(|_| r.f32()).collect::<Result<_>>()?),
+                1 => TensorData::F16((0..n).map(|_| r.u16()).collect::<Result<_>>()?),
+                2 => {
+                    let channels = r.u32()? as usize;
+                    ensure!(channels > 0 && n.is_multiple_of(channels), "tensor '{}': bad channel count {}", name, channels);
+                    let scales = (0..channels).map(|_| r.f32()).collect::<Result<_>>()?;
+                    let zero_points = r.take(channels)?.iter().map(|&b| b as i8).collect();
+                    let data = r.take(n)?.iter().map(|&b| b as i8).collect();
+                    TensorData::I8 { data, scales, zero_points }
+                }
+                other => bail!("tensor '{}': unknown dtype {}", name, other),
+            };
+            tensors.push(NfnTensor { name, shape, data });
+        }
+        ensure!(r.pos == body.len(), "{} trailing bytes before CRC", body.len() - r.pos);
+
+        Ok(Self { header, active_hidden, active_output, tensors })
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.buf.len())
+            .ok_or_else(|| anyhow!("truncated .nfn file at byte {}", self.pos))?;
+        let slice = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+    fn u8(&mut self) -> Result<u8> { Ok(self.take(1)?[0]) }
+    fn u16(&mut self) -> Result<u16> { Ok(u16::from_le_bytes(self.take(2)?.try_into()?)) }
+    fn u32(&mut self) -> Result<u32> { Ok(u32::from_le_bytes(self.take(4)?.try_into()?)) }
+    fn f32(&mut self) -> Result<f32> { Ok(f32::from_le_bytes(self.take(4)?.try_into()?)) }
+}
+
+pub(crate) fn activation_tag(act: &Activation) -> u8 {
+    match act {
+        Activation::ReLU => 0,
+        Activation::Sigmoid => 1,
+        Activation::Tanh => 2,
+        Activation::Identity => 3,
+    }
+}
+
+fn activation_from_tag(tag: u8) -> Result<Activation> {
+    Ok(match tag {
+        0 => Activation::ReLU,
+        1 => Activation::Sigmoid,
+        2 => Activation::Tanh,
+        3 => Activation::Identity,
+        other => bail!("unknown activation tag {}", other),
+    })
+}
+
+/// The tensors must chain into a network: w1 (hidden x in), b1 (hidden),
+/// w2 (out x hidden), b2 (out). A file can pass its CRC and still not.
+fn check_shapes(w1: &[usize], b1: &[usize], w2: &[usize], b2: &[usize]) -> Result<()> {
+    ensure!(w1.len() == 2 && w2.len() == 2, "weight tensors must be rank 2");
+    ensure!(b1.len() == 1 && b2.len() == 1, "bias tensors must be rank 1");
+    ensure!(b1[0] == w1[0], "b1 has {} entries for {} hidden units", b1[0], w1[0]);
+    ensure!(w2[1] == w1[0], "w2 takes {} hidden inputs, w1 has {} hidden units", w2[1], w1[0]);
+    ensure!(b2[0] == w2[0], "b2 has {} entries for {} outputs", b2[0], w2[0]);
+    Ok(())
+}
+
+/// CRC-32 (IEEE 802.3, reflected, as used by zip/png).
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fu::FUType;
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_f32_roundtrip_is_exact() {
+        let file = WeightFile::new("add", Some(FUType::ADDER), BaseFU::create_adder());
+        let bytes = NfnFile::from_weight_file(&file, Precision::F32).to_bytes().unwrap();
+        let back = NfnFile::from_bytes(&bytes).unwrap().to_weight_file().unwrap();
+        assert_eq!(back.weights.w1, file.weights.w1);
+        assert_eq!(back.header, file.header);
+    }
+
+    #[test]
+    fn test_lossy_precisions_roundtrip() {
+        let file = WeightFile::new("cmp", Some(FUType::CMP), BaseFU::create_comparator());
+        for (precision, tol) in [(Precision::F16, 1e-3), (Precision::Int8, 1e-2), (Precision::Int8PerChannel, 1e-2)] {
+            let bytes = NfnFile::from_weight_file(&file, precision).to_bytes().unwrap();
+            let back = NfnFile::from_bytes(&bytes).unwrap().to_weight_file().unwrap();
+            let max_err = (&back.weights.w1 - &file.weights.w1).mapv(f32::abs).fold(0.0f32, |a, &b| a.max(b));
+            assert!(max_err < tol, "{:?}: max error {}", precision, max_err);
+        }
+    }
+
+    #[test]
+    fn test_corruption_detected() {
+        let file = WeightFile::new("cmp", Some(FUType::CMP), BaseFU::create_comparator());
+        let mut bytes = NfnFile::from_weight_file(&file, Precision::F32).to_bytes().unwrap();
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0x40;
+        assert!(NfnFile::from_bytes(&bytes).is_err());
+
+        // Valid CRC but shapes that don't chain, or overflow when multiplied
+        let mut nfn = NfnFile::from_weight_file(&file, Precision::F32);
+        nfn.tensors[1] = NfnTensor::encode("b1", &[3], &[0.0; 3], Precision::F32);
+        let back = NfnFile::from_bytes(&nfn.to_bytes().unwrap()).unwrap();
+        assert!(back.dequantize().is_err() && back.to_quantized().is_err());
+        nfn.tensors[1] = NfnTensor { name: "b1".to_string(), shape: vec![u32::MAX as usize; 3], data: TensorData::F32(Vec::new()) };
+        assert!(NfnFile::from_bytes(&nfn.to_bytes().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Post-training quantization of `BaseFU` weights and a forward path that
+//! computes directly from the quantized tensors.
+//!
+//! * `F16`: weights, biases and activations are rounded to IEEE half after every op.
+//! * `Int8` / `Int8PerChannel`: weight-only affine int8. Accumulation runs on the
+//!   integer codes (minus zero point) and is rescaled once per output row. Biases
+//!   stay f32, as on typical int8 accelerators.
+
+use ndarray::{Array1, Array2};
+use rand::Rng;
+
+use super::nfn::{NfnTensor, Precision, TensorData};
+use super::{Activation, BaseFU, NeuralFunctionalUnit};
+
+/// A `BaseFU` whose weight matrices are held at reduced precision.
+#[derive(Debug, Clone)]
+pub struct QuantizedFU {
+    pub precision: Precision,
+    pub w1: NfnTensor,
+    pub b1: Array1<f32>,
+    pub w2: NfnTensor,
+    pub b2: Array1<f32>,
+    pub active_hidden: Activation,
+    pub active_output: Activation,
+}
+
+impl QuantizedFU {
+    pub fn quantize(fu: &BaseFU, precision: Precision) -> Self {
+        let bias = |b: &Array1<f32>| match precision {
+            Precision::F16 => b.mapv(round_f16),
+            _ => b.clone(),
+        };
+        let matrix = |name: &str, w: &Array2<f32>| {
+            NfnTensor::encode(name, w.shape(), &w.iter().copied().collect::<Vec<_>>(), precision)
+        };
+        Self {
+            precision,
+            w1: matrix("w1", &fu.w1),
+            b1: bias(&fu.b1),
+            w2: matrix("w2", &fu.w2),
+            b2: bias(&fu.b2),
+            active_hidden: fu.active_hidden.clone(),
+            active_output: fu.active_output.clone(),
+        }
+    }
+
+    /// The f32 weights this unit effectively computes with.
+    pub fn dequantize(&self) -> BaseFU {
+        let matrix = |t: &NfnTensor| {
+            Array2::from_shape_vec((t.shape[0], t.shape[1]), t.to_f32()).expect("rank-2 weight tensor")
+        };
+        BaseFU::new(
+            matrix(&self.w1), self.b1.clone(),
+            matrix(&self.w2), self.b2.clone(),
+            self.active_hidden.clone(), self.active_output.clone(),
+        )
+    }
+
+    /// Storage used by the weight matrices, in bytes.
+    pub fn weight_bytes(&self) -> usize {
+        [&self.w1, &self.w2].iter().map(|t| match &t.data {
+            TensorData::F32(v) => v.len() * 4,
+            TensorData::F16(v) => v.len() * 2,
+            TensorData::I8 { data, scales, zero_points } => data.len() + scales.len() * 4 + zero_points.len(),
+        }).sum()
+    }
+
+    fn layer(&self, w: &NfnTensor, b: &Array1<f32>, x: &Array1<f32>) -> Array1<f32> {
+        let (rows, cols) = (w.shape[0], w.shape[1]);
+        match &w.data {
+            TensorData::F32(v) => {
+                Array1::from_shape_fn(rows, |i| {
+                    v[i * cols..(i + 1) * cols].iter().zip(x.iter()).map(|(w, x)| w * x).sum::<f32>() + b[i]
+                })
+            }
+            TensorData::F16(v) => {
+                let x = x.mapv(round_f16);
+                Array1::from_shape_fn(rows, |i| {
+                    let acc: f32 = v[i * cols..(i + 1) * cols].iter().zip(x.iter())
+                        .map(|(&w, x)| round_f16(f16_to_f32(w) * x))
+                        .sum();
+                    round_f16(acc + b[i])
+                })
+            }
+            TensorData::I8 { data, scales, zero_points } => {
+                let per_channel = (data.len() / scales.len()).max(1);
+                Array1::from_shape_fn(rows, |i| {
+                    let c = (i * cols / per_channel).min(scales.len() - 1);
+                    let zp = zero_points[c] as i32;
+                    let acc: f32 = data[i * cols..(i + 1) * cols].iter().zip(x.iter())
+                        .map(|(&q, x)| (q as i32 - zp) as f32 * x)
+                        .sum();
+                    acc * scales[c] + b[i]
+                })
+            }
+        }
+    }
+
+    fn activate(&self, act: &Activation, x: &Array1<f32>) -> Array1<f32> {
+        let y = act.apply(x);
+        if self.precision == Precision::F16 { y.mapv(round_f16) } else { y }
+    }
+}
+
+impl NeuralFunctionalUnit for QuantizedFU {
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        let h = self.activate(&self.active_hidden, &self.layer(&self.w1, &self.b1, input));
+        self.activate(&self.active_output, &self.layer(&self.w2, &self.b2, &h))
+    }
+
+    /// Drift is applied in the storage domain: int8 codes move by whole steps
+    /// and f16 weights are re-rounded, so small nudges can vanish entirely.
+    fn perturb(&mut self, amount: f32) {
+        if amount <= 0.0 { return; }
+        let mut rng = rand::thread_rng();
+        for t in [&mut self.w1, &mut self.w2] {
+            match &mut t.data {
+                TensorData::F32(v) => {
+                    for w in v.iter_mut() {
+                        if rng.gen::<f32>() < 0.1 { *w += rng.gen_range(-amount..amount); }
+                    }
+                }
+                TensorData::F16(v) => {
+                    for w in v.iter_mut() {
+                        if rng.gen::<f32>() < 0.1 {
+                            *w = f32_to_f16(f16_to_f32(*w) + rng.gen_range(-amount..amount));
+                        }
+                    }
+                }
+                TensorData::I8 { data, scales, .. } => {
+                    let per_channel = (data.len() / scales.len()).max(1);
+                    for (i, q) in data.iter_mut().enumerate() {
+                        if rng.gen::<f32>() < 0.1 {
+                            let step = (rng.gen_range(-amount..amount) / scales[i / per_channel]).round();
+                            *q = (*q as f32 + step).clamp(-128.0, 127.0) as i8;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Round an f32 to the nearest representable f16 value.
+pub fn round_f16(v: f32) -> f32 {
+    f16_to_f32(f32_to_f16(v))
+}
+
+/// Per-channel variant of `quantize_i8`: `values` is split into `channels`
+/// equal contiguous rows, each with its own scale and zero point.
+pub fn quantize_i8_per_channel(values: &[f32], channels: usize) -> (Vec<i8>, Vec<f32>, Vec<i8>) {
+    let per_channel = values.len() / channels.max(1);
+    let mut data = Vec::with_capacity(values.len());
+    let mut scales = Vec::with_capacity(channels);
+    let mut zero_points = Vec::with_capacity(channels);
+    for row in values.chunks(per_channel.max(1)) {
+        let (q, scale, zp) = quantize_i8(row);
+        data.extend(q);
+        scales.push(scale);
+        zero_points.push(zp);
+    }
+    (data, scales, zero_points)
+}
+
+/// Asymmetric min/max int8 quantization of a slice: returns (values, scale, zero_point).
+pub fn quantize_i8(values: &[f32]) -> (Vec<i8>, f32, i8) {
+    // Range must include 0 so that zero stays exactly representable.
+    let min = values.iter().copied().fold(0.0f32, f32::min);
+    let max = values.iter().copied().fold(0.0f32, f32::max);
+    let scale = if max > min { (max - min) / 255.0 } else { 1.0 };
+    let zero_point = (-128.0 - min / scale).round().clamp(-128.0, 127.0) as i8;
+    let data = values.iter()
+        .map(|&v| (v / scale + zero_point as f32).round().clamp(-128.0, 127.0) as i8)
+        .collect();
+    (data, scale, zero_point)
+}
+
+/// IEEE 754 binary32 -> binary16, round to nearest even.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xFF) as i32;
+    let man = x & 0x007F_FFFF;
+
+    if exp == 0xFF {
+        // Inf / NaN (keep NaN quiet)
+        return sign | 0x7C00 | if man != 0 { 0x0200 } else { 0 };
+    }
+    let e = exp - 127 + 15;
+    if e >= 0x1F {
+        return sign | 0x7C00; // Overflow -> Inf
+    }
+    if e <= 0 {
+        if e < -10 {
+            return sign; // Underflow -> signed zero
+        }
+        // Subnormal: shift in the implicit bit
+        let m = man | 0x0080_0000;
+        let shift = (14 - e) as u32;
+        let half = m >> shift;
+        let rem = m & ((1 << shift) - 1);
+        let midpoint = 1 << (shift - 1);
+        let round = (rem > midpoint || (rem == midpoint && half & 1 == 1)) as u32;
+        return sign | (half + round) as u16;
+    }
+    let half = ((e as u32) << 10) | (man >> 13);
+    let rem = man & 0x1FFF;
+    let round = (rem > 0x1000 || (rem == 0x1000 && half & 1 == 1)) as u32;
+    // A carry out of the mantissa correctly bumps the exponent (up to Inf).
+    sign | (half + round) as u16
+}
+
+/// IEEE 754 binary16 -> binary32 (exact).
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exp = ((half >> 10) & 0x1F) as u32;
+    let man = (half & 0x03FF) as u32;
+    let bits = match (exp, man) {
+        (0, 0) => sign,
+        (0, _) => {
+            // Subnormal: normalise
+            let mut e = 127 - 15 + 1;
+            let mut m = man;
+            while m & 0x0400 == 0 {
+                m <<= 1;
+                e -= 1;
+            }
+            sign | ((e as u32) << 23) | ((m & 0x03FF) << 13)
+        }
+        (0x1F, _) => sign | 0x7F80_0000 | (man << 13),
+        _ => sign | ((exp + 127 - 15) << 23) | (man << 13),
+    };
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_roundtrip() {
+        for v in [0.0f32, 1.0, -2.5, 0.333_251_95, 65504.0, 6.103_515_6e-5, 5.960_464_5e-8] {
+            assert_eq!(f16_to_f32(f32_to_f16(v)), v);
+        }
+        assert_eq!(f16_to_f32(f32_to_f16(1e6)), f32::INFINITY);
+    }
+
+
+    #[test]
+    fn test_per_channel_is_tighter_than_per_tensor() {
+        // Two rows with very different ranges: a shared scale crushes the small row.
+        let values = [0.01f32, -0.02, 0.015, 0.0, 8.0, -6.0, 3.0, 1.0];
+        let err = |recon: Vec<f32>| values.iter().zip(recon).map(|(a, b)| (a - b).abs()).fold(0.0f32, f32::max);
+
+        let t = NfnTensor::encode("w", &[2, 4], &values, Precision::Int8);
+        let c = NfnTensor::encode("w", &[2, 4], &values, Precision::Int8PerChannel);
+        assert!(matches!(&c.data, TensorData::I8 { scales, .. } if scales.len() == 2));
+        assert!(err(c.to_f32()) < err(t.to_f32()));
+    }
+
+    #[test]
+    fn test_quantized_forward_matches_dequantized() {
+        let fu = BaseFU::create_adder();
+        let input = Array1::from(crate::fu::kind::encode_bits(0xA5C3, 16));
+        for precision in [Precision::F32, Precision::Int8, Precision::Int8PerChannel] {
+            let mut q = QuantizedFU::quantize(&fu, precision);
+            let mut deq = q.dequantize();
+            let diff = (&q.forward(&input) - &deq.forward(&input)).mapv(f32::abs).sum();
+            assert!(diff < 1e-4, "{:?}: {}", precision, diff);
+        }
+    }
+
+    #[test]
+    fn test_f16_forward_stays_close() {
+        let mut fu = BaseFU::create_comparator();
+        let mut q = QuantizedFU::quantize(&fu, Precision::F16);
+        let input = Array1::from(crate::fu::kind::encode_bits(0x11234, 17));
+        let diff = (&q.forward(&input) - &fu.forward(&input)).mapv(f32::abs).sum();
+        assert!(diff < 1e-2, "{}", diff);
+        assert_eq!(q.weight_bytes() * 2, QuantizedFU::quantize(&fu, Precision::F32).weight_bytes());
+    }
+}
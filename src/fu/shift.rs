@@ -0,0 +1,118 @@
+use ndarray::Array1;
+
+use super::{BaseFU, NeuralFunctionalUnit};
+
+/// Mode bits of the shifter's MODE port (LSB first).
+pub const SHIFT_MODE_RIGHT: u32 = 0b01;
+pub const SHIFT_MODE_ROTATE: u32 = 0b10;
+
+/// 8-bit neural barrel shifter / rotator (LSH, RSH, ROL, ROR) with carry-out.
+///
+/// Like `MultiplierFU`, the unit is decomposed into one small trained cell, a
+/// 2:1 multiplexer `(sel, x0, x1) -> sel ? x1 : x0` (8 cases). Three stages
+/// shift by 1, 2 and 4 under control of the AMOUNT bits. Right shifts are left
+/// shifts on the bit-reversed operand (pure wiring). The fill bit is a mux
+/// between 0 and the wrapped-around bit, selected by the rotate flag.
+///
+/// Carry is the last bit shifted (or rotated) out, as on x86: for amount 0 it is 0.
+#[derive(Debug, Clone)]
+pub struct ShifterFU<C: NeuralFunctionalUnit = BaseFU> {
+    pub cell: C,
+}
+
+impl<C: NeuralFunctionalUnit> ShifterFU<C> {
+    pub const WIDTH: usize = 8;
+
+    pub fn new(cell: C) -> Self {
+        Self { cell }
+    }
+
+    /// One mux evaluation with cleanup to 0.0/1.0.
+    fn mux(&mut self, sel: f32, x0: f32, x1: f32) -> f32 {
+        let out = self.cell.forward(&Array1::from(vec![sel, x0, x1]));
+        if out[0] > 0.5 { 1.0 } else { 0.0 }
+    }
+}
+
+impl<C: NeuralFunctionalUnit> NeuralFunctionalUnit for ShifterFU<C> {
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        // Input: A (8) + AMOUNT (3) + MODE (2). Output: RESULT (8) + CARRY (1).
+        let n = Self::WIDTH;
+        let bit = |i: usize| *input.get(i).unwrap_or(&0.0);
+        let right = bit(n + 3) > 0.5;
+        let rotate = bit(n + 4);
+
+        // Work LSB-first as a left shift; reverse the wiring for right shifts.
+        let mut bits: Vec<f32> = (0..n).map(bit).collect();
+        if right { bits.reverse(); }
+        let mut carry = 0.0;
+
+        for stage in 0..3 {
+            let k = 1 << stage;
+            let sel = bit(n + stage);
+            let mut next = Vec::with_capacity(n);
+            for i in 0..n {
+                let shifted = if i >= k {
+                    bits[i - k]
+                } else {
+                    self.mux(rotate, 0.0, bits[i + n - k])
+                };
+                next.push(self.mux(sel, bits[i], shifted));
+            }
+            carry = self.mux(sel, carry, bits[n - k]);
+            bits = next;
+        }
+
+        if right { bits.reverse(); }
+        bits.push(carry);
+        Array1::from(bits)
+    }
+
+    fn perturb(&mut self, amount: f32) {
+        self.cell.perturb(amount);
+    }
+
+    fn weights(&self) -> Option<&BaseFU> {
+        self.cell.weights()
+    }
+
+    fn weights_mut(&mut self) -> Option<&mut BaseFU> {
+        self.cell.weights_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fu::kind::{bits_match, shipped_unit};
+    use crate::fu::FUType;
+
+    /// A hand-built exact mux so the wiring can be checked independently of training.
+    struct IdealMux;
+    impl NeuralFunctionalUnit for IdealMux {
+        fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+            Array1::from(vec![if input[0] > 0.5 { input[2] } else { input[1] }])
+        }
+        fn perturb(&mut self, _amount: f32) {}
+    }
+
+    #[test]
+    fn test_wiring_matches_reference_exhaustively() {
+        let kind = FUType::SHIFT;
+        let mut fu = ShifterFU::new(IdealMux);
+        for idx in 0..kind.case_count() {
+            let (input, target) = kind.case(idx);
+            assert!(bits_match(&fu.forward(&input), &target), "case {}", idx);
+        }
+    }
+
+    #[test]
+    fn test_shipped_mux_cell_is_exact() {
+        let kind = FUType::SHIFT;
+        let mut fu = ShifterFU::new(shipped_unit("alu_shift", kind));
+        for idx in 0..kind.case_count() {
+            let (input, target) = kind.case(idx);
+            assert!(bits_match(&fu.forward(&input), &target), "case {}", idx);
+        }
+    }
+}
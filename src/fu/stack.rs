@@ -0,0 +1,318 @@
+use ndarray::{s, Array1};
+use serde::Deserialize;
+
+use super::kind::{decode_bits, encode_bits, PortLayout, PortSpec};
+use super::{Introspect, MemRequest, MemResponse, NeuralFunctionalUnit};
+
+/// RAM region holding the stack, set per unit in the manifest:
+/// `"stack": { "base": 32512, "size": 256 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct StackConfig {
+    pub base: u16,
+    pub size: u16,
+}
+
+impl Default for StackConfig {
+    /// The top 256 cells of RAM.
+    fn default() -> Self {
+        Self { base: 0x7F00, size: 0x100 }
+    }
+}
+
+impl StackConfig {
+    /// The region must lie inside RAM (0x2000 - 0x7FFF).
+    pub fn check(&self) -> anyhow::Result<()> {
+        let end = self.base as u32 + self.size as u32;
+        if self.base < 0x2000 || end > 0x8000 {
+            anyhow::bail!("stack region 0x{:X}..0x{:X} is outside RAM (0x2000 - 0x7FFF)", self.base, end);
+        }
+        Ok(())
+    }
+}
+
+/// Hardware stack in a RAM region, growing down from `base + size`.
+///
+/// Every input port is a trigger:
+/// - `SET_SP` (16) moves SP; it must stay inside the region.
+/// - `PUSH` (width) stores the value at `--SP`.
+/// - `POP` (1) loads `[SP++]` into `TOP`.
+/// - `PEEK` (8) loads `[SP + n]` into `TOP` without popping.
+/// - `CALL` (16) pushes the return address (the PC of the next bundle) and jumps there.
+/// - `RET` (1) pops a return address into the PC.
+///
+/// `SP` is readable as an output port. Pushing into a full stack or popping an
+/// empty one sets `FAULT` and is also a bus fault: the machine stops.
+#[derive(Debug, Clone)]
+pub struct StackPointerFU {
+    pub width: usize,
+    pub region: StackConfig,
+    pub sp: u16,
+    top: Array1<f32>,
+    fault: bool,
+    pub last_fault: Option<String>,
+    pending: Option<MemRequest>,
+    bus_fault: Option<String>,
+}
+
+impl StackPointerFU {
+    pub const SP_WIDTH: usize = 16;
+    pub const SET_SP_PORT: usize = 0;
+    pub const PUSH_PORT: usize = 1;
+    pub const POP_PORT: usize = 2;
+    pub const PEEK_PORT: usize = 3;
+    pub const CALL_PORT: usize = 4;
+    pub const RET_PORT: usize = 5;
+
+    pub fn new(width: usize, region: StackConfig) -> Self {
+        Self {
+            width,
+            region,
+            sp: region.base + region.size,
+            top: Array1::zeros(width),
+            fault: false,
+            last_fault: None,
+            pending: None,
+            bus_fault: None,
+        }
+    }
+
+    /// One past the highest stack cell; SP equals it when the stack is empty.
+    pub fn limit(&self) -> u16 {
+        self.region.base + self.region.size
+    }
+
+    pub fn depth(&self) -> usize {
+        (self.limit() - self.sp) as usize
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![
+                PortSpec::trigger("SET_SP", Self::SP_WIDTH),
+                PortSpec::trigger("PUSH", self.width),
+                PortSpec::trigger("POP", 1),
+                PortSpec::trigger("PEEK", 8),
+                PortSpec::trigger("CALL", Self::SP_WIDTH),
+                PortSpec::trigger("RET", 1),
+            ],
+            outputs: vec![
+                PortSpec::new("TOP", self.width),
+                PortSpec::new("SP", Self::SP_WIDTH),
+                PortSpec::new("FAULT", 1),
+            ],
+        }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        let mut out = self.top.to_vec();
+        out.extend(encode_bits(self.sp as u32, Self::SP_WIDTH));
+        out.push(if self.fault { 1.0 } else { 0.0 });
+        Array1::from(out)
+    }
+
+    fn set_fault(&mut self, msg: String) {
+        self.fault = true;
+        self.last_fault = Some(msg);
+    }
+
+    /// Overflow and underflow stop the machine.
+    fn raise(&mut self, msg: String) {
+        self.bus_fault = Some(msg.clone());
+        self.set_fault(msg);
+    }
+
+    /// Claim the next free cell, or fault if the stack is full.
+    fn push_slot(&mut self) -> Option<u16> {
+        if self.sp <= self.region.base {
+            self.raise(format!("stack overflow (SP 0x{:X})", self.sp));
+            return None;
+        }
+        self.sp -= 1;
+        Some(self.sp)
+    }
+
+    /// Release the top cell, or fault if the stack is empty.
+    fn pop_slot(&mut self) -> Option<u16> {
+        if self.sp >= self.limit() {
+            self.raise(format!("stack underflow (SP 0x{:X})", self.sp));
+            return None;
+        }
+        self.sp += 1;
+        Some(self.sp - 1)
+    }
+}
+
+impl NeuralFunctionalUnit for StackPointerFU {
+    /// Without port information the input is pushed.
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        let mut padded = vec![0.0; Self::SP_WIDTH];
+        padded.extend(input.iter());
+        self.trigger(Self::PUSH_PORT, &Array1::from(padded))
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn trigger(&mut self, port: usize, input: &Array1<f32>) -> Array1<f32> {
+        // Input: SET_SP (16) + PUSH (width) + POP (1) + PEEK (8) + CALL (16) + RET (1)
+        let w = self.width;
+        let field = |start: usize, len: usize| {
+            let end = (start + len).min(input.len());
+            input.slice(s![start.min(end)..end]).to_vec()
+        };
+        let at = |p: usize| [Self::SP_WIDTH, w, 1, 8, Self::SP_WIDTH][..p].iter().sum::<usize>();
+
+        match port {
+            Self::SET_SP_PORT => {
+                let sp = decode_bits(&field(0, Self::SP_WIDTH));
+                if (self.region.base as u32..=self.limit() as u32).contains(&sp) {
+                    self.sp = sp as u16;
+                    self.fault = false;
+                } else {
+                    self.set_fault(format!("SP 0x{:X} outside stack 0x{:X}-0x{:X}", sp, self.region.base, self.limit()));
+                }
+            }
+            Self::PUSH_PORT => {
+                let data: Vec<f32> = field(at(Self::PUSH_PORT), w).iter().map(|&v| if v > 0.5 { 1.0 } else { 0.0 }).collect();
+                if let Some(slot) = self.push_slot() {
+                    self.pending = Some(MemRequest::Store(slot, Array1::from(data)));
+                }
+            }
+            Self::POP_PORT => {
+                if let Some(slot) = self.pop_slot() {
+                    self.pending = Some(MemRequest::Load(slot));
+                }
+            }
+            Self::PEEK_PORT => {
+                let slot = self.sp as u32 + decode_bits(&field(at(Self::PEEK_PORT), 8));
+                if slot < self.limit() as u32 {
+                    self.pending = Some(MemRequest::Load(slot as u16));
+                } else {
+                    self.raise(format!("stack underflow (peek 0x{:X} above SP 0x{:X})", slot, self.sp));
+                }
+            }
+            Self::CALL_PORT => {
+                let target = decode_bits(&field(at(Self::CALL_PORT), Self::SP_WIDTH)) as u16;
+                if let Some(slot) = self.push_slot() {
+                    self.pending = Some(MemRequest::Call { slot, target });
+                }
+            }
+            _ => {
+                if let Some(slot) = self.pop_slot() {
+                    self.pending = Some(MemRequest::JumpVia(slot));
+                }
+            }
+        }
+        self.outputs()
+    }
+
+    fn mem_request(&mut self) -> Option<MemRequest> {
+        self.pending.take()
+    }
+
+    fn mem_complete(&mut self, response: MemResponse) -> Option<Array1<f32>> {
+        match response {
+            MemResponse::Loaded(v) => {
+                let v = v.unwrap_or_else(|| Array1::zeros(self.width));
+                self.top = Array1::from_shape_fn(self.width, |i| *v.get(i).unwrap_or(&0.0));
+                self.fault = false;
+            }
+            MemResponse::Stored => self.fault = false,
+            MemResponse::Resolved { .. } => {}
+            MemResponse::Fault(msg) => self.set_fault(msg),
+        }
+        Some(self.outputs())
+    }
+
+    fn take_fault(&mut self) -> Option<String> {
+        self.bus_fault.take()
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value((self.sp, &self.top, self.fault, &self.last_fault)).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        (self.sp, self.top, self.fault, self.last_fault) = serde_json::from_value(state)?;
+        Ok(())
+    }
+
+    fn introspect(&self) -> Option<&dyn Introspect> { Some(self) }
+}
+
+impl Introspect for StackPointerFU {
+    fn kind(&self) -> &'static str { "stack" }
+
+    fn port_layout(&self) -> Option<PortLayout> { Some(StackPointerFU::port_layout(self)) }
+
+    fn state(&self) -> Vec<(&'static str, String)> {
+        let mut state = vec![
+            ("sp", format!("0x{:04X}", self.sp)),
+            ("depth", format!("{} / {}", self.depth(), self.region.size)),
+            ("region", format!("0x{:04X}..0x{:04X}", self.region.base, self.limit())),
+            ("top", decode_bits(self.top.as_slice().unwrap_or(&[])).to_string()),
+            ("fault", self.fault.to_string()),
+        ];
+        if let Some(fault) = &self.last_fault {
+            state.push(("last_fault", fault.clone()));
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{MoveOp, SystemBus};
+    use crate::register::NeuralRegister;
+
+    #[test]
+    fn test_push_pop_peek_and_bounds() {
+        let mut bus = SystemBus::new();
+        for r in 0..4 {
+            bus.add_register(r, 8);
+        }
+        let stack = StackPointerFU::new(8, StackConfig { base: 0x7000, size: 2 });
+        let layout = stack.port_layout();
+        bus.add_unit_with_ports(0x1200, Box::new(stack), layout).unwrap();
+        // Ports: SET_SP 0x1200, PUSH 0x1201, POP 0x1202, PEEK 0x1203, CALL 0x1204, RET 0x1205,
+        //        TOP 0x1206, SP 0x1207, FAULT 0x1208
+        let sp = |bus: &SystemBus| decode_bits(bus.port_latches[&0x1207].as_slice().unwrap());
+        let top = |bus: &SystemBus| decode_bits(bus.port_latches[&0x1206].as_slice().unwrap());
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+
+        bus.registers.insert(0, NeuralRegister::from_symbolic(8, 7));
+        bus.registers.insert(1, NeuralRegister::from_symbolic(8, 9));
+        bus.execute(&mv(0, 0x1201));
+        bus.execute(&mv(1, 0x1201));
+        assert_eq!(sp(&bus), 0x7000);
+        assert_eq!(bus.ram[&0x7001], Array1::from(encode_bits(7, 8)));
+
+        // PEEK 1 reads below the top without popping
+        bus.execute(&mv(2, 0x1203)); // R2 = 0 -> PEEK 0
+        assert_eq!(top(&bus), 9);
+        bus.registers.insert(3, NeuralRegister::from_symbolic(8, 1));
+        bus.execute(&mv(3, 0x1203));
+        assert_eq!((top(&bus), sp(&bus)), (7, 0x7000));
+
+        // Full: the third push overflows, SP stays
+        bus.execute(&mv(0, 0x1201));
+        assert_eq!(bus.port_latches[&0x1208][0], 1.0);
+        assert!(bus.bus_fault.take().unwrap().contains("overflow"));
+        assert_eq!(sp(&bus), 0x7000);
+
+        bus.execute(&mv(3, 0x1202));
+        assert_eq!(top(&bus), 9);
+        bus.execute(&mv(3, 0x1202));
+        assert_eq!((top(&bus), sp(&bus)), (7, 0x7002));
+        assert_eq!(bus.port_latches[&0x1208][0], 0.0);
+        bus.execute(&mv(3, 0x1202));
+        assert!(bus.bus_fault.take().unwrap().contains("underflow"));
+
+        // SP is writable over the bus, but only inside the region
+        bus.execute(&mv(0x1207, 0x1200));
+        assert!(bus.bus_fault.is_none());
+        bus.execute(&mv(1, 0x1200)); // 0x0009 is far outside
+        assert_eq!(bus.port_latches[&0x1208][0], 1.0);
+        assert_eq!(sp(&bus), 0x7002);
+    }
+}
@@ -0,0 +1,68 @@
+use ndarray::Array1;
+
+use super::kind::{decode_bits, encode_bits, PortLayout, PortSpec};
+use super::NeuralFunctionalUnit;
+
+/// Programmable interval timer: raises its interrupt line every `PERIOD` ticks.
+///
+/// Writing `PERIOD` (16, trigger) restarts the count; 0 stops the timer.
+/// `COUNT` shows the ticks since the last expiry.
+#[derive(Debug, Clone, Default)]
+pub struct TimerFU {
+    pub period: u16,
+    pub count: u16,
+    /// Expiries not yet collected by the bus.
+    fired: bool,
+}
+
+impl TimerFU {
+    pub fn new(period: u16) -> Self {
+        Self { period, count: 0, fired: false }
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout { inputs: vec![PortSpec::trigger("PERIOD", 16)], outputs: vec![PortSpec::new("COUNT", 16)] }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        Array1::from(encode_bits(self.count as u32, 16))
+    }
+}
+
+impl NeuralFunctionalUnit for TimerFU {
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        self.period = decode_bits(input.as_slice().unwrap_or(&[])) as u16;
+        self.count = 0;
+        self.outputs()
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn tick(&mut self) {
+        if self.period == 0 {
+            return;
+        }
+        self.count += 1;
+        if self.count >= self.period {
+            self.count = 0;
+            self.fired = true;
+        }
+    }
+
+    fn poll(&self) -> Option<Array1<f32>> {
+        Some(self.outputs())
+    }
+
+    fn take_irq(&mut self) -> bool {
+        std::mem::take(&mut self.fired)
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value((self.period, self.count, self.fired)).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        (self.period, self.count, self.fired) = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
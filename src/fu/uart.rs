@@ -0,0 +1,274 @@
+use ndarray::Array1;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::sync::{mpsc, Arc, Mutex};
+
+use super::kind::{decode_bits, encode_bits, PortLayout, PortSpec};
+use super::{Introspect, NeuralFunctionalUnit};
+
+/// Where transmitted bytes go.
+pub trait ByteSink: Send + Sync {
+    fn write_byte(&mut self, byte: u8);
+}
+
+/// Where received bytes come from. `None` means nothing is waiting right now.
+pub trait ByteSource: Send + Sync {
+    fn read_byte(&mut self) -> Option<u8>;
+}
+
+/// Host stdout, flushed per byte.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl ByteSink for StdoutSink {
+    fn write_byte(&mut self, byte: u8) {
+        let mut out = std::io::stdout();
+        let _ = out.write_all(&[byte]).and_then(|_| out.flush());
+    }
+}
+
+/// Console buffer shared with the GUI (or a test).
+impl ByteSink for Arc<Mutex<String>> {
+    fn write_byte(&mut self, byte: u8) {
+        if let Ok(mut buf) = self.lock() {
+            buf.push(byte as char);
+        }
+    }
+}
+
+/// Shared in-memory input: tests script it, the GUI text box feeds it.
+#[derive(Debug, Clone, Default)]
+pub struct InputQueue(Arc<Mutex<VecDeque<u8>>>);
+
+impl InputQueue {
+    pub fn push_str(&self, text: &str) {
+        if let Ok(mut queue) = self.0.lock() {
+            queue.extend(text.bytes());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().map_or(0, |q| q.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ByteSource for InputQueue {
+    fn read_byte(&mut self) -> Option<u8> {
+        self.0.lock().ok()?.pop_front()
+    }
+}
+
+/// Contents of a host file, delivered once.
+#[derive(Debug, Clone, Default)]
+pub struct FileSource(VecDeque<u8>);
+
+impl FileSource {
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        Ok(Self(std::fs::read(path)?.into()))
+    }
+}
+
+impl ByteSource for FileSource {
+    fn read_byte(&mut self) -> Option<u8> {
+        self.0.pop_front()
+    }
+}
+
+/// Host stdin, read on a background thread so the emulator never blocks.
+pub struct StdinSource(Mutex<mpsc::Receiver<u8>>);
+
+impl StdinSource {
+    pub fn spawn() -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for byte in std::io::stdin().lock().bytes() {
+                let Ok(byte) = byte else { break };
+                if tx.send(byte).is_err() {
+                    break;
+                }
+            }
+        });
+        Self(Mutex::new(rx))
+    }
+}
+
+impl ByteSource for StdinSource {
+    fn read_byte(&mut self) -> Option<u8> {
+        self.0.lock().ok()?.try_recv().ok()
+    }
+}
+
+/// Serial port at 0x8000.
+///
+/// Ports: `TX` (8, trigger) sends a byte to the sink; `RX_ACK` (1, trigger)
+/// consumes the received byte. Outputs: `RX` (8), `RX_READY` (1), `TX_BUSY` (1).
+/// A new byte is fetched from the source on the tick after `RX_READY` drops,
+/// and raises the unit's interrupt line if it is wired to one. `TX_BUSY` stays
+/// high for `tx_ticks` ticks after each send; a send while busy still goes out
+/// but is counted in `tx_overruns`.
+pub struct UartFU {
+    pub sink: Box<dyn ByteSink>,
+    pub source: Option<Box<dyn ByteSource>>,
+    pub tx_ticks: usize,
+    pub tx_overruns: usize,
+    rx: Option<u8>,
+    tx_left: usize,
+    rx_irq: bool,
+}
+
+impl UartFU {
+    pub const TX_PORT: usize = 0;
+    pub const RX_ACK_PORT: usize = 1;
+
+    /// Transmit-only UART printing to stdout.
+    pub fn new() -> Self {
+        Self::with_output(Box::new(StdoutSink))
+    }
+
+    /// Transmit into a shared console buffer.
+    pub fn with_sink(sink: Arc<Mutex<String>>) -> Self {
+        Self::with_output(Box::new(sink))
+    }
+
+    pub fn with_output(sink: Box<dyn ByteSink>) -> Self {
+        Self { sink, source: None, tx_ticks: 0, tx_overruns: 0, rx: None, tx_left: 0, rx_irq: false }
+    }
+
+    pub fn with_source(self, source: Box<dyn ByteSource>) -> Self {
+        Self { source: Some(source), ..self }
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![PortSpec::trigger("TX", 8), PortSpec::trigger("RX_ACK", 1)],
+            outputs: vec![PortSpec::new("RX", 8), PortSpec::new("RX_READY", 1), PortSpec::new("TX_BUSY", 1)],
+        }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        let mut out = encode_bits(self.rx.unwrap_or(0) as u32, 8);
+        out.push(if self.rx.is_some() { 1.0 } else { 0.0 });
+        out.push(if self.tx_left > 0 { 1.0 } else { 0.0 });
+        Array1::from(out)
+    }
+}
+
+impl Default for UartFU {
+    fn default() -> Self { Self::new() }
+}
+
+impl NeuralFunctionalUnit for UartFU {
+    /// Without port information the input is transmitted.
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        self.trigger(Self::TX_PORT, input)
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn trigger(&mut self, port: usize, input: &Array1<f32>) -> Array1<f32> {
+        if port == Self::TX_PORT {
+            // Interpret input as ASCII char
+            let byte = decode_bits(&input.iter().take(8).copied().collect::<Vec<_>>()) as u8;
+            if self.tx_left > 0 {
+                self.tx_overruns += 1;
+            }
+            self.sink.write_byte(byte);
+            self.tx_left = self.tx_ticks;
+        } else {
+            self.rx = None;
+        }
+        self.outputs()
+    }
+
+    fn tick(&mut self) {
+        self.tx_left = self.tx_left.saturating_sub(1);
+        if self.rx.is_none() {
+            self.rx = self.source.as_mut().and_then(|s| s.read_byte());
+            self.rx_irq |= self.rx.is_some();
+        }
+    }
+
+    fn poll(&self) -> Option<Array1<f32>> {
+        Some(self.outputs())
+    }
+
+    fn take_irq(&mut self) -> bool {
+        std::mem::take(&mut self.rx_irq)
+    }
+
+    /// The received byte and transmit timing; the source's own position is not saved.
+    fn save_state(&self) -> Option<serde_json::Value> {
+        serde_json::to_value((self.rx, self.tx_left, self.tx_overruns, self.rx_irq)).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        (self.rx, self.tx_left, self.tx_overruns, self.rx_irq) = serde_json::from_value(state)?;
+        Ok(())
+    }
+
+    fn introspect(&self) -> Option<&dyn Introspect> { Some(self) }
+}
+
+impl Introspect for UartFU {
+    fn kind(&self) -> &'static str { "uart" }
+
+    fn port_layout(&self) -> Option<PortLayout> { Some(UartFU::port_layout(self)) }
+
+    fn state(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("rx", self.rx.map_or("-".to_string(), |b| format!("0x{:02X} {:?}", b, b as char))),
+            ("tx_busy", format!("{} ticks", self.tx_left)),
+            ("tx_overruns", self.tx_overruns.to_string()),
+            ("rx_irq", self.rx_irq.to_string()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{MoveOp, SystemBus};
+    use crate::register::NeuralRegister;
+
+    #[test]
+    fn test_echo_from_queue() {
+        let console = Arc::new(Mutex::new(String::new()));
+        let input = InputQueue::default();
+        let mut uart = UartFU::with_sink(console.clone()).with_source(Box::new(input.clone()));
+        uart.tx_ticks = 2;
+        let layout = uart.port_layout();
+        let mut bus = SystemBus::new();
+        bus.add_register(0, 8);
+        bus.add_unit_with_ports(0x8000, Box::new(uart), layout).unwrap();
+        // Ports: TX 0x8000, RX_ACK 0x8001, RX 0x8002, RX_READY 0x8003, TX_BUSY 0x8004
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+
+        bus.tick_all();
+        assert_eq!(bus.port_latches[&0x8003][0], 0.0, "nothing queued yet");
+        input.push_str("hi");
+        bus.tick_all();
+        assert_eq!(bus.port_latches[&0x8003][0], 1.0);
+
+        // Echo both bytes, acknowledging each
+        for _ in 0..2 {
+            bus.execute(&mv(0x8002, 0));
+            bus.execute(&mv(0, 0x8000));
+            bus.execute(&mv(0, 0x8001));
+            bus.tick_all();
+        }
+        assert_eq!(console.lock().unwrap().as_str(), "hi");
+        assert_eq!(bus.port_latches[&0x8003][0], 0.0);
+        assert!(input.is_empty());
+
+        // Back-to-back sends inside the busy window are overruns, but still delivered
+        bus.registers.insert(0, NeuralRegister::from_symbolic(8, b'!' as u32));
+        bus.execute(&mv(0, 0x8000));
+        assert_eq!(bus.port_latches[&0x8004][0], 1.0);
+        bus.execute(&mv(0, 0x8000));
+        assert_eq!(console.lock().unwrap().as_str(), "hi!!");
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::Context;
+use ndarray::{s, Array1};
+use std::sync::{Arc, Mutex};
+
+use super::kind::{decode_bits, PortLayout, PortSpec};
+use super::{MemRequest, MemResponse, NeuralFunctionalUnit};
+
+/// Monochrome 64×32 frame, row-major, `true` = lit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pixels: Vec<bool>,
+}
+
+/// Frame shared between the video unit and whoever displays or dumps it.
+pub type SharedFrame = Arc<Mutex<Frame>>;
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self { pixels: vec![false; Self::WIDTH * Self::HEIGHT] }
+    }
+}
+
+impl Frame {
+    pub const WIDTH: usize = 64;
+    pub const HEIGHT: usize = 32;
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.pixels[(y % Self::HEIGHT) * Self::WIDTH + x % Self::WIDTH]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, on: bool) {
+        self.pixels[(y % Self::HEIGHT) * Self::WIDTH + x % Self::WIDTH] = on;
+    }
+
+    /// Flip a pixel; true if it was lit (a CHIP-8 collision).
+    pub fn toggle(&mut self, x: usize, y: usize) -> bool {
+        let was = self.get(x, y);
+        self.set(x, y, !was);
+        was
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(false);
+    }
+
+    pub fn lit(&self) -> usize {
+        self.pixels.iter().filter(|&&p| p).count()
+    }
+
+    /// Plain (P1) PBM: readable in a diff, so golden frames can live in the repo.
+    pub fn to_pbm(&self) -> String {
+        let mut out = format!("P1\n{} {}\n", Self::WIDTH, Self::HEIGHT);
+        for row in self.pixels.chunks(Self::WIDTH) {
+            let line: Vec<&str> = row.iter().map(|&p| if p { "1" } else { "0" }).collect();
+            out.push_str(&line.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// 8-bit grayscale PNG, one image pixel per frame pixel.
+    pub fn to_png(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, Self::WIDTH as u32, Self::HEIGHT as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = self.pixels.iter().map(|&p| if p { 0xFF } else { 0x00 }).collect();
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(bytes)
+    }
+
+    /// Write a PNG or PBM, chosen by the file extension.
+    pub fn save(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        let bytes = match path.extension().and_then(|e| e.to_str()) {
+            Some("pbm") => self.to_pbm().into_bytes(),
+            Some("png") => self.to_png()?,
+            _ => anyhow::bail!("unsupported frame format {:?} (use .png or .pbm)", path),
+        };
+        std::fs::write(path, bytes).with_context(|| format!("writing frame to {:?}", path))
+    }
+}
+
+/// CHIP-8-style display at 0x8100.
+///
+/// Inputs: `X` (8) and `Y` (8) select a position, `SPRITE` (16) a RAM address.
+/// Triggers: `PIXEL` (1) sets or clears the pixel at (X, Y); `BLIT` (4) XORs an
+/// n-row sprite from RAM at (X, Y), one 8-bit cell per row with the MSB on the
+/// left; `CLEAR` (1) blanks the frame. Coordinates wrap. `COLLISION` reports
+/// whether the last blit turned off a lit pixel.
+pub struct VideoFU {
+    pub frame: SharedFrame,
+    collision: bool,
+    /// Next sprite row to draw: (x, y, row, rows, sprite address).
+    blit: Option<(usize, usize, usize, usize, u16)>,
+    /// Bumped on every change to the frame or collision flag, see `state_version`.
+    version: u64,
+}
+
+impl VideoFU {
+    pub const PIXEL_PORT: usize = 2;
+    pub const BLIT_PORT: usize = 4;
+    pub const CLEAR_PORT: usize = 5;
+
+    pub fn new(frame: SharedFrame) -> Self {
+        Self { frame, collision: false, blit: None, version: 0 }
+    }
+
+    pub fn port_layout(&self) -> PortLayout {
+        PortLayout {
+            inputs: vec![
+                PortSpec::new("X", 8),
+                PortSpec::new("Y", 8),
+                PortSpec::trigger("PIXEL", 1),
+                PortSpec::new("SPRITE", 16),
+                PortSpec::trigger("BLIT", 4),
+                PortSpec::trigger("CLEAR", 1),
+            ],
+            outputs: vec![PortSpec::new("COLLISION", 1)],
+        }
+    }
+
+    fn outputs(&self) -> Array1<f32> {
+        Array1::from(vec![if self.collision { 1.0 } else { 0.0 }])
+    }
+}
+
+impl NeuralFunctionalUnit for VideoFU {
+    /// Without port information the input is X + Y + PIXEL.
+    fn forward(&mut self, input: &Array1<f32>) -> Array1<f32> {
+        self.trigger(Self::PIXEL_PORT, input)
+    }
+
+    fn perturb(&mut self, _amount: f32) {}
+
+    fn trigger(&mut self, port: usize, input: &Array1<f32>) -> Array1<f32> {
+        // Input: X (8) + Y (8) + PIXEL (1) + SPRITE (16) + BLIT (4) + CLEAR (1)
+        let field = |start: usize, len: usize| {
+            let end = (start + len).min(input.len());
+            decode_bits(&input.slice(s![start.min(end)..end]).to_vec()) as usize
+        };
+        let (x, y) = (field(0, 8), field(8, 8));
+        let Ok(mut frame) = self.frame.lock() else { return self.outputs() };
+        self.version += 1;
+        match port {
+            Self::PIXEL_PORT => frame.set(x, y, field(16, 1) == 1),
+            Self::BLIT_PORT => {
+                self.collision = false;
+                let rows = field(33, 4);
+                if rows > 0 {
+                    self.blit = Some((x, y, 0, rows, field(17, 16) as u16));
+                }
+            }
+            Self::CLEAR_PORT => frame.clear(),
+            _ => {}
+        }
+        drop(frame);
+        self.outputs()
+    }
+
+    fn mem_request(&mut self) -> Option<MemRequest> {
+        let (_, _, row, _, sprite) = self.blit?;
+        Some(MemRequest::Load(sprite.wrapping_add(row as u16)))
+    }
+
+    fn mem_complete(&mut self, response: MemResponse) -> Option<Array1<f32>> {
+        let (x, y, row, rows, sprite) = self.blit.take()?;
+        let MemResponse::Loaded(cell) = response else {
+            return Some(self.outputs()); // Sprite outside RAM: stop drawing
+        };
+        let byte = cell.map_or(0, |v| decode_bits(v.as_slice().unwrap_or(&[])));
+        self.version += 1;
+        if let Ok(mut frame) = self.frame.lock() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 && frame.toggle(x + bit, y + row) {
+                    self.collision = true;
+                }
+            }
+        }
+        if row + 1 < rows {
+            self.blit = Some((x, y, row + 1, rows, sprite));
+        }
+        Some(self.outputs())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let frame = self.frame.lock().ok()?;
+        serde_json::to_value((&frame.pixels, self.collision)).ok()
+    }
+
+    fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+        let (pixels, collision): (Vec<bool>, bool) = serde_json::from_value(state)?;
+        if pixels.len() != Frame::WIDTH * Frame::HEIGHT {
+            anyhow::bail!("frame has {} pixels, expected {}", pixels.len(), Frame::WIDTH * Frame::HEIGHT);
+        }
+        self.frame.lock().map_err(|_| anyhow::anyhow!("frame lock poisoned"))?.pixels = pixels;
+        self.collision = collision;
+        self.version += 1;
+        Ok(())
+    }
+
+    fn state_version(&self) -> Option<u64> {
+        Some(self.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{MoveOp, SystemBus};
+    use crate::fu::kind::encode_bits;
+    use crate::register::NeuralRegister;
+
+    #[test]
+    fn test_sprite_blit_matches_golden_frame() {
+        let frame = SharedFrame::default();
+        let video = VideoFU::new(frame.clone());
+        let layout = video.port_layout();
+        let mut bus = SystemBus::new();
+        for r in 0..4 {
+            bus.add_register(r, 8);
+        }
+        bus.add_unit_with_ports(0x8100, Box::new(video), layout).unwrap();
+        // Ports: X 0x8100, Y 0x8101, PIXEL 0x8102, SPRITE 0x8103, BLIT 0x8104, CLEAR 0x8105, COLLISION 0x8106
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+
+        // CHIP-8 font "0" at 0x2050, pointer to it at 0x2000
+        for (i, row) in [0xF0, 0x90, 0x90, 0x90, 0xF0].into_iter().enumerate() {
+            bus.ram.insert(0x2050 + i as u16, Array1::from(encode_bits(row, 8)));
+        }
+        bus.ram.insert(0x2000, Array1::from(encode_bits(0x2050, 16)));
+        bus.registers.insert(0, NeuralRegister::from_symbolic(8, 62)); // Wraps on the right edge
+        bus.registers.insert(1, NeuralRegister::from_symbolic(8, 1));
+        bus.registers.insert(2, NeuralRegister::from_symbolic(8, 5));
+        bus.registers.insert(3, NeuralRegister::from_symbolic(8, 1));
+        for op in [mv(0, 0x8100), mv(1, 0x8101), mv(0x2000, 0x8103), mv(2, 0x8104)] {
+            bus.execute(&op);
+        }
+        assert_eq!(bus.port_latches[&0x8106][0], 0.0);
+        // A plotted pixel inside the glyph's hole
+        bus.registers.insert(0, NeuralRegister::from_symbolic(8, 0));
+        bus.registers.insert(1, NeuralRegister::from_symbolic(8, 3));
+        for op in [mv(0, 0x8100), mv(1, 0x8101), mv(3, 0x8102)] {
+            bus.execute(&op);
+        }
+
+        let golden = include_str!("../../assets/golden/video_sprite.pbm");
+        assert_eq!(frame.lock().unwrap().to_pbm(), golden);
+        assert!(frame.lock().unwrap().to_png().unwrap().starts_with(b"\x89PNG"));
+
+        // Drawing the same sprite again erases it and reports the collision
+        bus.registers.insert(0, NeuralRegister::from_symbolic(8, 62));
+        bus.registers.insert(1, NeuralRegister::from_symbolic(8, 1));
+        for op in [mv(0, 0x8100), mv(1, 0x8101), mv(2, 0x8104)] {
+            bus.execute(&op);
+        }
+        assert_eq!(bus.port_latches[&0x8106][0], 1.0);
+        assert_eq!(frame.lock().unwrap().lit(), 1);
+        bus.execute(&mv(3, 0x8105));
+        assert_eq!(frame.lock().unwrap().lit(), 0);
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::kind::{Accuracy, BitEncoding, FUType, PortLayout};
+use super::nfn::{activation_tag, NfnFile, Precision, NFN_MAGIC};
+use super::BaseFU;
+
+/// Identifies a self-describing weight file.
+pub const WEIGHT_FORMAT_MAGIC: &str = "ntse-fu-weights";
+/// Current container version. Version 0 is reserved for bare `BaseFU` dumps.
+pub const WEIGHT_FORMAT_VERSION: u32 = 1;
+
+/// Hyperparameters a unit was trained with, kept for provenance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrainingInfo {
+    pub learning_rate: f32,
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub seed: u64,
+}
+
+/// Outcome of the verification pass run after training.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VerificationInfo {
+    pub cases: usize,
+    pub errors: usize,
+    pub exhaustive: bool,
+    pub accuracy: f32,
+}
+
+impl VerificationInfo {
+    pub fn exhaustive(acc: Accuracy) -> Self {
+        Self { cases: acc.cases, errors: acc.errors, exhaustive: true, accuracy: acc.ratio() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WeightHeader {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    pub kind: Option<FUType>,
+    pub ports: PortLayout,
+    pub encoding: BitEncoding,
+    pub training: Option<TrainingInfo>,
+    pub verification: Option<VerificationInfo>,
+    /// `fnv1a64:<hex>` over the tensor shapes, values and activations.
+    pub content_hash: String,
+}
+
+/// Versioned container for a trained `BaseFU`: a header describing what the
+/// weights compute and how they were produced, followed by the weights.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeightFile {
+    pub header: WeightHeader,
+    pub weights: BaseFU,
+}
+
+impl WeightFile {
+    pub fn new(name: &str, kind: Option<FUType>, weights: BaseFU) -> Self {
+        let ports = kind.map(|k| k.port_layout()).unwrap_or_default();
+        let header = WeightHeader {
+            format: WEIGHT_FORMAT_MAGIC.to_string(),
+            version: WEIGHT_FORMAT_VERSION,
+            name: name.to_string(),
+            kind,
+            ports,
+            encoding: BitEncoding::default(),
+            training: None,
+            verification: None,
+            content_hash: content_hash(&weights),
+        };
+        Self { header, weights }
+    }
+
+    /// Wrap a bare (version 0) dump. The kind is inferred from tensor shapes.
+    pub fn from_bare(name: &str, weights: BaseFU) -> Self {
+        let mut file = Self::new(name, FUType::infer(&weights), weights);
+        file.header.version = 0;
+        file
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.header.version == 0
+    }
+
+    pub fn from_json_str(name: &str, json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        if value.get("header").is_none() {
+            let weights: BaseFU = serde_json::from_value(value)
+                .map_err(|e| anyhow!("{}: not a weight container or bare BaseFU dump: {}", name, e))?;
+            return Ok(Self::from_bare(name, weights));
+        }
+
+        let file: WeightFile = serde_json::from_value(value)?;
+        if file.header.format != WEIGHT_FORMAT_MAGIC {
+            bail!("{}: unknown weight format '{}'", name, file.header.format);
+        }
+        if file.header.version > WEIGHT_FORMAT_VERSION {
+            bail!("{}: weight format version {} is newer than supported {}",
+                name, file.header.version, WEIGHT_FORMAT_VERSION);
+        }
+        file.check_hash()?;
+        Ok(file)
+    }
+
+    /// Load a JSON container, a bare dump or a binary `.nfn` file (detected by magic).
+    /// Bare dumps are named after the file stem.
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        if bytes.starts_with(NFN_MAGIC) {
+            return NfnFile::from_bytes(&bytes)?.to_weight_file();
+        }
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        Self::from_json_str(&name, std::str::from_utf8(&bytes)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Write the binary `.nfn` form with tensors stored at `precision`.
+    pub fn save_nfn(&self, path: &Path, precision: Precision) -> Result<()> {
+        std::fs::write(path, NfnFile::from_weight_file(self, precision).to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Recompute the hash after the weights were changed in place.
+    pub fn rehash(&mut self) {
+        self.header.content_hash = content_hash(&self.weights);
+    }
+
+    pub fn check_hash(&self) -> Result<()> {
+        let actual = content_hash(&self.weights);
+        if actual != self.header.content_hash {
+            return Err(anyhow!("{}: content hash mismatch (header {}, computed {})",
+                self.header.name, self.header.content_hash, actual));
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a over everything that determines the unit's behaviour.
+pub fn content_hash(fu: &BaseFU) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for b in bytes {
+            hash ^= *b as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    for dim in fu.w1.shape().iter().chain(fu.w2.shape()) {
+        feed(&(*dim as u64).to_le_bytes());
+    }
+    let tensors = fu.w1.iter().chain(fu.b1.iter()).chain(fu.w2.iter()).chain(fu.b2.iter());
+    for v in tensors {
+        feed(&v.to_le_bytes());
+    }
+    for act in [&fu.active_hidden, &fu.active_output] {
+        feed(&[activation_tag(act)]);
+    }
+
+    format!("fnv1a64:{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_roundtrip() {
+        let mut file = WeightFile::new("cmp", Some(FUType::CMP), BaseFU::create_comparator());
+        file.header.training = Some(TrainingInfo { learning_rate: 0.1, epochs: 1, batch_size: 1, seed: 7 });
+        let json = serde_json::to_string(&file).unwrap();
+
+        let back = WeightFile::from_json_str("cmp", &json).unwrap();
+        assert!(!back.is_legacy());
+        assert_eq!(back.header, file.header);
+    }
+
+    #[test]
+    fn test_tampered_weights_rejected() {
+        let mut file = WeightFile::new("add", Some(FUType::ADDER), BaseFU::create_adder());
+        file.weights.w1[[0, 0]] += 1.0;
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(WeightFile::from_json_str("add", &json).is_err());
+    }
+
+    #[test]
+    fn test_reads_bare_asset() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fus/alu_adder.json");
+        let file = WeightFile::load(&path).unwrap();
+        assert!(file.is_legacy());
+        assert_eq!(file.header.name, "alu_adder");
+        assert_eq!(file.header.kind, Some(FUType::ADDER));
+    }
+}
@@ -0,0 +1,361 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ndarray::Array1;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::breakpoint::Breakpoint;
+use crate::fu::kind::{decode_bits, encode_bits};
+use crate::register::NeuralRegister;
+use crate::system::SystemEmulator;
+
+/// GDB register numbers: R0–R15, then the PC (a bundle index).
+pub const GDB_REGISTERS: usize = 17;
+const PC_REGNUM: usize = 16;
+/// Cycles between checks for a Ctrl-C from the client while continuing.
+const CONTINUE_SLICE: usize = 1_000;
+
+/// Sent for `qXfer:features:read:target.xml`, so GDB knows the register file without an architecture.
+fn target_xml() -> String {
+    let regs: String = (0..16)
+        .map(|i| format!("<reg name=\"r{}\" bitsize=\"32\" type=\"uint32\"/>", i))
+        .collect();
+    format!(
+        "<?xml version=\"1.0\"?><!DOCTYPE target SYSTEM \"gdb-target.dtd\"><target version=\"1.0\">\
+         <feature name=\"org.ntse.core\">{}<reg name=\"pc\" bitsize=\"32\" type=\"code_ptr\"/></feature></target>",
+        regs
+    )
+}
+
+/// Accept one debugger on `listener` and serve it until it detaches, kills or disconnects.
+///
+/// The target seen by GDB: registers are R0–R15 read as symbolic integers (LSB
+/// first, thresholded at 0.5) plus the PC as a bundle index; memory is the bus
+/// map, one byte per cell (the low 8 bits), readable everywhere without side
+/// effects and writable in RAM. Breakpoint addresses are bundle indices,
+/// watchpoint addresses are bus addresses; both go into `sys.breakpoints`.
+pub fn serve(sys: &mut SystemEmulator, listener: &TcpListener) -> Result<()> {
+    let (stream, peer) = listener.accept().context("waiting for a debugger")?;
+    sys.logs.push(format!("[GDB] Debugger connected from {}", peer));
+    // Packets are tiny and strictly request/reply; don't let Nagle hold them back
+    stream.set_nodelay(true)?;
+    let mut session = Session { reader: BufReader::new(stream.try_clone()?), stream, no_ack: false };
+    while let Some(packet) = session.read_packet()? {
+        match packet.as_str() {
+            "k" => break,
+            "D" => {
+                session.send("OK")?;
+                break;
+            }
+            "QStartNoAckMode" => {
+                session.send("OK")?;
+                session.no_ack = true;
+            }
+            _ if packet.starts_with('c') => {
+                let reply = match resume_at(sys, &packet[1..]) {
+                    Ok(()) => resume(sys, &mut session)?,
+                    Err(e) => refuse(sys, &packet, e),
+                };
+                session.send(&reply)?;
+            }
+            _ if packet.starts_with('s') => {
+                let reply = match resume_at(sys, &packet[1..]) {
+                    Ok(()) if sys.step() => "S05".to_string(),
+                    Ok(()) => exit_reply(sys),
+                    Err(e) => refuse(sys, &packet, e),
+                };
+                session.send(&reply)?;
+            }
+            _ => {
+                let reply = respond(sys, &packet).unwrap_or_else(|e| refuse(sys, &packet, e));
+                session.send(&reply)?;
+            }
+        }
+    }
+    sys.logs.push("[GDB] Debugger detached".to_string());
+    Ok(())
+}
+
+/// Log why a packet was rejected and give GDB its error reply.
+fn refuse(sys: &mut SystemEmulator, packet: &str, e: anyhow::Error) -> String {
+    sys.logs.push(format!("[GDB] '{}': {:#}", packet, e));
+    "E01".to_string()
+}
+
+/// Optional resume address of `c`/`s`, a bundle index.
+fn resume_at(sys: &mut SystemEmulator, addr: &str) -> Result<()> {
+    if !addr.is_empty() {
+        sys.pc = usize::from_str_radix(addr, 16)?;
+    }
+    Ok(())
+}
+
+/// Reply to a packet that neither resumes the target nor ends the session.
+fn respond(sys: &mut SystemEmulator, packet: &str) -> Result<String> {
+    let Some(cmd) = packet.get(..1) else { return Ok(String::new()) };
+    let args = &packet[1..];
+    Ok(match cmd {
+        "?" => "S05".to_string(),
+        "g" => (0..GDB_REGISTERS).map(|n| hex_u32(read_register(sys, n))).collect(),
+        "G" => {
+            for n in 0..GDB_REGISTERS {
+                let value = args.get(n * 8..n * 8 + 8).ok_or_else(|| anyhow!("short register block"))?;
+                // Registers the machine lacks read as zero in `g`; skip them here
+                if n == PC_REGNUM || sys.bus.registers.contains_key(&(n as u16)) {
+                    write_register(sys, n, parse_hex_u32(value)?)?;
+                }
+            }
+            "OK".to_string()
+        }
+        "p" => {
+            let n = usize::from_str_radix(args, 16)?;
+            if n >= GDB_REGISTERS {
+                bail!("no register {}", n);
+            }
+            hex_u32(read_register(sys, n))
+        }
+        "P" => {
+            let (n, value) = args.split_once('=').ok_or_else(|| anyhow!("missing '='"))?;
+            let n = usize::from_str_radix(n, 16)?;
+            if n >= GDB_REGISTERS {
+                bail!("no register {}", n);
+            }
+            write_register(sys, n, parse_hex_u32(value)?)?;
+            "OK".to_string()
+        }
+        "m" => {
+            let (addr, len) = parse_range(args)?;
+            (addr..addr + len).map(|a| format!("{:02x}", decode_bits(sys.bus.peek(a as u16).as_slice().unwrap_or(&[])) as u8)).collect()
+        }
+        "M" => {
+            let (range, data) = args.split_once(':').ok_or_else(|| anyhow!("missing ':'"))?;
+            let (addr, len) = parse_range(range)?;
+            let bytes = parse_hex_bytes(data)?;
+            if bytes.len() != len as usize {
+                bail!("{} bytes announced, {} sent", len, bytes.len());
+            }
+            if addr < 0x2000 || addr + len > 0x8000 {
+                bail!("only RAM (0x2000-0x7FFF) is writable");
+            }
+            for (a, byte) in (addr..).zip(bytes) {
+                let width = sys.bus.ram.get(&(a as u16)).map_or(8, |cell| cell.len());
+                sys.bus.ram.insert(a as u16, Array1::from(encode_bits(byte as u32, width)));
+            }
+            "OK".to_string()
+        }
+        "Z" | "z" => {
+            let breakpoint = parse_breakpoint(args)?;
+            let existing = sys.breakpoints.iter().position(|b| *b == breakpoint);
+            match (cmd, existing) {
+                ("Z", None) => sys.breakpoints.push(breakpoint),
+                ("z", Some(i)) => {
+                    sys.breakpoints.remove(i);
+                }
+                _ => {}
+            }
+            "OK".to_string()
+        }
+        "H" | "T" => "OK".to_string(),
+        "q" => match args {
+            _ if args.starts_with("Supported") => "PacketSize=4000;qXfer:features:read+;QStartNoAckMode+".to_string(),
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ if args.starts_with("Xfer:features:read:target.xml:") => {
+                let (offset, len) = args["Xfer:features:read:target.xml:".len()..]
+                    .split_once(',')
+                    .ok_or_else(|| anyhow!("missing ','"))?;
+                let (offset, len) = (usize::from_str_radix(offset, 16)?, usize::from_str_radix(len, 16)?);
+                let xml = target_xml();
+                let chunk = xml.get(offset..).unwrap_or("");
+                if chunk.len() > len { format!("m{}", &chunk[..len]) } else { format!("l{}", chunk) }
+            }
+            _ => String::new(),
+        },
+        // Unsupported: an empty reply tells GDB to fall back
+        _ => String::new(),
+    })
+}
+
+/// Run until a breakpoint, the end of the program or a Ctrl-C from the client.
+fn resume(sys: &mut SystemEmulator, session: &mut Session) -> Result<String> {
+    loop {
+        let (report, hit) = sys.run_until(CONTINUE_SLICE);
+        if let Some(hit) = hit {
+            return Ok(match sys.breakpoints.get(hit.index) {
+                Some(Breakpoint::Watch { addr, read, write }) => {
+                    let kind = match (read, write) {
+                        (true, true) => "awatch",
+                        (true, false) => "rwatch",
+                        _ => "watch",
+                    };
+                    format!("T05{}:{:x};", kind, addr)
+                }
+                _ => "S05".to_string(),
+            });
+        }
+        if report.halted {
+            return Ok(exit_reply(sys));
+        }
+        if session.interrupted()? {
+            return Ok("S02".to_string());
+        }
+    }
+}
+
+/// A fault stops the target like a segfault, so it can still be inspected; running off the end exits it.
+fn exit_reply(sys: &SystemEmulator) -> String {
+    if sys.fault.is_some() { "S0b".to_string() } else { "W00".to_string() }
+}
+
+fn read_register(sys: &SystemEmulator, n: usize) -> u32 {
+    match n {
+        PC_REGNUM => sys.pc as u32,
+        _ => sys.bus.registers.get(&(n as u16)).map_or(0, |r| r.to_symbolic()),
+    }
+}
+
+/// Only registers the machine has can be written; the debugger does not add any.
+fn write_register(sys: &mut SystemEmulator, n: usize, value: u32) -> Result<()> {
+    match n {
+        PC_REGNUM => sys.pc = value as usize,
+        _ => {
+            let reg = sys.bus.registers.get_mut(&(n as u16)).ok_or_else(|| anyhow!("no register R{} on this machine", n))?;
+            *reg = NeuralRegister::from_symbolic(reg.width, value);
+        }
+    }
+    Ok(())
+}
+
+/// `type,addr,kind`: 0/1 are breakpoints on a bundle index, 2/3/4 write/read/access watchpoints.
+fn parse_breakpoint(args: &str) -> Result<Breakpoint> {
+    let mut fields = args.split(',');
+    let (kind, addr) = (fields.next().unwrap_or(""), fields.next().ok_or_else(|| anyhow!("missing address"))?);
+    let addr = usize::from_str_radix(addr, 16)?;
+    let watch = |read, write| Ok(Breakpoint::Watch { addr: u16::try_from(addr)?, read, write });
+    match kind {
+        "0" | "1" => Ok(Breakpoint::Pc(addr)),
+        "2" => watch(false, true),
+        "3" => watch(true, false),
+        "4" => watch(true, true),
+        _ => bail!("unknown breakpoint type '{}'", kind),
+    }
+}
+
+fn parse_range(args: &str) -> Result<(u32, u32)> {
+    let (addr, len) = args.split_once(',').ok_or_else(|| anyhow!("missing ','"))?;
+    let (addr, len) = (u32::from_str_radix(addr, 16)?, u32::from_str_radix(len, 16)?);
+    if addr.checked_add(len).is_none_or(|end| end > 0x1_0000) {
+        bail!("0x{:X}+{} is outside the 16-bit bus", addr, len);
+    }
+    Ok((addr, len))
+}
+
+fn parse_hex_bytes(hex: &str) -> Result<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(hex.get(i..i + 2).ok_or_else(|| anyhow!("odd hex length"))?, 16)?))
+        .collect()
+}
+
+/// Registers travel as target-endian (little-endian) bytes.
+fn hex_u32(value: u32) -> String {
+    value.to_le_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn parse_hex_u32(hex: &str) -> Result<u32> {
+    let bytes = parse_hex_bytes(hex)?;
+    let mut le = [0u8; 4];
+    for (dst, src) in le.iter_mut().zip(&bytes) {
+        *dst = *src;
+    }
+    Ok(u32::from_le_bytes(le))
+}
+
+/// Packet framing: `$payload#checksum`, acknowledged with `+` until no-ack mode.
+struct Session {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+    no_ack: bool,
+}
+
+impl Session {
+    /// Next packet payload, or `None` once the client hangs up.
+    fn read_packet(&mut self) -> Result<Option<String>> {
+        loop {
+            let mut byte = [0u8];
+            if self.reader.read(&mut byte)? == 0 {
+                return Ok(None);
+            }
+            // Acks and stray interrupts (the target is already stopped) are skipped
+            if byte[0] != b'$' {
+                continue;
+            }
+            let mut payload = Vec::new();
+            if self.reader.read_until(b'#', &mut payload)? == 0 || payload.pop() != Some(b'#') {
+                return Ok(None);
+            }
+            let mut checksum = [0u8; 2];
+            self.reader.read_exact(&mut checksum)?;
+            let expected = u8::from_str_radix(std::str::from_utf8(&checksum)?, 16).ok();
+            if !self.no_ack {
+                let ok = expected == Some(checksum_of(&payload));
+                self.stream.write_all(if ok { b"+" } else { b"-" })?;
+                if !ok {
+                    continue;
+                }
+            }
+            return Ok(Some(String::from_utf8(payload)?));
+        }
+    }
+
+    fn send(&mut self, payload: &str) -> Result<()> {
+        loop {
+            write!(self.stream, "${}#{:02x}", payload, checksum_of(payload.as_bytes()))?;
+            if self.no_ack {
+                return Ok(());
+            }
+            // Resend on '-'; anything else before the ack (e.g. a Ctrl-C) is dropped
+            let mut byte = [0u8];
+            loop {
+                if self.reader.read(&mut byte)? == 0 {
+                    return Ok(());
+                }
+                match byte[0] {
+                    b'+' => return Ok(()),
+                    b'-' => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// True if the client sent Ctrl-C (0x03) since the last packet.
+    fn interrupted(&mut self) -> Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let found = loop {
+            let first = match self.reader.fill_buf() {
+                Ok(buf) => buf.first().copied(),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => None,
+                Err(e) => {
+                    self.stream.set_nonblocking(false)?;
+                    return Err(e.into());
+                }
+            };
+            match first {
+                Some(0x03) => {
+                    self.reader.consume(1);
+                    break true;
+                }
+                Some(b'+') => self.reader.consume(1),
+                _ => break false,
+            }
+        };
+        self.stream.set_nonblocking(false)?;
+        Ok(found)
+    }
+}
+
+fn checksum_of(payload: &[u8]) -> u8 {
+    payload.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
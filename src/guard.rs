@@ -0,0 +1,213 @@
+use anyhow::{anyhow, bail};
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bus::{FLAGS_ADDR, PC_ADDR};
+use crate::fu::kind::CMP_FLAGS;
+
+/// How a guard turns the value at its address into a single condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardTest {
+    /// Element `i` is high (> 0.5). `Bit(0)` is the classic guard.
+    Bit(usize),
+    /// At least one element is high.
+    Any,
+    /// Every element is high.
+    All,
+    /// More than half of the elements are high.
+    Majority,
+}
+
+/// Condition for a guarded move.
+///
+/// In JSON a plain guard stays a bare address (`"guard": 2`), so existing
+/// programs load unchanged. Everything else is written in assembler syntax:
+/// `"!R2.1"`, `"FLAGS.EQ"`, `"any(0x1005)"`, `"!maj(R3)"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "GuardRepr", into = "GuardRepr")]
+pub struct Guard {
+    pub addr: u16,
+    pub test: GuardTest,
+    pub negate: bool,
+}
+
+impl From<u16> for Guard {
+    fn from(addr: u16) -> Self {
+        Self { addr, test: GuardTest::Bit(0), negate: false }
+    }
+}
+
+impl Guard {
+    pub fn bit(addr: u16, bit: usize) -> Self {
+        Self { addr, test: GuardTest::Bit(bit), negate: false }
+    }
+
+    pub fn negated(self) -> Self {
+        Self { negate: !self.negate, ..self }
+    }
+
+    /// True if the move should execute given the value read from `addr`.
+    pub fn eval(&self, value: &Array1<f32>) -> bool {
+        let high = value.iter().filter(|&&v| v > 0.5).count();
+        let pass = match self.test {
+            GuardTest::Bit(i) => value.get(i).is_some_and(|&v| v > 0.5),
+            GuardTest::Any => high > 0,
+            GuardTest::All => !value.is_empty() && high == value.len(),
+            GuardTest::Majority => high * 2 > value.len(),
+        };
+        pass != self.negate
+    }
+
+    /// Assembler form with addresses rendered by `name`. FLAGS bits use their flag names.
+    pub fn describe(&self, name: impl Fn(u16) -> String) -> String {
+        let neg = if self.negate { "!" } else { "" };
+        let body = match self.test {
+            GuardTest::Bit(i) if self.addr == FLAGS_ADDR && i < CMP_FLAGS.len() => format!("FLAGS.{}", CMP_FLAGS[i]),
+            GuardTest::Bit(0) => name(self.addr),
+            GuardTest::Bit(i) => format!("{}.{}", name(self.addr), i),
+            GuardTest::Any => format!("any({})", name(self.addr)),
+            GuardTest::All => format!("all({})", name(self.addr)),
+            GuardTest::Majority => format!("maj({})", name(self.addr)),
+        };
+        format!("{}{}", neg, body)
+    }
+}
+
+/// `R<n>` for registers, `FLAGS`, `PC`, otherwise hex.
+pub(crate) fn default_name(addr: u16) -> String {
+    if addr == FLAGS_ADDR {
+        "FLAGS".to_string()
+    } else if addr == PC_ADDR {
+        "PC".to_string()
+    } else if addr < 0x1000 {
+        format!("R{}", addr)
+    } else {
+        format!("0x{:X}", addr)
+    }
+}
+
+pub(crate) fn parse_addr(s: &str) -> anyhow::Result<u16> {
+    let s = s.trim();
+    let parsed = if s == "FLAGS" {
+        Ok(FLAGS_ADDR)
+    } else if s == "PC" {
+        Ok(PC_ADDR)
+    } else if let Some(reg) = s.strip_prefix('R') {
+        reg.parse()
+    } else if let Some(hex) = s.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+    parsed.map_err(|_| anyhow!("bad guard address '{}'", s))
+}
+
+impl fmt::Display for Guard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.describe(default_name))
+    }
+}
+
+impl FromStr for Guard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let (negate, body) = match s.strip_prefix('!') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, s),
+        };
+
+        for (func, test) in [("any", GuardTest::Any), ("all", GuardTest::All), ("maj", GuardTest::Majority)] {
+            if let Some(arg) = body.strip_prefix(func).and_then(|r| r.trim_start().strip_prefix('(')) {
+                let Some(arg) = arg.strip_suffix(')') else { bail!("unclosed '(' in guard '{}'", s) };
+                return Ok(Self { addr: parse_addr(arg)?, test, negate });
+            }
+        }
+
+        let (addr, bit) = match body.split_once('.') {
+            Some((addr, bit)) => (addr, Some(bit.trim())),
+            None => (body, None),
+        };
+        let addr = parse_addr(addr)?;
+        let bit = match bit {
+            None => 0,
+            Some(b) if addr == FLAGS_ADDR && CMP_FLAGS.contains(&b) => CMP_FLAGS.iter().position(|f| *f == b).unwrap(),
+            Some(b) => b.parse().map_err(|_| anyhow!("bad bit index '{}' in guard '{}'", b, s))?,
+        };
+        Ok(Self { addr, test: GuardTest::Bit(bit), negate })
+    }
+}
+
+/// Serialized form: bare address for plain guards, assembler text otherwise.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum GuardRepr {
+    Addr(u16),
+    Expr(String),
+}
+
+impl TryFrom<GuardRepr> for Guard {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: GuardRepr) -> anyhow::Result<Self> {
+        match repr {
+            GuardRepr::Addr(addr) => Ok(addr.into()),
+            GuardRepr::Expr(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Guard> for GuardRepr {
+    fn from(guard: Guard) -> Self {
+        if guard == Guard::from(guard.addr) {
+            GuardRepr::Addr(guard.addr)
+        } else {
+            GuardRepr::Expr(guard.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::MoveOp;
+
+    #[test]
+    fn test_parse_and_display_roundtrip() {
+        for text in ["R2", "!R2.1", "FLAGS.EQ", "!FLAGS.LT", "any(0x1005)", "!maj(R3)", "all(FLAGS)"] {
+            let guard: Guard = text.parse().unwrap();
+            assert_eq!(guard.to_string(), text);
+        }
+        assert_eq!("!R2.1".parse::<Guard>().unwrap(), Guard::bit(2, 1).negated());
+        assert_eq!("FLAGS.EQ".parse::<Guard>().unwrap(), Guard::bit(FLAGS_ADDR, 5));
+        assert!("any(R2".parse::<Guard>().is_err());
+        assert!("R2.x".parse::<Guard>().is_err());
+    }
+
+    #[test]
+    fn test_serde_compatible_with_plain_address() {
+        let op: MoveOp = serde_json::from_str(r#"{ "src": 0, "dest": 1, "guard": 2 }"#).unwrap();
+        assert_eq!(op.guard, Some(Guard::from(2)));
+        assert!(serde_json::to_string(&op).unwrap().contains(r#""guard":2"#));
+
+        let op: MoveOp = serde_json::from_str(r#"{ "src": 0, "dest": 1, "guard": "!R2.1" }"#).unwrap();
+        assert_eq!(op.guard, Some(Guard::bit(2, 1).negated()));
+        assert!(serde_json::to_string(&op).unwrap().contains(r#""guard":"!R2.1""#));
+    }
+
+    #[test]
+    fn test_predicates() {
+        let v = Array1::from(vec![0.0, 1.0, 1.0, 0.0]);
+        assert!(!Guard::from(0).eval(&v));
+        assert!(Guard::bit(0, 1).eval(&v));
+        assert!(Guard::bit(0, 0).negated().eval(&v));
+        assert!(!Guard::bit(0, 9).eval(&v)); // Out of range reads low
+        let test = |test| Guard { addr: 0, test, negate: false }.eval(&v);
+        assert!(test(GuardTest::Any));
+        assert!(!test(GuardTest::All));
+        assert!(!test(GuardTest::Majority)); // 2 of 4 is not a majority
+    }
+}
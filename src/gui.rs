@@ -1,6 +1,13 @@
 use eframe::egui;
-use crate::system::SystemEmulator;
-use crate::loader::load_manifest;
+use neuro_symbolic_emulator::breakpoint::Breakpoint;
+use neuro_symbolic_emulator::bus::flag_name;
+use neuro_symbolic_emulator::fu::{BaseFU, Frame, Introspect, KeyEvent, PortSpec};
+use neuro_symbolic_emulator::system::SystemEmulator;
+use neuro_symbolic_emulator::journal::Journal;
+use neuro_symbolic_emulator::loader::{boot, load_manifest};
+use neuro_symbolic_emulator::snapshot::{Snapshot, WeightMode};
+use ndarray::{ArrayView2, Axis, Ix1, Ix2};
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
@@ -9,12 +16,24 @@ pub struct NtseApp {
     is_running: bool,
     steps_per_frame: usize,
     manifest_path: String,
+    disk_path: String,
+    state_path: String,
+    /// Keep an undo journal so the machine can run backwards. Off by default: it costs time every step.
+    record: bool,
+    /// Register watched by "Back until changed".
+    reverse_reg: u16,
+    /// Breakpoint being typed, e.g. `R3 == 42`.
+    break_text: String,
     
     // Visualization State
     selected_fu_addr: Option<u16>,
+    /// Weights as loaded, to show what perturbation changed.
+    golden: HashMap<u16, BaseFU>,
+    perturb_amount: f32,
     
     // Console
     console_output: Arc<Mutex<String>>,
+    console_line: String,
 }
 
 impl NtseApp {
@@ -22,44 +41,60 @@ impl NtseApp {
         // Init Sink
         let sink = Arc::new(Mutex::new(String::new()));
         
-        // Initial Load
-        let mut system = SystemEmulator::default();
-        if let Ok(sys) = load_manifest(Path::new("manifest.json"), Some(sink.clone())) {
-             system = sys;
-        } else {
-            // Default system needs the sink too
-            system.console_sink = sink.clone();
-            // And any default UART needs it? SystemEmulator::default() creates UART without sink.
-            // We should ideally update default UART or recreate it.
-            // Simplified: Just set the emulator's sink. 
-            // BUT: The UartFU created in default() holds None.
-            // We need to re-inject.
-            // Or better: SystemEmulator::new() should take bus, and we manually add UART with sink?
-            // For iteration 7, let's just accept that default() might have disconnected UART 
-            // until we load a manifest properly.
-        }
+        // Initial Load. The default system's UART is already wired to its own console.
+        let (system, sink) = match load_manifest(Path::new("manifest.json"), Some(sink.clone())) {
+            Ok(sys) => (sys, sink),
+            Err(_) => {
+                let sys = SystemEmulator::default();
+                let sink = sys.console_sink.clone();
+                (sys, sink)
+            }
+        };
 
+        let golden = golden_weights(&system);
         Self {
             system: Arc::new(Mutex::new(system)),
             is_running: false,
             steps_per_frame: 1,
             manifest_path: "manifest.json".to_string(),
+            disk_path: "disk.img".to_string(),
+            state_path: "state.json".to_string(),
+            record: false,
+            reverse_reg: 0,
+            break_text: String::new(),
             selected_fu_addr: None,
+            golden,
+            perturb_amount: 0.3,
             console_output: sink,
+            console_line: String::new(),
         }
     }
 }
 
 impl eframe::App for NtseApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // 0. Keypad: host key events, unless a text field has focus
+        if !ctx.wants_keyboard_input() {
+            let sys = self.system.lock().unwrap();
+            if let Some(keys) = &sys.keys {
+                ctx.input(|i| {
+                    for event in &i.events {
+                        if let egui::Event::Key { key, pressed, repeat: false, .. } = event {
+                            if let Some(c) = key.name().chars().next().filter(|_| key.name().len() == 1) {
+                                keys.push(KeyEvent::key(c, *pressed));
+                            }
+                        }
+                    }
+                });
+            }
+        }
+
         // 1. Logic Step
         if self.is_running {
              let mut sys = self.system.lock().unwrap();
-             for _ in 0..self.steps_per_frame {
-                 if !sys.step() {
-                     self.is_running = false;
-                     break;
-                 }
+             let (report, hit) = sys.run_until(self.steps_per_frame);
+             if report.halted || hit.is_some() {
+                 self.is_running = false;
              }
              ctx.request_repaint(); // Continuous repaint when running
         }
@@ -70,10 +105,24 @@ impl eframe::App for NtseApp {
                 ui.label("Manifest:");
                 ui.text_edit_singleline(&mut self.manifest_path);
                 if ui.button("Load").clicked() {
-                    if let Ok(sys) = load_manifest(Path::new(&self.manifest_path), Some(self.console_output.clone())) {
+                    if let Ok(mut sys) = load_manifest(Path::new(&self.manifest_path), Some(self.console_output.clone())) {
+                        sys.journal = self.record.then(Journal::default);
+                        self.golden = golden_weights(&sys);
                         *self.system.lock().unwrap() = sys;
                     }
                 }
+                ui.label("Disk:");
+                ui.text_edit_singleline(&mut self.disk_path);
+                if ui.button("Boot").clicked() {
+                    match boot(Path::new(&self.manifest_path), Path::new(&self.disk_path), Some(self.console_output.clone())) {
+                        Ok(mut sys) => {
+                            sys.journal = self.record.then(Journal::default);
+                            self.golden = golden_weights(&sys);
+                            *self.system.lock().unwrap() = sys;
+                        }
+                        Err(e) => self.system.lock().unwrap().logs.push(format!("[Boot] FAILED: {:#}", e)),
+                    }
+                }
 
                 ui.separator();
 
@@ -89,12 +138,55 @@ impl eframe::App for NtseApp {
                 if ui.button("Step").clicked() {
                      self.system.lock().unwrap().step();
                 }
+                if ui.checkbox(&mut self.record, "Record").on_hover_text("Keep history for Back").changed() {
+                    self.system.lock().unwrap().journal = self.record.then(Journal::default);
+                }
+                if ui.add_enabled(self.record, egui::Button::new("Back")).clicked() {
+                    self.is_running = false;
+                    self.system.lock().unwrap().step_back(1);
+                }
+                ui.add(egui::DragValue::new(&mut self.reverse_reg).clamp_range(0..=15).prefix("R"));
+                if ui.add_enabled(self.record, egui::Button::new("Back until changed")).clicked() {
+                    self.is_running = false;
+                    let mut sys = self.system.lock().unwrap();
+                    let line = match sys.reverse_until_changed(self.reverse_reg, 100_000) {
+                        Some(pc) => format!("[Reverse] R{} last written by bundle {} (step {})", self.reverse_reg, pc, sys.total_steps),
+                        None => format!("[Reverse] R{} unchanged as far back as the journal goes", self.reverse_reg),
+                    };
+                    sys.logs.push(line);
+                }
                 
                  if ui.button("Reset").clicked() {
                      let mut sys = self.system.lock().unwrap();
                      sys.pc = 0;
                      sys.total_steps = 0;
                      sys.logs.clear();
+                     sys.stats = Default::default();
+                     sys.fault = None;
+                     sys.bus.bus_fault = None;
+                     sys.bus.assoc_stats = Default::default();
+                     if let Some(journal) = &mut sys.journal {
+                         journal.clear();
+                     }
+                }
+
+                ui.separator();
+                ui.label("State:");
+                ui.text_edit_singleline(&mut self.state_path);
+                if ui.button("Save State").clicked() {
+                    let mut sys = self.system.lock().unwrap();
+                    let result = sys.snapshot(WeightMode::Inline).save(Path::new(&self.state_path));
+                    let line = match result {
+                        Ok(()) => format!("[Step {} | PC {}] Saved snapshot to {}", sys.total_steps, sys.pc, self.state_path),
+                        Err(e) => format!("[Snapshot] FAILED: {:#}", e),
+                    };
+                    sys.logs.push(line);
+                }
+                if ui.button("Load State").clicked() {
+                    let mut sys = self.system.lock().unwrap();
+                    if let Err(e) = Snapshot::load(Path::new(&self.state_path)).and_then(|snap| sys.restore(&snap)) {
+                        sys.logs.push(format!("[Snapshot] FAILED: {:#}", e));
+                    }
                 }
                 
                 ui.separator();
@@ -102,8 +194,22 @@ impl eframe::App for NtseApp {
                 ui.add(egui::Slider::new(&mut self.steps_per_frame, 1..=100).text("steps/frame"));
                 
                 ui.separator();
-                let step_count = self.system.lock().unwrap().total_steps;
-                ui.label(format!("Steps: {}", step_count));
+                let sys = self.system.lock().unwrap();
+                ui.label(format!("Steps: {}", sys.total_steps));
+                if sys.stats.stalls > 0 {
+                    ui.label(format!("Stalls: {}", sys.stats.stalls));
+                }
+                if sys.buses > 1 {
+                    ui.label(format!("Buses: {} ({:.0}% used)", sys.buses, sys.stats.utilization() * 100.0));
+                }
+                let assoc = sys.bus.assoc_stats;
+                if assoc.lookups > 0 {
+                    ui.label(format!("Assoc: {} recovered / {} lookups, {} faults, conf {:.2}",
+                        assoc.recovered, assoc.lookups, assoc.faults, assoc.mean_confidence()));
+                }
+                if let Some(fault) = &sys.fault {
+                    ui.colored_label(egui::Color32::RED, format!("FAULT: {}", fault));
+                }
             });
         });
         
@@ -143,6 +249,16 @@ impl eframe::App for NtseApp {
                         });
                     });
                     
+                    // UART input: a line is queued with its newline when sent
+                    ui.horizontal(|ui| {
+                        let edit = ui.text_edit_singleline(&mut self.console_line);
+                        let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if ui.button("Send").clicked() || enter {
+                            let line = std::mem::take(&mut self.console_line);
+                            self.system.lock().unwrap().console_input.push_str(&format!("{}\n", line));
+                        }
+                    });
+
                     egui::ScrollArea::vertical().id_source("console_out").stick_to_bottom(true).show(ui, |ui| {
                         if let Ok(output) = self.console_output.lock() {
                             ui.label(egui::RichText::new(output.as_str()).monospace().color(egui::Color32::GREEN));
@@ -162,7 +278,7 @@ impl eframe::App for NtseApp {
                     keys.sort();
                     
                     for k in keys {
-                        ui.label(format!("R{}", k));
+                        ui.label(flag_name(*k).unwrap_or_else(|| format!("R{}", k)));
                         if let Some(reg) = sys.bus.registers.get(k) {
                             let val = &reg.state;
                             let v0 = val.get(0).unwrap_or(&0.0);
@@ -180,8 +296,113 @@ impl eframe::App for NtseApp {
         egui::SidePanel::right("inspector").resizable(true).show(ctx, |ui| {
              ui.heading("Inspector");
              egui::ScrollArea::vertical().id_source("inspector_scroll").show(ui, |ui| {
-                 let sys = self.system.lock().unwrap();
-                 
+                 let mut sys = self.system.lock().unwrap();
+
+                 // Selected unit: internals, weight heatmaps, hidden activations and drift from the loaded weights
+                 if let Some(addr) = self.selected_fu_addr {
+                     let name = sys.bus.unit_name(addr).unwrap_or_default();
+                     egui::CollapsingHeader::new(format!("{} @ 0x{:X}", name, addr)).default_open(true).show(ui, |ui| {
+                         let bus = &mut sys.bus;
+                         let Some(unit) = bus.units.get_mut(&addr).or_else(|| bus.mmio.get_mut(&addr)) else {
+                             ui.label("(no longer on the bus)");
+                             return;
+                         };
+                         // Wrapped networks (multiplier, shifter, ...) are shown through their weights
+                         let view = unit.introspect().or_else(|| unit.weights().map(|w| w as &dyn Introspect));
+                         if let Some(view) = view {
+                             ui.label(format!("Kind: {}", view.kind()));
+                             egui::Grid::new("unit_state").striped(true).show(ui, |ui| {
+                                 for (key, value) in view.state() {
+                                     ui.label(key);
+                                     ui.monospace(value);
+                                     ui.end_row();
+                                 }
+                             });
+                             if let Some(layout) = view.port_layout().or_else(|| bus.layouts.get(&addr).cloned()) {
+                                 let ports = |specs: &[PortSpec]| specs.iter()
+                                     .map(|p| format!("{}:{}{}", p.name, p.width, if p.trigger { "*" } else { "" }))
+                                     .collect::<Vec<_>>().join(" ");
+                                 ui.monospace(format!(" In: {}", ports(&layout.inputs)));
+                                 ui.monospace(format!("Out: {}", ports(&layout.outputs)));
+                             }
+                             for (tensor, values) in view.tensors() {
+                                 ui.label(format!("{} {:?}", tensor, values.shape()));
+                                 match values.ndim() {
+                                     1 => heatmap(ui, values.into_dimensionality::<Ix1>().unwrap().insert_axis(Axis(0)), 6.0),
+                                     2 => heatmap(ui, values.into_dimensionality::<Ix2>().unwrap(), 6.0),
+                                     _ => {}
+                                 }
+                             }
+                             if !view.tensors().is_empty() {
+                                 ui.label("Hidden activations (last forward)");
+                                 match view.hidden_activations() {
+                                     Some(hidden) => heatmap(ui, hidden.insert_axis(Axis(0)), 10.0),
+                                     None => {
+                                         ui.weak("not fired yet");
+                                     }
+                                 }
+                             }
+                         }
+                         if let Some((input, output)) = bus.fu_io_cache.get(&addr) {
+                             ui.monospace(format!("Last in:  {}", bits(input.iter())));
+                             ui.monospace(format!("Last out: {}", bits(output.iter())));
+                         }
+                         let Some(net) = unit.weights() else { return };
+
+                         if let Some(golden) = self.golden.get(&addr).filter(|g| g.w1.dim() == net.w1.dim() && g.w2.dim() == net.w2.dim()) {
+                             let d1 = &net.w1 - &golden.w1;
+                             let d2 = &net.w2 - &golden.w2;
+                             let changed = d1.iter().chain(&d2).filter(|d| **d != 0.0).count();
+                             let max = d1.iter().chain(&d2).fold(0.0f32, |m, d| m.max(d.abs()));
+                             ui.separator();
+                             ui.label(format!("Diff against loaded weights: {} changed, max |\u{0394}| {:.3}", changed, max));
+                             if changed > 0 {
+                                 ui.label("\u{0394}w1");
+                                 heatmap(ui, d1.view(), 6.0);
+                                 ui.label("\u{0394}w2");
+                                 heatmap(ui, d2.view(), 6.0);
+                             }
+                         }
+                         ui.horizontal(|ui| {
+                             ui.add(egui::DragValue::new(&mut self.perturb_amount).speed(0.01).clamp_range(0.0..=5.0));
+                             if ui.button("Perturb").clicked() {
+                                 unit.perturb(self.perturb_amount);
+                             }
+                             if let Some(golden) = self.golden.get(&addr) {
+                                 if ui.button("Restore loaded weights").clicked() {
+                                     if let Some(net) = unit.weights_mut() {
+                                         *net = golden.clone();
+                                     }
+                                 }
+                             }
+                         });
+                     });
+                     ui.separator();
+                 }
+
+                 // Video unit framebuffer, 4x scale
+                 if let Some(display) = sys.display.clone() {
+                     ui.collapsing("Display", |ui| {
+                         let frame = display.lock().unwrap().clone();
+                         let scale = 4.0;
+                         let size = egui::vec2(Frame::WIDTH as f32 * scale, Frame::HEIGHT as f32 * scale);
+                         let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                         let painter = ui.painter_at(rect);
+                         painter.rect_filled(rect, 0.0, egui::Color32::BLACK);
+                         for (i, _) in frame.pixels.iter().enumerate().filter(|(_, &p)| p) {
+                             let (x, y) = ((i % Frame::WIDTH) as f32, (i / Frame::WIDTH) as f32);
+                             let min = rect.min + egui::vec2(x * scale, y * scale);
+                             painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(scale, scale)), 0.0, egui::Color32::GREEN);
+                         }
+                         if ui.small_button("Save frame.png").clicked() {
+                             if let Err(e) = frame.save(Path::new("frame.png")) {
+                                 sys.logs.push(format!("[Display] FAILED: {:#}", e));
+                             }
+                         }
+                     });
+                     ui.separator();
+                 }
+
                  // RAM Inspection (Show non-zero)
                  ui.collapsing("RAM (Non-Zero)", |ui| {
                      let mut mem_keys: Vec<&u16> = sys.bus.ram.keys().collect();
@@ -219,16 +440,20 @@ impl eframe::App for NtseApp {
         // 6. Central Panel: FUs & Program
         egui::CentralPanel::default().show(ctx, |ui| {
              ui.heading("Functional Units");
-             let sys = self.system.lock().unwrap();
+             let mut sys = self.system.lock().unwrap();
              
              // Simple Grid of Units
              egui::ScrollArea::vertical().id_source("fus_scroll").max_height(150.0).show(ui, |ui| {
                  ui.horizontal_wrapped(|ui| {
                      let mut u_keys: Vec<&u16> = sys.bus.units.keys().collect();
                      u_keys.sort();
+                     // Click a tile to inspect the unit
                      for k in u_keys {
                          ui.group(|ui| {
-                             ui.label(format!("FU @ 0x{:X}", k));
+                             let name = sys.bus.unit_name(*k).unwrap_or_default();
+                             if ui.selectable_label(self.selected_fu_addr == Some(*k), format!("{} @ 0x{:X}", name, k)).clicked() {
+                                 self.selected_fu_addr = Some(*k);
+                             }
                          });
                      }
                       // MMIO too
@@ -236,7 +461,10 @@ impl eframe::App for NtseApp {
                      m_keys.sort();
                       for k in m_keys {
                          ui.group(|ui| {
-                             ui.label(format!("MMIO @ 0x{:X}", k));
+                             let name = sys.bus.unit_name(*k).unwrap_or_default();
+                             if ui.selectable_label(self.selected_fu_addr == Some(*k), format!("{} @ 0x{:X}", name, k)).clicked() {
+                                 self.selected_fu_addr = Some(*k);
+                             }
                          });
                       }
                  });
@@ -244,33 +472,100 @@ impl eframe::App for NtseApp {
              
              ui.separator();
              ui.heading("Program");
+             ui.horizontal_wrapped(|ui| {
+                 ui.label("Break on:");
+                 let field = ui.text_edit_singleline(&mut self.break_text);
+                 if ui.button("Add").clicked() || (field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                     match self.break_text.parse::<Breakpoint>() {
+                         Ok(b) => {
+                             sys.breakpoints.push(b);
+                             self.break_text.clear();
+                         }
+                         Err(e) => sys.logs.push(format!("[Break] {:#}", e)),
+                     }
+                 }
+                 let mut remove = None;
+                 for (i, b) in sys.breakpoints.iter().enumerate() {
+                     if ui.button(format!("{} \u{2715}", b)).on_hover_text("Remove").clicked() {
+                         remove = Some(i);
+                     }
+                 }
+                 if let Some(i) = remove {
+                     sys.breakpoints.remove(i);
+                 }
+             });
+             let mut toggle = None;
              egui::ScrollArea::vertical().id_source("prog_scroll").show(ui, |ui| {
-                 for (i, op) in sys.program.iter().enumerate() {
+                 for (i, bundle) in sys.program.iter().enumerate() {
                       // Name Resolution Helper
                       let resolve = |addr: u16| -> String {
                           if addr < 16 { return format!("R{}", addr); }
                           if addr == 0x8000 { return "UART".to_string(); }
-                          if addr >= 0x2000 && addr < 0x8000 { return format!("RAM[0x{:X}]", addr); }
+                          if (0x2000..0x8000).contains(&addr) { return format!("RAM[0x{:X}]", addr); }
+                          if let Some(port) = sys.bus.port_name(addr) { return port; }
+                          if let Some(flag) = flag_name(addr) { return flag; }
                           if sys.bus.units.contains_key(&addr) { return format!("FU[0x{:X}]", addr); }
                           format!("0x{:X}", addr)
                       };
                      
-                      let src_name = resolve(op.src);
-                      let dest_name = resolve(op.dest);
-                      let guard_info = if let Some(g) = op.guard {
-                          format!(" [IF {}]", resolve(g))
-                      } else {
-                          "".to_string()
-                      };
+                      // Parallel moves of a bundle are separated by `||`
+                      let moves: Vec<String> = bundle.moves.iter().map(|op| {
+                          let guard_info = if let Some(g) = op.guard {
+                              format!(" [IF {}]", g.describe(resolve))
+                          } else {
+                              "".to_string()
+                          };
+                          format!("{} -> {}{}", resolve(op.src), resolve(op.dest), guard_info)
+                      }).collect();
                      
-                     let text = format!("{:04}: {} -> {}{}", i, src_name, dest_name, guard_info);
+                     // Click a line to set or clear a breakpoint on it
+                     let marked = sys.breakpoints.contains(&Breakpoint::Pc(i));
+                     let mut text = egui::RichText::new(format!("{} {:04}: {}", if marked { "\u{25CF}" } else { " " }, i, moves.join(" || ")));
+                     if marked {
+                         text = text.color(egui::Color32::RED);
+                     }
                      if i == sys.pc {
-                         ui.label(egui::RichText::new(text).strong().background_color(egui::Color32::DARK_BLUE));
-                     } else {
-                         ui.label(text);
+                         text = text.strong().background_color(egui::Color32::DARK_BLUE);
+                     }
+                     if ui.add(egui::Label::new(text).sense(egui::Sense::click())).clicked() {
+                         toggle = Some(i);
                      }
                  }
              });
+             if let Some(pc) = toggle {
+                 sys.toggle_breakpoint(pc);
+             }
         });
     }
 }
+
+/// Weights of every neural unit as loaded.
+fn golden_weights(sys: &SystemEmulator) -> HashMap<u16, BaseFU> {
+    sys.bus.units.iter().chain(&sys.bus.mmio)
+        .filter_map(|(&base, unit)| Some((base, unit.weights()?.clone())))
+        .collect()
+}
+
+fn bits<'a>(values: impl Iterator<Item = &'a f32>) -> String {
+    values.map(|v| if *v > 0.5 { '1' } else { '0' }).collect()
+}
+
+/// One square per value, red for positive and blue for negative, scaled to the largest magnitude.
+fn heatmap(ui: &mut egui::Ui, values: ArrayView2<f32>, cell: f32) {
+    let (rows, cols) = values.dim();
+    let max = values.iter().fold(0.0f32, |m, v| m.max(v.abs())).max(f32::EPSILON);
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(cols as f32 * cell, rows as f32 * cell), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    for ((r, c), &v) in values.indexed_iter() {
+        let level = (v.abs() / max * 255.0) as u8;
+        let color = if v >= 0.0 { egui::Color32::from_rgb(level, 0, 0) } else { egui::Color32::from_rgb(0, 0, level) };
+        let min = rect.min + egui::vec2(c as f32 * cell, r as f32 * cell);
+        painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(cell, cell)), 0.0, color);
+    }
+    if let Some(pos) = response.hover_pos() {
+        let (r, c) = (((pos.y - rect.min.y) / cell) as usize, ((pos.x - rect.min.x) / cell) as usize);
+        if let Some(v) = values.get((r, c)) {
+            response.on_hover_text(format!("[{}, {}] = {:.4}", r, c, v));
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use ndarray::Array1;
+use std::collections::{HashMap, VecDeque};
+
+use crate::fu::lsu::AssocStats;
+use crate::pipeline::Pipeline;
+use crate::register::NeuralRegister;
+use crate::snapshot::{Snapshot, WeightMode};
+use crate::system::{BusStats, SystemEmulator};
+
+/// What one `step` changed, enough to put the machine back exactly as it was.
+#[derive(Debug, Clone)]
+struct UndoEntry {
+    pc: usize,
+    total_steps: usize,
+    fault: Option<String>,
+    stats: BusStats,
+    logs: usize,
+    console: usize,
+    registers: HashMap<u16, NeuralRegister>,
+    port_latches: HashMap<u16, Array1<f32>>,
+    fu_io_cache: HashMap<u16, (Array1<f32>, Array1<f32>)>,
+    pipelines: HashMap<u16, Pipeline>,
+    stale_reads: usize,
+    early_issues: usize,
+    assoc_stats: AssocStats,
+    bus_fault: Option<String>,
+    /// Previous contents of every RAM cell written, in write order.
+    ram: Vec<(u16, Option<Array1<f32>>)>,
+    /// Previous `save_state` of the units whose state changed.
+    units: Vec<(u16, serde_json::Value)>,
+}
+
+/// Undo history for reverse execution.
+///
+/// Every step records what it overwrote: registers, port latches, the I/O
+/// cache and pipelines are small and copied whole, RAM writes are logged by
+/// the bus as they happen, and unit state is kept only when it changed
+/// (units with a `state_version` are not even saved while it stays put). The
+/// last `capacity` steps can be undone exactly; beyond that, a full snapshot
+/// taken every `checkpoint_every` steps is restored and the machine re-run
+/// up to the requested step. Host input consumed on the way (UART bytes, key
+/// events) is not replayed, and weights perturbed between steps are not undone.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    pub capacity: usize,
+    pub checkpoint_every: usize,
+    pub max_checkpoints: usize,
+    entries: VecDeque<UndoEntry>,
+    /// (snapshot, log length when it was taken), oldest first.
+    checkpoints: VecDeque<(Snapshot, usize)>,
+    /// Unit states after the last recorded step.
+    unit_states: HashMap<u16, serde_json::Value>,
+    /// `state_version` of the units whose state is in `unit_states`.
+    versions: HashMap<u16, u64>,
+    pending: Option<UndoEntry>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new(10_000, 1_000)
+    }
+}
+
+impl Journal {
+    pub fn new(capacity: usize, checkpoint_every: usize) -> Self {
+        Self {
+            capacity,
+            checkpoint_every: checkpoint_every.max(1),
+            max_checkpoints: 16,
+            entries: VecDeque::new(),
+            checkpoints: VecDeque::new(),
+            unit_states: HashMap::new(),
+            versions: HashMap::new(),
+            pending: None,
+        }
+    }
+
+    /// Steps that can be undone without going through a checkpoint.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Earliest step `step_back` can reach.
+    pub fn horizon(&self, sys: &SystemEmulator) -> usize {
+        let exact = sys.total_steps - self.entries.len();
+        self.checkpoints.front().map_or(exact, |(snap, _)| snap.total_steps.min(exact))
+    }
+
+    /// Forget all history, e.g. after the machine was reset or loaded from elsewhere.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.checkpoints.clear();
+        self.unit_states.clear();
+        self.versions.clear();
+        self.pending = None;
+    }
+
+    /// Called before a step: take a checkpoint when due and capture what the step may overwrite.
+    pub(crate) fn begin(&mut self, sys: &mut SystemEmulator) {
+        if self.unit_states.is_empty() {
+            self.versions.clear();
+            self.unit_states = unit_states(sys, &mut self.versions);
+        }
+        let due = sys.total_steps.is_multiple_of(self.checkpoint_every);
+        if due && self.checkpoints.back().is_none_or(|(snap, _)| snap.total_steps < sys.total_steps) {
+            self.checkpoints.push_back((sys.snapshot(WeightMode::Inline), sys.logs.len()));
+            if self.checkpoints.len() > self.max_checkpoints {
+                self.checkpoints.pop_front();
+            }
+        }
+        let bus = &sys.bus;
+        self.pending = Some(UndoEntry {
+            pc: sys.pc,
+            total_steps: sys.total_steps,
+            fault: sys.fault.clone(),
+            stats: sys.stats.clone(),
+            logs: sys.logs.len(),
+            console: sys.console_sink.lock().map_or(0, |c| c.len()),
+            registers: bus.registers.clone(),
+            port_latches: bus.port_latches.clone(),
+            fu_io_cache: bus.fu_io_cache.clone(),
+            pipelines: bus.pipelines.clone(),
+            stale_reads: bus.stale_reads,
+            early_issues: bus.early_issues,
+            assoc_stats: bus.assoc_stats,
+            bus_fault: bus.bus_fault.clone(),
+            ram: Vec::new(),
+            units: Vec::new(),
+        });
+        sys.bus.ram_journal = Some(Vec::new());
+    }
+
+    /// Called after a step: keep the entry if the step changed anything.
+    pub(crate) fn end(&mut self, sys: &mut SystemEmulator) {
+        let ram = sys.bus.ram_journal.take().unwrap_or_default();
+        let Some(mut entry) = self.pending.take() else { return };
+        if sys.total_steps == entry.total_steps && sys.fault == entry.fault {
+            return; // Halted: nothing happened
+        }
+        entry.ram = ram;
+        for (base, state) in unit_states(sys, &mut self.versions) {
+            if let Some(old) = self.unit_states.insert(base, state.clone()).filter(|old| *old != state) {
+                entry.units.push((base, old));
+            }
+        }
+        self.entries.push_back(entry);
+        if self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Undo up to `n` steps; returns how many were undone.
+    pub(crate) fn step_back(&mut self, sys: &mut SystemEmulator, n: usize) -> usize {
+        let start = sys.total_steps;
+        let target = start.saturating_sub(n);
+        while sys.total_steps > target {
+            let Some(entry) = self.entries.pop_back() else { break };
+            self.undo(sys, entry);
+        }
+        if sys.total_steps > target {
+            self.rewind(sys, target);
+        }
+        // Checkpoints from the abandoned future would restore the wrong timeline
+        while self.checkpoints.back().is_some_and(|(snap, _)| snap.total_steps > sys.total_steps) {
+            self.checkpoints.pop_back();
+        }
+        start - sys.total_steps
+    }
+
+    fn undo(&mut self, sys: &mut SystemEmulator, entry: UndoEntry) {
+        let bus = &mut sys.bus;
+        for (addr, old) in entry.ram.into_iter().rev() {
+            match old {
+                Some(value) => bus.ram.insert(addr, value),
+                None => bus.ram.remove(&addr),
+            };
+        }
+        for (base, state) in entry.units {
+            let unit = match bus.units.get_mut(&base) {
+                Some(unit) => Some(unit),
+                None => bus.mmio.get_mut(&base),
+            };
+            if let Some(unit) = unit {
+                if let Err(e) = unit.load_state(state.clone()) {
+                    sys.logs.push(format!("[Reverse] unit 0x{:X} could not be rewound: {:#}", base, e));
+                }
+            }
+            self.unit_states.insert(base, state);
+        }
+        bus.registers = entry.registers;
+        bus.port_latches = entry.port_latches;
+        bus.fu_io_cache = entry.fu_io_cache;
+        bus.pipelines = entry.pipelines;
+        bus.stale_reads = entry.stale_reads;
+        bus.early_issues = entry.early_issues;
+        bus.assoc_stats = entry.assoc_stats;
+        bus.bus_fault = entry.bus_fault;
+        bus.jump = None;
+        sys.pc = entry.pc;
+        sys.total_steps = entry.total_steps;
+        sys.fault = entry.fault;
+        sys.stats = entry.stats;
+        sys.logs.truncate(entry.logs);
+        if let Ok(mut console) = sys.console_sink.lock() {
+            console.truncate(entry.console);
+        }
+    }
+
+    /// Past the undo window: restore the newest checkpoint at or before `target` and run forward to it.
+    fn rewind(&mut self, sys: &mut SystemEmulator, target: usize) {
+        while self.checkpoints.back().is_some_and(|(snap, _)| snap.total_steps > target) {
+            self.checkpoints.pop_back();
+        }
+        let Some((snapshot, logs)) = self.checkpoints.back().cloned() else { return };
+        if let Err(e) = sys.restore(&snapshot) {
+            sys.logs.push(format!("[Reverse] checkpoint at step {} could not be restored: {:#}", snapshot.total_steps, e));
+            return;
+        }
+        sys.logs.truncate(logs);
+        self.entries.clear();
+        self.unit_states.clear();
+        self.versions.clear();
+        while sys.total_steps < target {
+            self.begin(sys);
+            let ran = sys.execute_step();
+            self.end(sys);
+            if !ran {
+                break;
+            }
+        }
+    }
+}
+
+/// States of the units that may have changed since `versions` was last updated.
+fn unit_states(sys: &SystemEmulator, versions: &mut HashMap<u16, u64>) -> HashMap<u16, serde_json::Value> {
+    let bus = &sys.bus;
+    bus.units.iter().chain(&bus.mmio)
+        .filter(|(&base, unit)| match unit.state_version() {
+            Some(version) => versions.insert(base, version) != Some(version),
+            None => true,
+        })
+        .filter_map(|(&base, unit)| Some((base, unit.save_state()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::MoveOp;
+
+    #[test]
+    fn test_step_back_and_find_the_write() {
+        let build = || {
+            let mut sys = SystemEmulator::default();
+            sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, b'A' as u32));
+            sys.bus.registers.insert(1, NeuralRegister::from_symbolic(8, 0x42));
+            let mv = |src, dest| MoveOp { src, dest, guard: None };
+            sys.load_program(vec![
+                mv(0, 0x8000), mv(0, 0x2000), mv(1, 3), mv(0x2000, 2), // UART, RAM, R3, R2
+                mv(1, 0x2001), mv(0, 0x8000), mv(0, 0x8000), mv(0x2000, 3), // overwrite R3 from RAM
+                mv(0, 4), mv(0, 5),
+            ]);
+            sys
+        };
+        let mut sys = build();
+        sys.journal = Some(Journal::new(4, 3));
+        sys.run(10);
+        let end = (sys.pc, sys.bus.registers[&3].state.clone(), sys.console_sink.lock().unwrap().clone());
+
+        // Exact undo inside the window, then forward again to the same end state
+        assert_eq!(sys.step_back(2), 2);
+        assert_eq!((sys.pc, sys.total_steps), (8, 8));
+        sys.run(10);
+        assert_eq!((sys.pc, sys.bus.registers[&3].state.clone(), sys.console_sink.lock().unwrap().clone()), end);
+
+        // Who last wrote R3? Bundle 7, found by running backwards
+        assert_eq!(sys.reverse_until_changed(3, 100), Some(7));
+        assert_eq!(sys.bus.registers[&3].to_symbolic(), 0x42);
+
+        // Past the 4-step window: back to the checkpoint at step 0, then re-run to step 2
+        assert_eq!(sys.step_back(5), 5);
+        let mut fresh = build();
+        fresh.run(2);
+        assert_eq!(sys.total_steps, 2);
+        assert_eq!(sys.bus.ram, fresh.bus.ram);
+        assert_eq!(sys.bus.registers[&2].state, fresh.bus.registers[&2].state);
+        assert_eq!(sys.console_sink.lock().unwrap().as_str(), "A");
+        assert_eq!(sys.logs.len(), fresh.logs.len());
+        assert!(!sys.bus.ram.is_empty());
+
+        // Nothing before step 0
+        assert_eq!(sys.step_back(10), 2);
+        assert!(sys.bus.ram.is_empty());
+        assert_eq!(sys.step_back(1), 0);
+
+        // The framebuffer is only saved on steps that drew, and still undone exactly
+        let mut sys = SystemEmulator::default();
+        sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 1));
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        sys.load_program(vec![mv(0, 0x8102), mv(0, 1), mv(0, 2)]); // PIXEL at (0, 0)
+        sys.journal = Some(Journal::default());
+        sys.run(10);
+        let frame = sys.display.clone().unwrap();
+        assert!(frame.lock().unwrap().get(0, 0));
+        assert_eq!(sys.step_back(2), 2);
+        assert!(frame.lock().unwrap().get(0, 0));
+        assert_eq!(sys.step_back(1), 1);
+        assert!(!frame.lock().unwrap().get(0, 0));
+    }
+}
@@ -1,5 +1,14 @@
 pub mod legacy;
 pub mod fu;
 pub mod bus;
+pub mod guard;
+pub mod pipeline;
 pub mod register;
 pub mod voter;
+pub mod system;
+pub mod loader;
+pub mod boot;
+pub mod snapshot;
+pub mod journal;
+pub mod breakpoint;
+pub mod gdb;
@@ -1,41 +1,91 @@
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::Path;
-use anyhow::{Result, Context};
+use anyhow::{bail, Context, Result};
 use ndarray::Array1;
 use crate::system::SystemEmulator;
 use crate::register::NeuralRegister;
 use crate::bus::SystemBus;
-use crate::fu::{BaseFU, UartFU};
+use crate::fu::{
+    AssocConfig, BaseFU, ByteSource, DiskFU, DiskImage, FUType, FileSource, InputQueue, InterruptControllerFU, KeyQueue, KeypadFU,
+    LoadStoreFU, ScriptedKey, SharedFrame, StackConfig, StackPointerFU, StdinSource, TimerFU, UartFU, VideoFU,
+    WeightFile,
+};
+use crate::pipeline::{HazardPolicy, UnitTiming};
 
 
 #[derive(Debug, Deserialize)]
 pub struct Manifest {
     pub ram_size: usize,
+    /// Transport buses, i.e. moves per instruction bundle.
+    #[serde(default = "default_buses")]
+    pub buses: usize,
+    /// Reads of results still in a pipeline: `stall` (default) or `stale`.
+    #[serde(default)]
+    pub hazard_policy: HazardPolicy,
     pub units: Vec<UnitConfig>,
     pub program_path: Option<String>,
     pub ram_init: Option<HashMap<String, Vec<f32>>>,
 }
 
+fn default_buses() -> usize {
+    1
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UnitConfig {
     pub name: String,
     pub address: u16,
-    pub unit_type: String, // "comparator", "bitwise", "uart", "generic"
+    pub unit_type: String, // "adder", "comparator", "bitwise", "multiplier", "shifter", "divider", "lsu", "stack", "irq", "timer", "uart", "video", "keypad", "disk", "generic"
     pub weights_path: Option<String>,
+    /// Pipelined timing; a unit with neither is combinational.
+    pub latency: Option<usize>,
+    pub initiation_interval: Option<usize>,
+    /// LSU only: resolve noisy addresses to the nearest populated cell.
+    pub associative: Option<AssocConfig>,
+    /// Stack only: RAM region, defaults to the top 256 cells.
+    pub stack: Option<StackConfig>,
+    /// UART only: "stdin", "file:<path>" or "console" (the default, typed into the GUI).
+    pub input: Option<String>,
+    /// Keypad only: JSON key timeline for headless runs, relative to the manifest.
+    pub key_script: Option<String>,
+    /// Disk only: host image file, relative to the manifest.
+    pub image: Option<String>,
+    /// Interrupt source: the controller line this unit raises.
+    pub irq: Option<u8>,
+    /// Timer only: initial period in ticks (0 or absent: stopped).
+    pub period: Option<u16>,
+    /// Interrupt controller only: vector table base, defaults to 0x2000.
+    pub vectors: Option<u16>,
 }
 
-// Helper to deserialize MoveOps
-#[derive(Deserialize)]
-struct ProgramFile {
-    ops: Vec<crate::bus::MoveOp>,
+pub fn load_manifest(path: &Path, console_sink: Option<std::sync::Arc<std::sync::Mutex<String>>>) -> Result<SystemEmulator> {
+    build(path, console_sink).map(|(emulator, _)| emulator)
 }
 
-pub fn load_manifest(path: &Path, console_sink: Option<std::sync::Arc<std::sync::Mutex<String>>>) -> Result<SystemEmulator> {
+/// Two-stage boot: instantiate the firmware manifest's units, then let the ROM
+/// stage load the boot sector of `disk_image` (see `SystemEmulator::load_firmware`).
+/// Each stage is reported in the trace; the boot program replaces any `program_path`.
+pub fn boot(
+    firmware_manifest: &Path,
+    disk_image: &Path,
+    console_sink: Option<std::sync::Arc<std::sync::Mutex<String>>>,
+) -> Result<SystemEmulator> {
+    let (mut emulator, units) = build(firmware_manifest, console_sink)?;
+    emulator.logs.push(format!("[Boot 1/3] Firmware: {} units from {:?}", units.len(), firmware_manifest));
+    emulator.logs.extend(units.into_iter().map(|unit| format!("[Boot 1/3]   {}", unit)));
+    let mut image = DiskImage::open(disk_image)?;
+    emulator.load_firmware(&mut image).with_context(|| format!("booting from {:?}", disk_image))?;
+    Ok(emulator)
+}
+
+/// The emulator, plus one line per instantiated unit saying where its weights came from.
+fn build(path: &Path, console_sink: Option<std::sync::Arc<std::sync::Mutex<String>>>) -> Result<(SystemEmulator, Vec<String>)> {
     let file = std::fs::File::open(path)?;
     let manifest: Manifest = serde_json::from_reader(file)?;
 
     let mut bus = SystemBus::new();
+    bus.hazard_policy = manifest.hazard_policy;
 
     // 1. Initialize RAM
     // Pre-populate RAM if ram_init is present
@@ -53,7 +103,18 @@ pub fn load_manifest(path: &Path, console_sink: Option<std::sync::Arc<std::sync:
     }
 
     // 3. Initialize Functional Units
+    let console_input = InputQueue::default();
+    let mut display = None;
+    let mut keys = None;
+    let mut units = Vec::new();
     for unit_cfg in manifest.units {
+        let source = match &unit_cfg.weights_path {
+            Some(w_path) if Path::new(w_path).exists() => format!("weights {}", w_path),
+            _ if neural_kind(&unit_cfg.unit_type).is_some() => "random weights".to_string(),
+            _ => "no weights".to_string(),
+        };
+        units.push(format!("{} ({}) at 0x{:X}, {}", unit_cfg.name, unit_cfg.unit_type, unit_cfg.address, source));
+        bus.names.insert(unit_cfg.address, unit_cfg.name.clone());
         // The original code used a match statement to create the unit, then added it.
         // The new instruction implies an if-else if structure and direct addition.
         // We'll adapt the existing logic to this new structure.
@@ -64,38 +125,69 @@ pub fn load_manifest(path: &Path, console_sink: Option<std::sync::Arc<std::sync:
             } else {
                 UartFU::new()
             };
-            // UART is MMIO usually, but manifest treats as unit?
-            // Manifest has address 32768 (0x8000), which is MMIO.
-            // But valid Unit range is 0x1000..0x1FFF.
-            // Bus adds to MMIO if addr >= 0x8000.
-            if unit_cfg.address >= 0x8000 {
-                bus.mmio.insert(unit_cfg.address, Box::new(fu));
-            } else {
-                bus.units.insert(unit_cfg.address, Box::new(fu));
-            }
-        } else if unit_cfg.unit_type == "comparator" {
-            let fu = BaseFU::create_comparator();
-            if let Some(w_path) = unit_cfg.weights_path {
-                if Path::new(&w_path).exists() {
-                   // fu.load_weights(&w_path)?;
-                }
-            }
-            if unit_cfg.address >= 0x8000 {
-                bus.mmio.insert(unit_cfg.address, Box::new(fu));
-            } else {
-                bus.units.insert(unit_cfg.address, Box::new(fu));
-            }
-        } else if unit_cfg.unit_type == "bitwise" {
-            let fu = BaseFU::create_bitwise();
-            if let Some(w_path) = unit_cfg.weights_path {
-                if Path::new(&w_path).exists() {
-                   // fu.load_weights(&w_path)?;
-                }
+            let source: Box<dyn ByteSource> = match unit_cfg.input.as_deref() {
+                None | Some("console") => Box::new(console_input.clone()),
+                Some("stdin") => Box::new(StdinSource::spawn()),
+                Some(other) => match other.strip_prefix("file:") {
+                    Some(path) => Box::new(FileSource::open(Path::new(path))?),
+                    None => bail!("unknown UART input '{}' (expected console, stdin or file:<path>)", other),
+                },
+            };
+            let fu = fu.with_source(source);
+            let layout = fu.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(fu), layout)?;
+        } else if unit_cfg.unit_type == "lsu" {
+            let lsu = match unit_cfg.associative {
+                Some(config) => LoadStoreFU::associative(8, config),
+                None => LoadStoreFU::new(8),
+            };
+            let layout = lsu.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(lsu), layout)?;
+        } else if unit_cfg.unit_type == "stack" {
+            let region = unit_cfg.stack.unwrap_or_default();
+            region.check().with_context(|| format!("stack unit '{}'", unit_cfg.name))?;
+            let stack = StackPointerFU::new(8, region);
+            let layout = stack.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(stack), layout)?;
+        } else if unit_cfg.unit_type == "irq" {
+            let vectors = unit_cfg.vectors.unwrap_or(0x2000);
+            InterruptControllerFU::check_vectors(vectors).with_context(|| format!("irq unit '{}'", unit_cfg.name))?;
+            let irq = InterruptControllerFU::new(vectors);
+            let layout = irq.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(irq), layout)?;
+            bus.irq_controller = Some(unit_cfg.address);
+        } else if unit_cfg.unit_type == "video" {
+            let video = VideoFU::new(display.get_or_insert_with(SharedFrame::default).clone());
+            let layout = video.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(video), layout)?;
+        } else if unit_cfg.unit_type == "keypad" {
+            let mut keypad = KeypadFU::new(keys.get_or_insert_with(KeyQueue::default).clone());
+            if let Some(script) = &unit_cfg.key_script {
+                let script_path = path.parent().unwrap_or(Path::new(".")).join(script);
+                let events: Vec<ScriptedKey> = serde_json::from_reader(std::fs::File::open(script_path)?)?;
+                keypad = keypad.with_script(events);
             }
-            if unit_cfg.address >= 0x8000 {
-                bus.mmio.insert(unit_cfg.address, Box::new(fu));
-            } else {
-                bus.units.insert(unit_cfg.address, Box::new(fu));
+            let layout = keypad.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(keypad), layout)?;
+        } else if unit_cfg.unit_type == "disk" {
+            let Some(image) = &unit_cfg.image else {
+                bail!("disk unit '{}' needs an image", unit_cfg.name);
+            };
+            let image_path = path.parent().unwrap_or(Path::new(".")).join(image);
+            let disk = DiskFU::new(DiskImage::open(&image_path)?);
+            let layout = disk.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(disk), layout)?;
+        } else if unit_cfg.unit_type == "timer" {
+            let timer = TimerFU::new(unit_cfg.period.unwrap_or(0));
+            let layout = timer.port_layout();
+            bus.add_unit_with_ports(unit_cfg.address, Box::new(timer), layout)?;
+        } else if let Some(kind) = neural_kind(&unit_cfg.unit_type) {
+            // Neural units are port-mapped: operands from the base address,
+            // trigger on the last input, results readable after it.
+            let weights = load_or_create(&unit_cfg, kind)?;
+            bus.add_unit_with_ports(unit_cfg.address, kind.build(weights), kind.port_layout())?;
+            if kind == FUType::CMP {
+                bus.add_flag_unit(unit_cfg.address);
             }
         } else {
             // Default generic or error
@@ -106,30 +198,96 @@ pub fn load_manifest(path: &Path, console_sink: Option<std::sync::Arc<std::sync:
                 bus.units.insert(unit_cfg.address, Box::new(fu));
             }
         }
+
+        if unit_cfg.latency.is_some() || unit_cfg.initiation_interval.is_some() {
+            let defaults = UnitTiming::default();
+            bus.set_timing(unit_cfg.address, UnitTiming {
+                latency: unit_cfg.latency.unwrap_or(defaults.latency),
+                initiation_interval: unit_cfg.initiation_interval.unwrap_or(defaults.initiation_interval),
+            })?;
+        }
+        if let Some(line) = unit_cfg.irq {
+            bus.connect_irq(unit_cfg.address, line)?;
+        }
     }
     
+    if manifest.buses == 0 {
+        bail!("manifest must declare at least one bus");
+    }
     let mut emulator = SystemEmulator::new(bus);
+    emulator.console_input = console_input;
+    emulator.display = display;
+    emulator.keys = keys;
+    emulator.buses = manifest.buses;
+    emulator.manifest = Some(path.to_path_buf());
 
     // 4. Load Program if specified
     if let Some(prog_path_str) = manifest.program_path {
         let prog_path = path.parent().unwrap_or(Path::new(".")).join(prog_path_str);
         if prog_path.exists() {
             let pfile = std::fs::File::open(prog_path)?;
-            let ops: Vec<crate::bus::MoveOp> = serde_json::from_reader(pfile)?;
-            emulator.load_program(ops);
+            let bundles: Vec<crate::bus::Bundle> = serde_json::from_reader(pfile)?;
+            emulator.load_bundles(bundles);
+            emulator.check_program()?;
         } else {
              eprintln!("Warning: Program file not found at {:?}", prog_path);
         }
     }
 
-    Ok(emulator)
+    Ok((emulator, units))
+}
+
+/// Manifest `unit_type` strings for the trainable units.
+fn neural_kind(unit_type: &str) -> Option<FUType> {
+    match unit_type {
+        "adder" => Some(FUType::ADDER),
+        "comparator" => Some(FUType::CMP),
+        "bitwise" => Some(FUType::BITWISE),
+        "multiplier" => Some(FUType::MUL),
+        "shifter" => Some(FUType::SHIFT),
+        "divider" => Some(FUType::DIV),
+        _ => None,
+    }
+}
+
+/// Use trained weights if the manifest points at an existing file, otherwise a fresh random unit.
+/// Self-describing weight files must declare the kind the manifest expects.
+fn load_or_create(cfg: &UnitConfig, kind: FUType) -> Result<BaseFU> {
+    if let Some(w_path) = &cfg.weights_path {
+        if Path::new(w_path).exists() {
+            let file = WeightFile::load(Path::new(w_path))?;
+            if let Some(found) = file.header.kind {
+                if found != kind {
+                    bail!("{}: weights at {} are for {:?}, expected {:?}", cfg.name, w_path, found, kind);
+                }
+            }
+            let shape = (file.weights.w1.ncols(), file.weights.w2.nrows());
+            if let Some((inputs, _, outputs)) = kind.network_shape() {
+                if shape != (inputs, outputs) {
+                    bail!("{}: weights at {} are {}->{}, {:?} needs {}->{}",
+                        cfg.name, w_path, shape.0, shape.1, kind, inputs, outputs);
+                }
+            }
+            return Ok(file.weights);
+        }
+    }
+    kind.create_unit(&mut rand::thread_rng())
+        .ok_or_else(|| anyhow::anyhow!("{}: {:?} has no network to create", cfg.name, kind))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fu::NeuralFunctionalUnit;
     use std::io::Write;
 
+    /// Load `json` as a manifest from its own temporary file, deleted when this returns.
+    fn load_test_manifest(json: &str) -> Result<SystemEmulator> {
+        let mut file = tempfile::Builder::new().suffix(".json").tempfile()?;
+        file.write_all(json.as_bytes())?;
+        load_manifest(file.path(), None)
+    }
+
     #[test]
     fn test_manifest_parsing() {
         let json_content = r#"
@@ -152,14 +310,8 @@ mod tests {
         }
         "#;
         
-        // Create temp file
-        let mut temp_file = std::env::temp_dir();
-        temp_file.push("test_manifest.json");
-        let mut file = std::fs::File::create(&temp_file).unwrap();
-        file.write_all(json_content.as_bytes()).unwrap();
-        
         // Test Load
-        let mut sys = load_manifest(&temp_file, None).expect("Failed to load manifest");
+        let sys = load_test_manifest(json_content).expect("Failed to load manifest");
         
         // Verify Config
         // Check MMIO (UART at 0x8000 = 32768)
@@ -169,8 +321,239 @@ mod tests {
         // Check Registers
         assert!(sys.bus.registers.contains_key(&0));
         assert!(sys.bus.registers.contains_key(&15));
-        
-        // Cleanup
-        std::fs::remove_file(temp_file).unwrap();
+        // Names from the manifest, kinds from the units
+        assert_eq!(sys.bus.unit_name(32768).as_deref(), Some("TestUART"));
+        assert_eq!(sys.bus.unit(32768).and_then(|u| u.introspect()).map(|i| i.kind()), Some("uart"));
+    }
+
+    #[test]
+    fn test_multiplier_through_ports() {
+        let weights = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fus/alu_mul.json");
+        let json_content = format!(r#"{{
+            "ram_size": 1024,
+            "units": [
+                {{ "name": "Mul", "address": 4096, "unit_type": "multiplier", "weights_path": {:?} }}
+            ]
+        }}"#, weights.to_string_lossy());
+        let mut sys = load_test_manifest(&json_content).expect("Failed to load manifest");
+
+        // 200 * 123 = 24600 = 0x6018
+        sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 200));
+        sys.bus.registers.insert(1, NeuralRegister::from_symbolic(8, 123));
+        let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+        sys.load_program(vec![mv(0, 0x1000), mv(1, 0x1001), mv(0x1002, 2), mv(0x1003, 3)]);
+        while sys.step() {}
+
+        assert_eq!(sys.bus.registers[&2].to_symbolic(), 0x18);
+        assert_eq!(sys.bus.registers[&3].to_symbolic(), 0x60);
+    }
+
+    #[test]
+    fn test_pipelined_latency_policies() {
+        let weights = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fus/alu_mul.json");
+        let run = |policy: &str| {
+            let json_content = format!(r#"{{
+                "ram_size": 1024,
+                "hazard_policy": "{}",
+                "units": [
+                    {{ "name": "Mul", "address": 4096, "unit_type": "multiplier", "weights_path": {:?},
+                       "latency": 3, "initiation_interval": 2 }}
+                ]
+            }}"#, policy, weights.to_string_lossy());
+            let mut sys = load_test_manifest(&json_content).expect("Failed to load manifest");
+
+            // 12 * 11 = 132, read LO straight after the trigger
+            sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 12));
+            sys.bus.registers.insert(1, NeuralRegister::from_symbolic(8, 11));
+            let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+            sys.load_program(vec![mv(0, 0x1000), mv(1, 0x1001), mv(0x1002, 2)]);
+            let report = sys.run(100);
+            (report, sys.bus.registers[&2].to_symbolic())
+        };
+
+        // Interlocked: two stall cycles until the result lands three cycles after the trigger
+        let (report, lo) = run("stall");
+        assert_eq!(lo, 132);
+        assert_eq!((report.cycles, report.bundles, report.stalls, report.stale_reads), (5, 3, 2, 0));
+        assert!(report.halted);
+
+        // Exposed: no stalls, the read sees the old (empty) latch
+        let (report, lo) = run("stale");
+        assert_eq!(lo, 0);
+        assert_eq!((report.cycles, report.stalls, report.stale_reads), (3, 0, 1));
+    }
+
+    #[test]
+    fn test_associative_lsu_fault_halts() {
+        let json_content = r#"{
+            "ram_size": 1024,
+            "units": [
+                { "name": "LSU", "address": 4352, "unit_type": "lsu", "weights_path": null,
+                  "associative": { "metric": "cosine", "confidence_floor": 0.9 } }
+            ]
+        }"#;
+        let mut sys = load_test_manifest(json_content).expect("Failed to load manifest");
+
+        // Nothing in RAM to resolve to: the load is a bus fault and the machine stops
+        let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+        sys.load_program(vec![mv(0, 0x1102), mv(1, 2)]);
+        let report = sys.run(10);
+        assert_eq!(report.bundles, 1);
+        assert!(sys.fault.as_deref().unwrap().contains("no populated RAM cell"));
+        assert_eq!(sys.bus.assoc_stats.faults, 1);
+    }
+
+    #[test]
+    fn test_stack_call_and_return() {
+        let json_content = r#"{
+            "ram_size": 1024,
+            "units": [
+                { "name": "Stack", "address": 4608, "unit_type": "stack", "weights_path": null,
+                  "stack": { "base": 28672, "size": 16 } }
+            ]
+        }"#;
+        let mut sys = load_test_manifest(json_content).expect("Failed to load manifest");
+
+        // Stack ports: CALL 0x1204, RET 0x1205, SP 0x1207
+        sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 4));
+        sys.bus.registers.insert(1, NeuralRegister::from_symbolic(8, 5));
+        sys.bus.registers.insert(4, NeuralRegister::from_symbolic(8, 6));
+        let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+        sys.load_program(vec![
+            mv(0, 0x1204),             // 0: CALL 4
+            mv(1, 3),                  // 1: back from the call
+            mv(4, crate::bus::PC_ADDR), // 2: jump past the end
+            mv(0, 5),                  // 3: never runs
+            mv(1, 2),                  // 4: subroutine
+            mv(0, 0x1205),             // 5: RET
+        ]);
+        let report = sys.run(20);
+
+        assert!(report.halted && sys.fault.is_none());
+        assert_eq!(report.bundles, 5);
+        assert_eq!((sys.bus.registers[&2].to_symbolic(), sys.bus.registers[&3].to_symbolic()), (5, 5));
+        assert_eq!(sys.bus.registers[&5].to_symbolic(), 0);
+        assert_eq!(sys.bus.ram[&0x700F], Array1::from(crate::fu::kind::encode_bits(1, 16))); // Return address
+        assert_eq!(crate::fu::kind::decode_bits(sys.bus.port_latches[&0x1207].as_slice().unwrap()), 0x7010);
+
+        // Regions that leave RAM (or the address space) are rejected, not a panic
+        for region in [r#"{ "base": 65280, "size": 512 }"#, r#"{ "base": 4096, "size": 16 }"#] {
+            let bad = json_content.replace(r#"{ "base": 28672, "size": 16 }"#, region);
+            let err = load_test_manifest(&bad).err().expect("bad stack region accepted");
+            assert!(format!("{:#}", err).contains("outside RAM"));
+        }
+    }
+
+    #[test]
+    fn test_timer_interrupts_and_reti() {
+        let json_content = r#"{
+            "ram_size": 1024,
+            "units": [
+                { "name": "PIC", "address": 4864, "unit_type": "irq", "weights_path": null, "vectors": 8448 },
+                { "name": "Timer", "address": 4880, "unit_type": "timer", "weights_path": null, "period": 4, "irq": 1 }
+            ]
+        }"#;
+        let mut sys = load_test_manifest(json_content).expect("Failed to load manifest");
+
+        // Controller ports: ENABLE 0x1300 .. RETI 0x1305. Vector for line 1 at 0x2101.
+        sys.bus.ram.insert(0x2101, Array1::from(crate::fu::kind::encode_bits(6, 16)));
+        sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 0b10));
+        sys.bus.registers.insert(5, NeuralRegister::from_symbolic(8, 0x5A));
+        sys.bus.registers.insert(7, NeuralRegister::from_symbolic(8, 8));
+        let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+        sys.load_program(vec![
+            mv(0, 0x1300),             // 0: enable line 1
+            mv(1, 2),                  // 1-4: main line
+            mv(1, 2),
+            mv(1, 2),
+            mv(1, 2),
+            mv(7, crate::bus::PC_ADDR), // 5: jump past the end
+            mv(5, 6),                  // 6: handler
+            mv(0, 0x1305),             // 7: RETI
+        ]);
+        let report = sys.run(50);
+
+        // Every 4 ticks: taken before bundles 4 and 5, each time returning where it left off
+        assert!(report.halted && sys.fault.is_none());
+        assert_eq!(report.cycles, 12);
+        assert_eq!(sys.logs.iter().filter(|l| l.contains("Interrupt")).count(), 2);
+        assert_eq!(sys.bus.registers[&6].to_symbolic(), 0x5A);
+        assert_eq!(sys.bus.ram[&0x2108], Array1::from(crate::fu::kind::encode_bits(5, 16))); // Last saved PC
+
+        // A table that would leave RAM is refused, from the manifest and from a program
+        let err = load_test_manifest(&json_content.replace("8448", "65530")).err().expect("bad vector table accepted");
+        assert!(format!("{:#}", err).contains("outside RAM"));
+        let mut pic = InterruptControllerFU::new(0x2100);
+        let mut input = vec![0.0; InterruptControllerFU::LINES];
+        input.extend(crate::fu::kind::encode_bits(0xFFFC, 16));
+        pic.trigger(InterruptControllerFU::VECTORS_PORT, &Array1::from(input));
+        assert!(pic.take_fault().unwrap().contains("outside RAM"));
+        assert_eq!(pic.vectors, 0x2100);
+    }
+
+    #[test]
+    fn test_boot_from_disk_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("firmware.json");
+        std::fs::write(&manifest, r#"{
+            "ram_size": 1024,
+            "units": [
+                { "name": "UART", "address": 32768, "unit_type": "uart", "weights_path": null },
+                { "name": "Disk", "address": 36864, "unit_type": "disk", "weights_path": null, "image": "boot.img" }
+            ]
+        }"#).unwrap();
+
+        // Boot program prints the two bytes stored after it in the same sector, entering at bundle 1
+        let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+        let boot_sector = crate::boot::BootSector {
+            program: vec![mv(0x2102, 0x8000).into(), mv(0x2100, 0x8000).into(), mv(0x2101, 0x8000).into()],
+            entry: 1,
+        };
+        let mut sector = boot_sector.encode().unwrap();
+        sector[0x100..0x103].copy_from_slice(b"OKX");
+        let image = dir.path().join("boot.img");
+        DiskImage::create(&image, 2).unwrap().write_sector(0, &sector).unwrap();
+
+        let sink = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+        let mut sys = boot(&manifest, &image, Some(sink.clone())).expect("Failed to boot");
+        assert_eq!(sys.pc, 1);
+        assert_eq!(sys.bus.ram[&0x2000], Array1::from(crate::fu::kind::encode_bits(b'N' as u32, 8)));
+        for stage in ["[Boot 1/3]", "[Boot 2/3]", "[Boot 3/3]"] {
+            assert!(sys.logs.iter().any(|l| l.starts_with(stage)), "missing {}", stage);
+        }
+        assert!(sys.run(10).halted);
+        assert_eq!(sink.lock().unwrap().as_str(), "OK");
+
+        // A disk without a boot signature stops at the ROM stage
+        DiskImage::create(&image, 1).unwrap();
+        assert!(boot(&manifest, &image, None).is_err());
+    }
+
+    #[test]
+    fn test_divider_busy_polling() {
+        let weights = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/fus/alu_div.json");
+        let json_content = format!(r#"{{
+            "ram_size": 1024,
+            "units": [
+                {{ "name": "Div", "address": 4096, "unit_type": "divider", "weights_path": {:?} }}
+            ]
+        }}"#, weights.to_string_lossy());
+        let mut sys = load_test_manifest(&json_content).expect("Failed to load manifest");
+
+        // 200 / 7 = 28 rem 4. Ports: DIVIDEND, DIVISOR, QUOTIENT, REMAINDER, DIV0, BUSY.
+        sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, 200));
+        sys.bus.registers.insert(1, NeuralRegister::from_symbolic(8, 7));
+        let mv = |src, dest| crate::bus::MoveOp { src, dest, guard: None };
+        let mut program = vec![mv(0, 0x1000), mv(1, 0x1001), mv(0x1005, 4)];
+        program.extend(std::iter::repeat_n(mv(0x1005, 5), 6)); // Poll BUSY until the last step
+        program.extend([mv(0x1005, 6), mv(0x1002, 2), mv(0x1003, 3)]);
+        sys.load_program(program);
+        while sys.step() {}
+
+        assert_eq!(sys.bus.registers[&4].to_symbolic(), 1);
+        assert_eq!(sys.bus.registers[&5].to_symbolic(), 1);
+        assert_eq!(sys.bus.registers[&6].to_symbolic(), 0);
+        assert_eq!(sys.bus.registers[&2].to_symbolic(), 28);
+        assert_eq!(sys.bus.registers[&3].to_symbolic(), 4);
     }
 }
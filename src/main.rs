@@ -1,14 +1,4 @@
-mod fu;
-mod bus;
-mod register;
-mod voter;
-mod system;
 mod gui;
-mod loader;
-
-// use bus::{MoveOp, SystemBus}; // Removed references to CLI-only run
-// use system::SystemEmulator;
-// use register::NeuralRegister;
 
 fn main() -> Result<(), eframe::Error> {
     // env_logger::init(); 
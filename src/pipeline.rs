@@ -0,0 +1,105 @@
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Timing of a pipelined unit, declared per unit in the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitTiming {
+    /// Cycles from the trigger until the result is on the output ports.
+    /// 0 and 1 both behave like a combinational unit: the next bundle sees the result.
+    pub latency: usize,
+    /// Minimum cycles between two triggers.
+    pub initiation_interval: usize,
+}
+
+impl Default for UnitTiming {
+    fn default() -> Self {
+        Self { latency: 0, initiation_interval: 1 }
+    }
+}
+
+/// What happens when a bundle touches a pipelined unit that is not ready.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HazardPolicy {
+    /// Interlocked: the bundle waits (the cycle is a stall) until results are
+    /// ready and the unit can accept a new trigger.
+    #[default]
+    Stall,
+    /// Exposed pipeline: reads return whatever is latched and early triggers
+    /// are accepted. Both are counted so schedules can be checked.
+    Stale,
+}
+
+/// Results in flight inside one pipelined unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub timing: UnitTiming,
+    /// (ticks until ready, output), oldest first.
+    in_flight: VecDeque<(usize, Array1<f32>)>,
+    /// Ticks since the last trigger.
+    since_issue: usize,
+}
+
+impl Pipeline {
+    pub fn new(timing: UnitTiming) -> Self {
+        Self { timing, in_flight: VecDeque::new(), since_issue: usize::MAX }
+    }
+
+    /// True while a result is still travelling through the pipeline.
+    pub fn is_pending(&self) -> bool {
+        !self.in_flight.is_empty()
+    }
+
+    /// True if a trigger now would violate the initiation interval.
+    pub fn is_blocked(&self) -> bool {
+        self.since_issue < self.timing.initiation_interval
+    }
+
+    /// Start a result on its way. Returns it instead if the unit is combinational.
+    pub fn issue(&mut self, output: Array1<f32>) -> Option<Array1<f32>> {
+        self.since_issue = 0;
+        if self.timing.latency == 0 {
+            return Some(output);
+        }
+        self.in_flight.push_back((self.timing.latency, output));
+        None
+    }
+
+    /// Advance one cycle; returns the results that became ready, oldest first.
+    pub fn tick(&mut self) -> Vec<Array1<f32>> {
+        self.since_issue = self.since_issue.saturating_add(1);
+        for entry in self.in_flight.iter_mut() {
+            entry.0 -= 1;
+        }
+        let mut ready = Vec::new();
+        while self.in_flight.front().is_some_and(|(left, _)| *left == 0) {
+            ready.extend(self.in_flight.pop_front().map(|(_, out)| out));
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_and_interval() {
+        let mut p = Pipeline::new(UnitTiming { latency: 3, initiation_interval: 2 });
+        assert!(!p.is_blocked());
+        assert!(p.issue(Array1::from(vec![1.0])).is_none());
+        assert!(p.is_pending() && p.is_blocked());
+
+        assert!(p.tick().is_empty());
+        assert!(p.is_blocked());
+        assert!(p.tick().is_empty());
+        assert!(!p.is_blocked()); // II = 2: a second trigger is fine now
+        p.issue(Array1::from(vec![2.0]));
+        assert_eq!(p.tick(), vec![Array1::from(vec![1.0])]);
+        assert!(p.is_pending());
+        assert!(p.tick().is_empty());
+        assert_eq!(p.tick(), vec![Array1::from(vec![2.0])]);
+        assert!(!p.is_pending());
+    }
+}
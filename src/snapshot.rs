@@ -0,0 +1,83 @@
+use anyhow::{bail, Context, Result};
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::bus::Bundle;
+use crate::fu::lsu::AssocStats;
+use crate::fu::BaseFU;
+use crate::pipeline::Pipeline;
+use crate::system::BusStats;
+
+/// Bumped whenever a field changes meaning; older files are refused rather than misread.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// How a snapshot stores trained weights.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightMode {
+    /// Full tensors, so perturbed (fault-injected) weights come back exactly.
+    #[default]
+    Inline,
+    /// Content hash only: restoring checks that the rebuilt system loaded the same weights.
+    Reference,
+}
+
+/// One unit's part of a snapshot.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnitSnapshot {
+    /// `NeuralFunctionalUnit::save_state`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights: Option<BaseFU>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights_hash: Option<String>,
+}
+
+/// Everything a running `SystemEmulator` holds that its manifest does not.
+///
+/// Units themselves (and their byte sinks, sources and image files) are not
+/// serialized: a snapshot is restored onto a system built from the same
+/// manifest, which `manifest` records when it is known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub manifest: Option<PathBuf>,
+    pub program: Vec<Bundle>,
+    pub pc: usize,
+    pub total_steps: usize,
+    pub fault: Option<String>,
+    pub stats: BusStats,
+    pub console: String,
+    pub registers: BTreeMap<u16, Array1<f32>>,
+    pub ram: BTreeMap<u16, Array1<f32>>,
+    pub port_latches: BTreeMap<u16, Array1<f32>>,
+    pub fu_io_cache: BTreeMap<u16, (Array1<f32>, Array1<f32>)>,
+    pub pipelines: BTreeMap<u16, Pipeline>,
+    pub stale_reads: usize,
+    pub early_issues: usize,
+    pub assoc_stats: AssocStats,
+    pub bus_fault: Option<String>,
+    /// Units by base address (FU sockets and MMIO alike).
+    pub units: BTreeMap<u16, UnitSnapshot>,
+}
+
+impl Snapshot {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path).with_context(|| format!("creating snapshot {:?}", path))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path).with_context(|| format!("opening snapshot {:?}", path))?;
+        let value: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+        if version != SNAPSHOT_VERSION as u64 {
+            bail!("{:?} is a version {} snapshot, this build reads version {}", path, version, SNAPSHOT_VERSION);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
@@ -1,21 +1,147 @@
-use crate::bus::{SystemBus, MoveOp};
-use crate::fu::UartFU;
+use anyhow::{bail, Context};
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::boot::{BootSector, BOOT_ADDR};
+use crate::breakpoint::{BreakHit, Breakpoint};
+use crate::bus::{Bundle, SystemBus, MoveOp};
+use crate::fu::kind::encode_bits;
+use crate::fu::weights::content_hash;
+use crate::fu::{DiskImage, InputQueue, KeyQueue, KeypadFU, SharedFrame, UartFU, VideoFU};
+use crate::journal::Journal;
+use crate::register::NeuralRegister;
+use crate::snapshot::{Snapshot, UnitSnapshot, WeightMode, SNAPSHOT_VERSION};
 
 // System struct removed in favor of SystemEmulator
 
 
+/// Transport bus usage, one slot per bus per cycle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BusStats {
+    pub cycles: usize,
+    /// Cycles spent waiting on a pipelined unit (no bundle issued).
+    pub stalls: usize,
+    pub slots: usize,
+    /// Moves whose guard passed.
+    pub moves: usize,
+    /// Moves issued but skipped by their guard.
+    pub skipped: usize,
+    /// `histogram[k]` = cycles that executed `k` moves.
+    pub histogram: Vec<usize>,
+}
+
+impl BusStats {
+    pub fn record(&mut self, buses: usize, issued: usize, executed: usize) {
+        self.cycles += 1;
+        self.slots += buses;
+        self.moves += executed;
+        self.skipped += issued - executed;
+        if self.histogram.len() <= executed {
+            self.histogram.resize(executed + 1, 0);
+        }
+        self.histogram[executed] += 1;
+    }
+
+    pub fn record_stall(&mut self, buses: usize) {
+        self.record(buses, 0, 0);
+        self.stalls += 1;
+    }
+
+    /// Fraction of bus slots that carried an executed move.
+    pub fn utilization(&self) -> f32 {
+        if self.slots == 0 { return 0.0; }
+        self.moves as f32 / self.slots as f32
+    }
+}
+
+/// Cycle-accurate counts for one `SystemEmulator::run`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunReport {
+    pub cycles: usize,
+    /// Bundles that issued (cycles minus stalls).
+    pub bundles: usize,
+    pub stalls: usize,
+    pub moves: usize,
+    pub stale_reads: usize,
+    pub early_issues: usize,
+    /// True if the run ended because the program halted or faulted, not on the cycle limit.
+    pub halted: bool,
+}
+
+impl std::fmt::Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} cycles, {} bundles, {} stalls, {} moves", self.cycles, self.bundles, self.stalls, self.moves)?;
+        if self.stale_reads + self.early_issues > 0 {
+            write!(f, ", {} stale reads, {} early issues", self.stale_reads, self.early_issues)?;
+        }
+        Ok(())
+    }
+}
+
 // Extended System struct to hold the ROM for iteration 4 transparency
 pub struct SystemEmulator {
     pub bus: SystemBus,
-    pub program: Vec<MoveOp>,
+    pub program: Vec<Bundle>,
     pub pc: usize, // Index in program vector
+
+    /// Transport buses, i.e. the most moves a bundle may issue per cycle.
+    pub buses: usize,
     
     // Phase 6: Stats & Logs
     pub total_steps: usize,
     pub logs: Vec<String>,
+    pub stats: BusStats,
+    /// Set when the machine stops on an error (e.g. a bus conflict); `step` refuses to run.
+    pub fault: Option<String>,
     
     // Phase 7: Console Output
     pub console_sink: std::sync::Arc<std::sync::Mutex<String>>,
+    /// Bytes typed into the console, for a UART without another input source.
+    pub console_input: InputQueue,
+    /// Frame of the video unit, if the system has one.
+    pub display: Option<SharedFrame>,
+    /// Live key events for the keypad, if the system has one.
+    pub keys: Option<KeyQueue>,
+    /// Manifest the system was built from, recorded in snapshots.
+    pub manifest: Option<PathBuf>,
+    /// Undo history; `None` (the default) skips recording.
+    pub journal: Option<Journal>,
+    /// Checked by `run_until`; `run` and `step` ignore them.
+    pub breakpoints: Vec<Breakpoint>,
+    /// Where the last `run_until` stopped, so the next one can step past it.
+    pub last_break: Option<BreakHit>,
+}
+
+impl Default for SystemEmulator {
+    fn default() -> Self {
+        let mut bus = SystemBus::new();
+        // Setup Registers R0-R15
+        for i in 0..16 {
+            bus.add_register(i, 8);
+        }
+        // UART at 0x8000, wired to the console
+        let sink = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+        let input = InputQueue::default();
+        let uart = UartFU::with_sink(sink.clone()).with_source(Box::new(input.clone()));
+        let layout = uart.port_layout();
+        bus.add_unit_with_ports(0x8000, Box::new(uart), layout).expect("empty bus has room for the UART");
+        // Display at 0x8100
+        let frame = SharedFrame::default();
+        let video = VideoFU::new(frame.clone());
+        let layout = video.port_layout();
+        bus.add_unit_with_ports(0x8100, Box::new(video), layout).expect("empty bus has room for the display");
+        // Keypad at 0x8200
+        let keys = KeyQueue::default();
+        let keypad = KeypadFU::new(keys.clone());
+        let layout = keypad.port_layout();
+        bus.add_unit_with_ports(0x8200, Box::new(keypad), layout).expect("empty bus has room for the keypad");
+        for (base, name) in [(0x8000, "UART"), (0x8100, "Display"), (0x8200, "Keypad")] {
+            bus.names.insert(base, name.to_string());
+        }
+
+        Self { console_sink: sink, console_input: input, display: Some(frame), keys: Some(keys), ..Self::new(bus) }
+    }
 }
 
 impl SystemEmulator {
@@ -24,49 +150,416 @@ impl SystemEmulator {
             bus,
             program: Vec::new(),
             pc: 0,
+            buses: 1,
             total_steps: 0,
             logs: Vec::new(),
+            stats: BusStats::default(),
+            fault: None,
             console_sink: std::sync::Arc::new(std::sync::Mutex::new(String::new())),
+            console_input: InputQueue::default(),
+            display: None,
+            keys: None,
+            manifest: None,
+            journal: None,
+            breakpoints: Vec::new(),
+            last_break: None,
         }
     }
 
-    pub fn default() -> Self {
-        let mut bus = SystemBus::new();
-        // Setup Registers R0-R15
-        for i in 0..16 {
-            bus.add_register(i, 8);
+    /// ROM boot stage: copy sector 0 of `image` into RAM at `BOOT_ADDR` (one byte
+    /// per cell), take the moves it holds as the program and set the PC to its entry.
+    pub fn load_firmware(&mut self, image: &mut DiskImage) -> anyhow::Result<()> {
+        let sector = image.read_sector(0)?;
+        for (i, &byte) in sector.iter().enumerate() {
+            self.bus.ram.insert(BOOT_ADDR + i as u16, Array1::from(encode_bits(byte as u32, 8)));
         }
-        // UART at 0x8000
-        bus.add_mmio(0x8000, Box::new(UartFU::new()));
-        
-        Self::new(bus)
-    }
-    
-    pub fn load_firmware(&mut self) {
-        // Init default FUs if needed.
+        self.logs.push(format!("[Boot 2/3] Boot sector: {} bytes -> RAM 0x{:X}-0x{:X}",
+            sector.len(), BOOT_ADDR, BOOT_ADDR as usize + sector.len() - 1));
+
+        let boot = BootSector::decode(&sector)?;
+        self.load_bundles(boot.program);
+        self.check_program()?;
+        self.pc = boot.entry;
+        self.logs.push(format!("[Boot 3/3] Boot program: {} bundles, PC = {}", self.program.len(), self.pc));
+        Ok(())
     }
     
+    /// Load a single-issue program: one move per cycle.
     pub fn load_program(&mut self, prog: Vec<MoveOp>) {
+        self.program = prog.into_iter().map(Bundle::from).collect();
+    }
+
+    pub fn load_bundles(&mut self, prog: Vec<Bundle>) {
         self.program = prog;
     }
+
+    /// Reject bundles wider than the bus count or with conflicting unguarded moves.
+    pub fn check_program(&self) -> anyhow::Result<()> {
+        for (i, bundle) in self.program.iter().enumerate() {
+            if bundle.moves.len() > self.buses {
+                anyhow::bail!("bundle {} issues {} moves on {} bus(es)", i, bundle.moves.len(), self.buses);
+            }
+            if let Some(dest) = bundle.static_conflicts().first() {
+                anyhow::bail!("bundle {} writes 0x{:X} from more than one move", i, dest);
+            }
+        }
+        Ok(())
+    }
     
     pub fn step(&mut self) -> bool {
-        if self.pc >= self.program.len() {
+        let Some(mut journal) = self.journal.take() else { return self.execute_step() };
+        journal.begin(self);
+        let ran = self.execute_step();
+        journal.end(self);
+        self.journal = Some(journal);
+        ran
+    }
+
+    /// Undo up to `n` steps (needs `journal`); returns how many were undone.
+    pub fn step_back(&mut self, n: usize) -> usize {
+        let Some(mut journal) = self.journal.take() else { return 0 };
+        let undone = journal.step_back(self, n);
+        self.journal = Some(journal);
+        undone
+    }
+
+    /// Step backwards until the register at `addr` holds a different value, at
+    /// most `max_steps` steps. Returns the PC of the bundle that wrote it, which
+    /// is where the machine stops: just before that write.
+    pub fn reverse_until_changed(&mut self, addr: u16, max_steps: usize) -> Option<usize> {
+        let value = |sys: &Self| sys.bus.registers.get(&addr).map(|r| r.state.clone());
+        let current = value(self);
+        for _ in 0..max_steps {
+            if self.step_back(1) == 0 {
+                return None;
+            }
+            if value(self) != current {
+                return Some(self.pc);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn execute_step(&mut self) -> bool {
+        self.bus.accesses.clear();
+        if self.fault.is_some() || self.pc >= self.program.len() {
              return false; // Halted
         }
         
-        let op = &self.program[self.pc];
-        let exec_log = self.bus.execute(op);
+        // Interrupts are taken between bundles; entering the handler costs one cycle.
+        if let Some(handler) = self.bus.take_interrupt(self.pc as u16) {
+            self.logs.push(format!("[Step {} | PC {}] Interrupt -> handler at {}", self.total_steps, self.pc, handler));
+            self.stats.record(self.buses, 0, 0);
+            self.bus.tick_all();
+            self.pc = handler as usize;
+            self.total_steps += 1;
+            return true;
+        }
+        if let Some(fault) = self.bus.bus_fault.take() {
+            self.logs.push(format!("[Step {} | PC {}] BUS FAULT: {}", self.total_steps, self.pc, fault));
+            self.fault = Some(fault);
+            return false;
+        }
+
+        let bundle = &self.program[self.pc];
+        let issued = bundle.moves.len();
+
+        // Interlock: the bundle waits for pipelined units, the clock does not.
+        if let Some(reason) = self.bus.stall_reason(&bundle.moves) {
+            self.logs.push(format!("[Step {} | PC {}] Stall ({})", self.total_steps, self.pc, reason));
+            self.stats.record_stall(self.buses);
+            self.bus.tick_all();
+            self.total_steps += 1;
+            return true;
+        }
+
+        // Reads of PC see the next bundle; a write to PC (or CALL/RET) lands in `bus.jump`.
+        self.bus.pc = (self.pc + 1) as u16;
+        self.bus.jump = None;
+        let result = if issued > self.buses {
+            Err(anyhow::anyhow!("bundle issues {} moves on {} bus(es)", issued, self.buses))
+        } else {
+            self.bus.execute_bundle(&bundle.moves)
+        };
+        let outcome = match result {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                self.logs.push(format!("[Step {} | PC {}] FAULT: {}", self.total_steps, self.pc, e));
+                self.fault = Some(e.to_string());
+                return false;
+            }
+        };
         
         // Log the result
         // TODO: Circular buffer optimization if logs get huge
-        self.logs.push(format!("[Step {} | PC {}] {}", self.total_steps, self.pc, exec_log));
+        let exec_log = outcome.logs.join(" || ");
+        if self.buses > 1 {
+            self.logs.push(format!("[Step {} | PC {} | {}/{} buses] {}",
+                self.total_steps, self.pc, outcome.executed, self.buses, exec_log));
+        } else {
+            self.logs.push(format!("[Step {} | PC {}] {}", self.total_steps, self.pc, exec_log));
+        }
+        self.stats.record(self.buses, issued, outcome.executed);
         
         // Clock Tick
         self.bus.tick_all();
-        self.pc += 1; // Simple PC increment
+        let at = self.pc;
+        self.pc = match self.bus.jump.take() {
+            Some(target) => target as usize,
+            None => self.pc + 1,
+        };
         self.total_steps += 1;
-        
+
+        // A unit hit something the machine cannot continue from (e.g. an unresolvable fuzzy address)
+        if let Some(fault) = self.bus.bus_fault.take() {
+            self.logs.push(format!("[Step {} | PC {}] BUS FAULT: {}", self.total_steps - 1, at, fault));
+            self.fault = Some(fault);
+            return false;
+        }
+
         true
     }
+
+    /// Capture the machine state. Units are identified by base address and
+    /// must be rebuilt (from `manifest`) before `restore`.
+    pub fn snapshot(&self, weights: WeightMode) -> Snapshot {
+        let bus = &self.bus;
+        let units = bus.units.iter().chain(&bus.mmio).map(|(&base, unit)| {
+            let net = unit.weights();
+            let snap = UnitSnapshot {
+                state: unit.save_state(),
+                weights: net.filter(|_| weights == WeightMode::Inline).cloned(),
+                weights_hash: net.filter(|_| weights == WeightMode::Reference).map(content_hash),
+            };
+            (base, snap)
+        });
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            manifest: self.manifest.clone(),
+            program: self.program.clone(),
+            pc: self.pc,
+            total_steps: self.total_steps,
+            fault: self.fault.clone(),
+            stats: self.stats.clone(),
+            console: self.console_sink.lock().map(|c| c.clone()).unwrap_or_default(),
+            registers: bus.registers.iter().map(|(&a, r)| (a, r.state.clone())).collect(),
+            ram: bus.ram.iter().map(|(&a, v)| (a, v.clone())).collect(),
+            port_latches: bus.port_latches.iter().map(|(&a, v)| (a, v.clone())).collect(),
+            fu_io_cache: bus.fu_io_cache.iter().map(|(&a, io)| (a, io.clone())).collect(),
+            pipelines: bus.pipelines.iter().map(|(&a, p)| (a, p.clone())).collect(),
+            stale_reads: bus.stale_reads,
+            early_issues: bus.early_issues,
+            assoc_stats: bus.assoc_stats,
+            bus_fault: bus.bus_fault.clone(),
+            units: units.collect(),
+        }
+    }
+
+    /// Put a snapshot back. The system must have the same units at the same
+    /// addresses; weights stored by reference must match what was loaded.
+    pub fn restore(&mut self, snapshot: &Snapshot) -> anyhow::Result<()> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            bail!("snapshot version {} is not supported (expected {})", snapshot.version, SNAPSHOT_VERSION);
+        }
+        let bus = &mut self.bus;
+        let mut bases: Vec<u16> = bus.units.keys().chain(bus.mmio.keys()).copied().collect();
+        bases.sort_unstable();
+        if !bases.iter().eq(snapshot.units.keys()) {
+            bail!("snapshot units {:X?} do not match this system's {:X?}", snapshot.units.keys().collect::<Vec<_>>(), bases);
+        }
+
+        for (&base, saved) in &snapshot.units {
+            let unit = match bus.units.get_mut(&base) {
+                Some(unit) => unit,
+                None => bus.mmio.get_mut(&base).expect("bases checked above"),
+            };
+            if let Some(weights) = &saved.weights {
+                let net = unit.weights_mut().with_context(|| format!("unit 0x{:X} has no weights to restore", base))?;
+                *net = weights.clone();
+            } else if let Some(hash) = &saved.weights_hash {
+                let found = unit.weights().map(content_hash);
+                if found.as_ref() != Some(hash) {
+                    bail!("unit 0x{:X}: loaded weights {:?} differ from the snapshot's {}", base, found, hash);
+                }
+            }
+            if let Some(state) = &saved.state {
+                unit.load_state(state.clone()).with_context(|| format!("restoring unit 0x{:X}", base))?;
+            }
+        }
+
+        bus.registers = snapshot.registers.iter()
+            .map(|(&a, state)| (a, NeuralRegister { state: state.clone(), width: state.len() }))
+            .collect();
+        bus.ram = snapshot.ram.iter().map(|(&a, v)| (a, v.clone())).collect();
+        bus.port_latches = snapshot.port_latches.iter().map(|(&a, v)| (a, v.clone())).collect();
+        bus.fu_io_cache = snapshot.fu_io_cache.iter().map(|(&a, io)| (a, io.clone())).collect();
+        bus.pipelines = snapshot.pipelines.iter().map(|(&a, p)| (a, p.clone())).collect();
+        bus.stale_reads = snapshot.stale_reads;
+        bus.early_issues = snapshot.early_issues;
+        bus.assoc_stats = snapshot.assoc_stats;
+        bus.bus_fault = snapshot.bus_fault.clone();
+        bus.jump = None;
+
+        self.program = snapshot.program.clone();
+        self.pc = snapshot.pc;
+        self.total_steps = snapshot.total_steps;
+        self.fault = snapshot.fault.clone();
+        self.stats = snapshot.stats.clone();
+        if let Ok(mut console) = self.console_sink.lock() {
+            *console = snapshot.console.clone();
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.clear();
+        }
+        self.logs.push(format!("[Step {} | PC {}] Restored snapshot", self.total_steps, self.pc));
+        Ok(())
+    }
+
+    /// Add a PC breakpoint at `pc`, or remove it if there is one.
+    pub fn toggle_breakpoint(&mut self, pc: usize) {
+        match self.breakpoints.iter().position(|b| *b == Breakpoint::Pc(pc)) {
+            Some(i) => {
+                self.breakpoints.remove(i);
+            }
+            None => self.breakpoints.push(Breakpoint::Pc(pc)),
+        }
+    }
+
+    /// Step until the program halts, faults or `max_cycles` pass.
+    pub fn run(&mut self, max_cycles: usize) -> RunReport {
+        self.run_with(max_cycles, false).0
+    }
+
+    /// Like `run`, but also stops on the first breakpoint hit, recorded in `last_break`.
+    ///
+    /// Value and drift conditions stop when they become true, not on every step
+    /// they stay true; if several breakpoints hit on one step, the first listed wins.
+    /// Calling again after a PC breakpoint runs the bundle it stopped before.
+    pub fn run_until(&mut self, max_cycles: usize) -> (RunReport, Option<BreakHit>) {
+        self.run_with(max_cycles, true)
+    }
+
+    fn run_with(&mut self, max_cycles: usize, breakpoints: bool) -> (RunReport, Option<BreakHit>) {
+        let (stats, stale, early) = (self.stats.clone(), self.bus.stale_reads, self.bus.early_issues);
+        let mut halted = false;
+        let mut hit = None;
+        // Resuming from a PC breakpoint: don't stop on it again before its bundle runs
+        let mut resumed_at = self.last_break.take()
+            .filter(|h| h.step == self.total_steps && matches!(self.breakpoints.get(h.index), Some(Breakpoint::Pc(pc)) if *pc == self.pc))
+            .map(|h| h.index);
+        for _ in 0..max_cycles {
+            let found = if breakpoints {
+                self.breakpoints.iter().enumerate()
+                    .filter(|(i, _)| Some(*i) != resumed_at)
+                    .find_map(|(i, b)| Some((i, b.before(self)?)))
+            } else {
+                None
+            };
+            resumed_at = None;
+            if let Some((index, reason)) = found {
+                hit = Some((index, reason));
+                break;
+            }
+            let was_true: Vec<bool> = if breakpoints {
+                self.breakpoints.iter().map(|b| !matches!(b, Breakpoint::Watch { .. }) && b.after(self).is_some()).collect()
+            } else {
+                Vec::new()
+            };
+            if !self.step() {
+                halted = true;
+                break;
+            }
+            let found = was_true.iter().enumerate()
+                .filter(|(_, was)| !**was)
+                .find_map(|(i, _)| Some((i, self.breakpoints[i].after(self)?)));
+            if let Some((index, reason)) = found {
+                hit = Some((index, reason));
+                break;
+            }
+        }
+        let hit = hit.map(|(index, reason)| BreakHit { index, step: self.total_steps, pc: self.pc, reason });
+        if let Some(h) = &hit {
+            self.logs.push(format!("[Step {} | PC {}] Break #{}: {}", h.step, h.pc, h.index, h.reason));
+        }
+        self.last_break = hit.clone();
+        let cycles = self.stats.cycles - stats.cycles;
+        let stalls = self.stats.stalls - stats.stalls;
+        let report = RunReport {
+            cycles,
+            bundles: cycles - stalls,
+            stalls,
+            moves: self.stats.moves - stats.moves,
+            stale_reads: self.bus.stale_reads - stale,
+            early_issues: self.bus.early_issues - early,
+            halted,
+        };
+        (report, hit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multi_issue_stats_and_width_fault() {
+        let mut sys = SystemEmulator { buses: 2, ..Default::default() };
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        sys.load_bundles(vec![
+            Bundle { moves: vec![mv(0, 1), mv(1, 0)] },
+            Bundle::from(mv(2, 3)),
+            Bundle { moves: vec![mv(0, 1), mv(1, 2), mv(2, 3)] },
+        ]);
+        assert!(sys.check_program().is_err());
+
+        while sys.step() {}
+        assert_eq!(sys.stats.cycles, 2);
+        assert_eq!(sys.stats.histogram, vec![0, 1, 1]);
+        assert_eq!(sys.stats.utilization(), 0.75);
+        assert!(sys.fault.as_deref().is_some_and(|f| f.contains("3 moves")));
+        assert_eq!(sys.pc, 2);
+    }
+
+    #[test]
+    fn test_snapshot_restore_resumes_identically() {
+        use crate::fu::{BaseFU, ProgramCounterFU};
+        use crate::register::NeuralRegister;
+
+        let build = || {
+            let mut sys = SystemEmulator::default();
+            sys.bus.add_unit(0x1000, Box::new(BaseFU::create_random(8, 8, 8)));
+            sys.bus.add_unit(0x1100, Box::new(ProgramCounterFU::new()));
+            sys.bus.registers.insert(0, NeuralRegister::from_symbolic(8, b'A' as u32));
+            let mv = |src, dest| MoveOp { src, dest, guard: None };
+            sys.load_program(vec![
+                mv(0, 0x8000), mv(0, 0x1000), mv(0, 0x2000), mv(0x2000, 1), // UART, FU, RAM, register
+                mv(1, 0x8000), mv(0, 0x1000), mv(0x2000, 2), mv(2, 0x8000),
+            ]);
+            sys
+        };
+        let mut original = build();
+        original.bus.units.get_mut(&0x1000).unwrap().perturb(0.3);
+        original.run(4);
+        let json = serde_json::to_string(&original.snapshot(WeightMode::Inline)).unwrap();
+        let snapshot: Snapshot = serde_json::from_str(&json).unwrap();
+        original.run(10);
+
+        let mut resumed = build();
+        resumed.restore(&snapshot).unwrap();
+        assert_eq!(resumed.console_sink.lock().unwrap().as_str(), "A");
+        resumed.run(10);
+        assert_eq!(resumed.pc, original.pc);
+        assert_eq!(resumed.total_steps, original.total_steps);
+        assert_eq!(resumed.console_sink.lock().unwrap().as_str(), "AAA");
+        assert_eq!(resumed.bus.registers[&2].state, original.bus.registers[&2].state);
+        assert_eq!(resumed.bus.fu_io_cache[&0x1000], original.bus.fu_io_cache[&0x1000]);
+        assert_eq!(resumed.bus.units[&0x1100].save_state(), original.bus.units[&0x1100].save_state());
+
+        // By reference, a system with different weights is refused; one without the unit too
+        let by_ref = original.snapshot(WeightMode::Reference);
+        assert!(by_ref.units[&0x1000].weights.is_none());
+        assert!(build().restore(&by_ref).is_err());
+        assert!(original.restore(&by_ref).is_ok());
+        assert!(SystemEmulator::default().restore(&snapshot).is_err());
+    }
 }
@@ -0,0 +1,96 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use neuro_symbolic_emulator::bus::MoveOp;
+use neuro_symbolic_emulator::gdb;
+use neuro_symbolic_emulator::system::SystemEmulator;
+
+/// Send one packet and return the stub's reply, acknowledging both ways.
+fn packet(stream: &mut TcpStream, payload: &str) -> String {
+    let checksum = payload.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+    write!(stream, "${}#{:02x}", payload, checksum).unwrap();
+    let mut byte = [0u8];
+    stream.read_exact(&mut byte).unwrap();
+    assert_eq!(byte[0], b'+', "stub rejected {}", payload);
+
+    stream.read_exact(&mut byte).unwrap();
+    assert_eq!(byte[0], b'$');
+    let mut reply = Vec::new();
+    loop {
+        stream.read_exact(&mut byte).unwrap();
+        if byte[0] == b'#' {
+            break;
+        }
+        reply.push(byte[0]);
+    }
+    let mut checksum = [0u8; 2];
+    stream.read_exact(&mut checksum).unwrap();
+    let sum = reply.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    assert_eq!(std::str::from_utf8(&checksum).unwrap(), format!("{:02x}", sum));
+    stream.write_all(b"+").unwrap();
+    String::from_utf8(reply).unwrap()
+}
+
+/// Register `n` out of a `g` reply, as the little-endian integer GDB would show.
+fn register(block: &str, n: usize) -> u32 {
+    u32::from_str_radix(&block[n * 8..n * 8 + 8], 16).unwrap().swap_bytes()
+}
+
+#[test]
+fn test_gdb_client_steps_breaks_and_edits_memory() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let mut sys = SystemEmulator::default();
+        sys.bus.registers.remove(&9);
+        let mv = |src, dest| MoveOp { src, dest, guard: None };
+        sys.load_program(vec![mv(0, 0x2002), mv(0x2000, 1), mv(1, 2), mv(2, 3), mv(3, 5), mv(5, 6), mv(6, 7)]);
+        gdb::serve(&mut sys, &listener).unwrap();
+        sys
+    });
+
+    let mut gdb = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    assert!(packet(&mut gdb, "qSupported:multiprocess+").contains("qXfer:features:read+"));
+    assert!(packet(&mut gdb, "qXfer:features:read:target.xml:0,ffff").contains("name=\"pc\""));
+    assert_eq!(packet(&mut gdb, "?"), "S05");
+
+    // Registers and RAM from the debugger
+    assert_eq!(packet(&mut gdb, "P0=2a000000"), "OK");
+    assert_eq!(packet(&mut gdb, "p0"), "2a000000");
+    assert_eq!(packet(&mut gdb, "p11"), "E01"); // Past the PC
+    assert_eq!(packet(&mut gdb, "P9=01000000"), "E01"); // Not on this machine
+    assert_eq!(packet(&mut gdb, "M2000,1:41"), "OK");
+    assert_eq!(packet(&mut gdb, "m2000,1"), "41");
+    assert_eq!(packet(&mut gdb, "M0,1:41"), "E01"); // Registers are written with P, not M
+    assert_eq!(packet(&mut gdb, "mffffffff,2"), "E01");
+    assert_eq!(packet(&mut gdb, "M7fff,ffffffff:41"), "E01");
+    assert_eq!(packet(&mut gdb, "czz"), "E01"); // Malformed packets don't end the session
+    assert_eq!(packet(&mut gdb, "s-1"), "E01");
+
+    // Breakpoint on bundle 3: the stores and loads before it have happened
+    assert_eq!(packet(&mut gdb, "Z0,3,1"), "OK");
+    assert_eq!(packet(&mut gdb, "c"), "S05");
+    let regs = packet(&mut gdb, "g");
+    assert_eq!(regs.len(), gdb::GDB_REGISTERS * 8);
+    assert_eq!((register(&regs, 16), register(&regs, 1)), (3, 0x41));
+    assert_eq!(packet(&mut gdb, &format!("G{}", regs)), "OK");
+    assert_eq!(packet(&mut gdb, "m2002,1"), "2a");
+
+    // Write watchpoint on R5, then a single step
+    assert_eq!(packet(&mut gdb, "Z2,5,1"), "OK");
+    assert_eq!(packet(&mut gdb, "c"), "T05watch:5;");
+    assert_eq!(packet(&mut gdb, "p10"), "05000000");
+    assert_eq!(packet(&mut gdb, "s"), "S05");
+    assert_eq!(packet(&mut gdb, "p10"), "06000000");
+
+    // Without breakpoints the program runs to its end
+    assert_eq!(packet(&mut gdb, "z2,5,1"), "OK");
+    assert_eq!(packet(&mut gdb, "z0,3,1"), "OK");
+    assert_eq!(packet(&mut gdb, "c"), "W00");
+    assert_eq!(packet(&mut gdb, "D"), "OK");
+
+    let sys = server.join().unwrap();
+    assert!(sys.breakpoints.is_empty());
+    assert!(!sys.bus.registers.contains_key(&9));
+    assert_eq!(sys.bus.registers[&7].to_symbolic(), 0x41);
+}